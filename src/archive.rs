@@ -0,0 +1,382 @@
+use crate::inflate::inflate;
+use crate::utils::{read_u16, read_u32, read_u64};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const LOCAL_HEADER_SIG: u32 = 0x0403_4B50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4B50;
+const EOCD_SIG: u32 = 0x0605_4B50;
+const EOCD64_SIG: u32 = 0x0606_4B50;
+const EOCD64_LOCATOR_SIG: u32 = 0x0706_4B50;
+const EOCD_SIZE: usize = 22;
+const MAX_COMMENT: usize = 0xFFFF;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+const ARCHIVE_EXTENSIONS: [&str; 4] = ["apk", "xapk", "ipa", "zip"];
+
+// Preferred order when the archive ships several ABIs and only one is wanted.
+const ABI_PREFERENCE: [&str; 4] = ["arm64-v8a", "armeabi-v7a", "x86_64", "x86"];
+
+pub(crate) struct ZipEntry {
+    pub name: String,
+    pub method: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub local_header_offset: u64,
+}
+
+pub(crate) struct ZipArchive<R> {
+    reader: R,
+    pub entries: Vec<ZipEntry>,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    pub fn open(mut reader: R) -> Result<Self, String> {
+        let len = reader.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        let tail_len = (len as usize).min(EOCD_SIZE + MAX_COMMENT);
+        let tail = read_at(&mut reader, len - tail_len as u64, tail_len)?;
+
+        let eocd = (0..=tail_len.saturating_sub(EOCD_SIZE))
+            .rev()
+            .find(|&i| read_u32(&tail, i) == Ok(EOCD_SIG))
+            .ok_or("Not a ZIP archive: end of central directory not found")?;
+        let eocd_pos = len - (tail_len - eocd) as u64;
+
+        let mut count = read_u16(&tail, eocd + 10)? as u64;
+        let mut cd_size = read_u32(&tail, eocd + 12)? as u64;
+        let mut cd_offset = read_u32(&tail, eocd + 16)? as u64;
+
+        if (count == 0xFFFF || cd_size == 0xFFFF_FFFF || cd_offset == 0xFFFF_FFFF) && eocd_pos >= 20
+        {
+            let locator = read_at(&mut reader, eocd_pos - 20, 20)?;
+            if read_u32(&locator, 0)? == EOCD64_LOCATOR_SIG {
+                let eocd64 = read_at(&mut reader, read_u64(&locator, 8)?, 56)?;
+                if read_u32(&eocd64, 0)? != EOCD64_SIG {
+                    return Err("Corrupt ZIP64 end of central directory".to_string());
+                }
+                count = read_u64(&eocd64, 32)?;
+                cd_size = read_u64(&eocd64, 40)?;
+                cd_offset = read_u64(&eocd64, 48)?;
+            }
+        }
+
+        let cd = read_at(&mut reader, cd_offset, cd_size as usize)?;
+        let mut entries = Vec::with_capacity(count.min(0x10000) as usize);
+        let mut pos = 0;
+        for _ in 0..count {
+            if read_u32(&cd, pos)? != CENTRAL_HEADER_SIG {
+                return Err("Corrupt ZIP central directory".to_string());
+            }
+            let name_len = read_u16(&cd, pos + 28)? as usize;
+            let extra_len = read_u16(&cd, pos + 30)? as usize;
+            let comment_len = read_u16(&cd, pos + 32)? as usize;
+            let name_bytes = cd
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or("ZIP entry name out of bounds")?;
+            let extra = cd
+                .get(pos + 46 + name_len..pos + 46 + name_len + extra_len)
+                .ok_or("ZIP extra field out of bounds")?;
+
+            let mut entry = ZipEntry {
+                name: String::from_utf8_lossy(name_bytes).into_owned(),
+                method: read_u16(&cd, pos + 10)?,
+                crc32: read_u32(&cd, pos + 16)?,
+                compressed_size: read_u32(&cd, pos + 20)? as u64,
+                uncompressed_size: read_u32(&cd, pos + 24)? as u64,
+                local_header_offset: read_u32(&cd, pos + 42)? as u64,
+            };
+            apply_zip64_extra(&mut entry, extra)?;
+            entries.push(entry);
+
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { reader, entries })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    pub fn read(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let entry = self
+            .find(name)
+            .ok_or_else(|| format!("Entry not found in archive: {}", name))?;
+        let (method, crc, compressed_size, size, offset) = (
+            entry.method,
+            entry.crc32,
+            entry.compressed_size as usize,
+            entry.uncompressed_size as usize,
+            entry.local_header_offset,
+        );
+
+        let local = read_at(&mut self.reader, offset, 30)?;
+        if read_u32(&local, 0)? != LOCAL_HEADER_SIG {
+            return Err(format!("Corrupt local header for {}", name));
+        }
+        let data_offset = offset + 30 + read_u16(&local, 26)? as u64 + read_u16(&local, 28)? as u64;
+        let raw = read_at(&mut self.reader, data_offset, compressed_size)?;
+
+        let data = match method {
+            METHOD_STORED => raw,
+            METHOD_DEFLATE => inflate(&raw, size).map_err(|e| format!("{}: {}", name, e))?,
+            other => {
+                return Err(format!(
+                    "{}: unsupported compression method {}",
+                    name, other
+                ));
+            }
+        };
+        if data.len() != size || crc32(&data) != crc {
+            return Err(format!("{}: checksum mismatch after extraction", name));
+        }
+        Ok(data)
+    }
+}
+
+// Lengths come from the archive's headers, so they are checked against what
+// is left of the file before anything is allocated for them.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+    let file_len = reader.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    if len as u64 > file_len.saturating_sub(offset) {
+        return Err(format!(
+            "Archive read out of bounds: 0x{:X} bytes at 0x{:X} in a 0x{:X} byte file",
+            len, offset, file_len
+        ));
+    }
+    reader
+        .seek(SeekFrom::Start(offset))
+        .map_err(|e| e.to_string())?;
+    let mut buf = vec![0u8; len];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|e| format!("Archive read failed at 0x{:X}: {}", offset, e))?;
+    Ok(buf)
+}
+
+// ZIP64 stores only the fields whose 32-bit slot is saturated, in this order.
+fn apply_zip64_extra(entry: &mut ZipEntry, extra: &[u8]) -> Result<(), String> {
+    let mut pos = 0;
+    while pos + 4 <= extra.len() {
+        let id = read_u16(extra, pos)?;
+        let size = read_u16(extra, pos + 2)? as usize;
+        if id == 0x0001 {
+            let mut field = pos + 4;
+            for value in [
+                &mut entry.uncompressed_size,
+                &mut entry.compressed_size,
+                &mut entry.local_header_offset,
+            ] {
+                if *value == 0xFFFF_FFFF && field + 8 <= pos + 4 + size {
+                    *value = read_u64(extra, field)?;
+                    field += 8;
+                }
+            }
+        }
+        pos += 4 + size;
+    }
+    Ok(())
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, slot) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *slot = c;
+    }
+    !data.iter().fold(!0u32, |crc, &b| {
+        table[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// A native library in an archive.
+pub struct Target {
+    /// The ABI, or `ios` or `windows`, naming the output file.
+    pub label: String,
+    /// The split APK holding it, when nested in an XAPK.
+    pub container: Option<String>,
+    /// Its path in the archive, or in the container.
+    pub entry: String,
+}
+
+impl Target {
+    /// The entry's path, behind its container's and a `!` when nested.
+    pub fn display_path(&self) -> String {
+        match &self.container {
+            Some(container) => format!("{}!{}", container, self.entry),
+            None => self.entry.clone(),
+        }
+    }
+}
+
+/// Whether `path` is a ZIP-based package, by its extension or magic.
+pub fn is_archive<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let by_extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ARCHIVE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    if by_extension {
+        return true;
+    }
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| u32::from_le_bytes(magic) == LOCAL_HEADER_SIG)
+}
+
+// Native libraries the extractor knows how to read, labelled by platform so
+// several of them can be written side by side.
+fn target_label(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name.split('/').collect();
+    match parts.as_slice() {
+        ["lib", abi, "libil2cpp.so"] => Some(abi.to_string()),
+        [
+            "Payload",
+            app,
+            "Frameworks",
+            "UnityFramework.framework",
+            "UnityFramework",
+        ] if app.ends_with(".app") => Some("ios".to_string()),
+        [.., "GameAssembly.dll"] => Some("windows".to_string()),
+        _ => None,
+    }
+}
+
+/// Every native library in the archive at `path`, split APKs included,
+/// in ABI preference order.
+pub fn list_targets<P: AsRef<Path>>(path: P) -> Result<Vec<Target>, String> {
+    let file = File::open(path.as_ref()).map_err(|e| format!("File not found: {}", e))?;
+    let mut archive = ZipArchive::open(file)?;
+
+    let mut targets: Vec<Target> = archive
+        .entries
+        .iter()
+        .filter_map(|e| {
+            target_label(&e.name).map(|label| Target {
+                label,
+                container: None,
+                entry: e.name.clone(),
+            })
+        })
+        .collect();
+
+    // XAPK bundles keep the native libraries in split APKs
+    // (config.arm64_v8a.apk and friends), so look one level down. A nested
+    // entry that does not open as a ZIP simply holds no targets.
+    let nested: Vec<String> = archive
+        .entries
+        .iter()
+        .filter(|e| e.name.to_ascii_lowercase().ends_with(".apk"))
+        .map(|e| e.name.clone())
+        .collect();
+    for container in nested {
+        let Ok(inner) = archive
+            .read(&container)
+            .and_then(|data| ZipArchive::open(Cursor::new(data)))
+        else {
+            continue;
+        };
+        targets.extend(inner.entries.iter().filter_map(|e| {
+            target_label(&e.name).map(|label| Target {
+                label,
+                container: Some(container.clone()),
+                entry: e.name.clone(),
+            })
+        }));
+    }
+
+    targets.sort_by_key(|t| {
+        ABI_PREFERENCE
+            .iter()
+            .position(|&abi| abi == t.label)
+            .unwrap_or(ABI_PREFERENCE.len())
+    });
+    Ok(targets)
+}
+
+/// Reads the library `target` out of the archive at `path`.
+pub fn load_target<P: AsRef<Path>>(path: P, target: &Target) -> Result<Vec<u8>, String> {
+    let file = File::open(path.as_ref()).map_err(|e| format!("File not found: {}", e))?;
+    let mut archive = ZipArchive::open(file)?;
+    match &target.container {
+        Some(container) => {
+            let mut inner = ZipArchive::open(Cursor::new(archive.read(container)?))?;
+            inner.read(&target.entry)
+        }
+        None => archive.read(&target.entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A single-entry archive whose central directory claims the given sizes
+    // for `data`, stored or deflated as `method` says.
+    fn archive(data: &[u8], method: u16, compressed_size: u32, size: u32) -> Vec<u8> {
+        let name = b"lib/arm64-v8a/libil2cpp.so";
+        let mut out = Vec::new();
+        out.extend(LOCAL_HEADER_SIG.to_le_bytes());
+        out.extend([0; 22]);
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend([0; 2]);
+        out.extend(name);
+        out.extend(data);
+
+        let cd_offset = out.len() as u32;
+        out.extend(CENTRAL_HEADER_SIG.to_le_bytes());
+        out.extend([0; 6]);
+        out.extend(method.to_le_bytes());
+        out.extend([0; 4]);
+        out.extend(crc32(data).to_le_bytes());
+        out.extend(compressed_size.to_le_bytes());
+        out.extend(size.to_le_bytes());
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend([0; 12]);
+        out.extend(0u32.to_le_bytes());
+        out.extend(name);
+        let cd_size = out.len() as u32 - cd_offset;
+
+        out.extend(EOCD_SIG.to_le_bytes());
+        out.extend([0; 6]);
+        out.extend(1u16.to_le_bytes());
+        out.extend(cd_size.to_le_bytes());
+        out.extend(cd_offset.to_le_bytes());
+        out.extend([0; 2]);
+        out
+    }
+
+    #[test]
+    fn reads_a_stored_entry() {
+        let data = archive(b"ELF", METHOD_STORED, 3, 3);
+        let mut zip = ZipArchive::open(Cursor::new(data)).unwrap();
+        assert_eq!(zip.read("lib/arm64-v8a/libil2cpp.so").unwrap(), b"ELF");
+    }
+
+    #[test]
+    fn oversized_compressed_size_is_an_error() {
+        let data = archive(b"ELF", METHOD_STORED, 0xFFFF_FFF0, 3);
+        let mut zip = ZipArchive::open(Cursor::new(data)).unwrap();
+        assert!(zip.read("lib/arm64-v8a/libil2cpp.so").is_err());
+    }
+
+    #[test]
+    fn oversized_uncompressed_size_is_an_error() {
+        let stored_block = [0x01, 0x03, 0x00, 0xFC, 0xFF, b'E', b'L', b'F'];
+        let data = archive(&stored_block, METHOD_DEFLATE, 8, 0xFFFF_FFF0);
+        let mut zip = ZipArchive::open(Cursor::new(data)).unwrap();
+        assert!(zip.read("lib/arm64-v8a/libil2cpp.so").is_err());
+    }
+}
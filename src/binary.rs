@@ -0,0 +1,245 @@
+use crate::elf::{self, ElfFile};
+use crate::macho::MachOFile;
+use crate::pe::PeFile;
+use crate::utils::{read_u32, read_u64};
+use std::collections::HashMap;
+
+/// The container format of a binary.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A Windows `GameAssembly.dll`.
+    Pe,
+    /// An Android `libil2cpp.so`, 32 or 64 bit.
+    Elf,
+    /// An iOS or macOS `UnityFramework`, thin or fat.
+    MachO,
+}
+
+impl Format {
+    /// The format's usual name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Pe => "PE",
+            Format::Elf => "ELF",
+            Format::MachO => "Mach-O",
+        }
+    }
+
+    /// PE sections are addressed relative to the image base, ELF and Mach-O
+    /// sections by their link-time virtual address.
+    pub fn address_label(&self) -> &'static str {
+        match self {
+            Format::Pe => "RVA",
+            Format::Elf | Format::MachO => "VA",
+        }
+    }
+}
+
+pub struct Section {
+    pub name: String,
+    pub address: u64,
+    pub file_offset: usize,
+    pub size: usize,
+    pub executable: bool,
+    pub searchable: bool,
+    pub slice: Option<String>,
+    pub pointer_size: usize,
+}
+
+impl Section {
+    pub fn raw<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let start = self.file_offset.min(data.len());
+        let end = start.saturating_add(self.size).min(data.len());
+        &data[start..end]
+    }
+}
+
+pub struct Binary {
+    pub format: Format,
+    pub arch: String,
+    pub sections: Vec<Section>,
+    /// What pointers stored in the file are relative to: the preferred image
+    /// base for PE, whose sections are addressed by RVA, and 0 otherwise.
+    pub image_base: u64,
+    /// Pointer values the loader writes, keyed by file offset.
+    pub relocations: HashMap<usize, u64>,
+    /// Why pointers stored in the file cannot be trusted, when the loader
+    /// rewrites them in a way that is not decoded.
+    pub pointer_error: Option<String>,
+}
+
+impl Binary {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if ElfFile::is_elf(data) {
+            return Self::from_elf(&ElfFile::parse(data)?);
+        }
+        if MachOFile::is_macho(data) {
+            return Self::from_macho(&MachOFile::parse(data)?);
+        }
+        if data.starts_with(b"MZ") {
+            return Self::from_pe(&PeFile::parse(data)?, data.len());
+        }
+        Err("Unrecognized binary format (expected PE, ELF or Mach-O)".to_string())
+    }
+
+    pub fn searchable_sections(&self) -> impl Iterator<Item = &Section> {
+        self.sections.iter().filter(|s| s.searchable)
+    }
+
+    /// Fat Mach-O sections carry their slice name, which is the architecture.
+    pub fn section_arch<'a>(&'a self, section: &'a Section) -> &'a str {
+        section.slice.as_deref().unwrap_or(&self.arch)
+    }
+
+    pub fn offset_for_address(&self, address: u64, slice: Option<&str>) -> Option<usize> {
+        self.sections
+            .iter()
+            .filter(|s| s.slice.as_deref() == slice)
+            .find(|s| address >= s.address && address - s.address < s.size as u64)
+            .map(|s| s.file_offset + (address - s.address) as usize)
+    }
+
+    pub fn offset_for_pointer(&self, pointer: u64, slice: Option<&str>) -> Option<usize> {
+        self.offset_for_address(pointer.checked_sub(self.image_base)?, slice)
+    }
+
+    /// Little-endian, as every IL2CPP target is.
+    pub fn read_pointer(&self, data: &[u8], offset: usize, pointer_size: usize) -> Option<u64> {
+        if let Some(&value) = self.relocations.get(&offset) {
+            return Some(value);
+        }
+        match pointer_size {
+            8 => read_u64(data, offset).ok(),
+            _ => read_u32(data, offset).ok().map(u64::from),
+        }
+    }
+
+    fn from_pe(pe: &PeFile, file_len: usize) -> Result<Self, String> {
+        let sections = pe
+            .sections
+            .iter()
+            .map(|s| {
+                let (start, end) = s.raw_range(file_len);
+                Section {
+                    name: s.name.clone(),
+                    address: s.virtual_address as u64,
+                    file_offset: start,
+                    size: end - start,
+                    executable: s.is_code(),
+                    searchable: pe.is_searchable(s),
+                    slice: None,
+                    pointer_size: if pe.is_64 { 8 } else { 4 },
+                }
+            })
+            .collect();
+        Ok(Self {
+            format: Format::Pe,
+            arch: pe.machine_name().to_string(),
+            sections,
+            image_base: pe.image_base,
+            relocations: HashMap::new(),
+            pointer_error: None,
+        })
+    }
+
+    fn from_elf(elf: &ElfFile) -> Result<Self, String> {
+        let pointer_size = if elf.is_64 { 8 } else { 4 };
+        let mut sections: Vec<Section> = elf
+            .sections
+            .iter()
+            .filter(|s| s.flags & elf::SHF_ALLOC != 0 && s.sh_type != elf::SHT_NOBITS)
+            .map(|s| Section {
+                name: s.name.clone(),
+                address: s.addr,
+                file_offset: s.offset as usize,
+                size: s.size as usize,
+                executable: s.is_code(),
+                searchable: s.is_searchable(),
+                slice: None,
+                pointer_size,
+            })
+            .collect();
+
+        if sections.is_empty() {
+            sections = elf
+                .segments
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.p_type == elf::PT_LOAD)
+                .map(|(i, s)| Section {
+                    name: format!("LOAD[{}]", i),
+                    address: s.vaddr,
+                    file_offset: s.offset as usize,
+                    size: s.filesz as usize,
+                    executable: s.flags & elf::PF_X != 0,
+                    searchable: s.flags & elf::PF_X == 0,
+                    slice: None,
+                    pointer_size,
+                })
+                .collect();
+        }
+
+        if sections.is_empty() {
+            return Err("ELF file has no loadable sections or segments".to_string());
+        }
+        Ok(Self {
+            format: Format::Elf,
+            arch: elf.machine_name().to_string(),
+            sections,
+            image_base: 0,
+            relocations: elf
+                .relocations
+                .iter()
+                .filter_map(|&(address, value)| {
+                    Some((elf.offset_for_vaddr(address)? as usize, value))
+                })
+                .collect(),
+            pointer_error: None,
+        })
+    }
+
+    fn from_macho(macho: &MachOFile) -> Result<Self, String> {
+        let mut sections = Vec::new();
+        let mut relocations = HashMap::new();
+        let mut pointer_error = None;
+        for slice in &macho.slices {
+            match &slice.fixups {
+                Ok(fixups) => relocations.extend(
+                    fixups
+                        .iter()
+                        .map(|&(offset, value)| (slice.offset + offset, value)),
+                ),
+                Err(e) => {
+                    pointer_error.get_or_insert_with(|| format!("{}: {}", slice.arch_name(), e));
+                }
+            }
+            for s in &slice.sections {
+                sections.push(Section {
+                    name: format!("{},{}", s.segname, s.sectname),
+                    address: s.addr,
+                    file_offset: slice.offset + s.offset as usize,
+                    size: if s.is_zerofill() { 0 } else { s.size as usize },
+                    executable: s.is_code(),
+                    searchable: s.is_searchable(),
+                    slice: macho.is_fat.then(|| slice.arch_name().to_string()),
+                    pointer_size: if slice.is_64 { 8 } else { 4 },
+                });
+            }
+        }
+
+        let archs: Vec<&str> = macho.slices.iter().map(|s| s.arch_name()).collect();
+        let arch = if macho.is_fat {
+            format!("universal: {}", archs.join(", "))
+        } else {
+            archs.concat()
+        };
+        Ok(Self {
+            format: Format::MachO,
+            arch,
+            sections,
+            image_base: 0,
+            relocations,
+            pointer_error,
+        })
+    }
+}
@@ -0,0 +1,211 @@
+use crate::hgxxtea::{DEFAULT_KEY, KeySchedule, Rounds, Xxtea, XxteaParams};
+use std::sync::Arc;
+
+const DEFAULT_CIPHER: &str = "xxtea";
+
+/// A way the metadata blob is encrypted.
+pub trait MetadataCipher: Send + Sync {
+    /// The name `--cipher` knows it by.
+    fn name(&self) -> &str;
+    /// Decrypts `data` with `key`.
+    fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, String>;
+    /// Encrypts `data` with `key`, the inverse of `decrypt`.
+    fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// For titles that ship the blob behind the marker without encrypting it.
+pub struct Plain;
+
+impl MetadataCipher for Plain {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    fn decrypt(&self, data: &[u8], _key: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(data.to_vec())
+    }
+
+    fn encrypt(&self, data: &[u8], _key: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(data.to_vec())
+    }
+}
+
+/// The cipher and key to decrypt and re-encrypt the blob with. The default
+/// is hg's XXTEA with its stock key.
+pub struct DecryptConfig {
+    /// The key, as given; the cipher pads or repeats it.
+    pub key: Vec<u8>,
+    /// The cipher.
+    pub cipher: Arc<dyn MetadataCipher>,
+}
+
+impl DecryptConfig {
+    /// hg's XXTEA with `key`.
+    pub fn with_key(key: Vec<u8>) -> Self {
+        Self {
+            key,
+            cipher: Arc::new(Xxtea::new(DEFAULT_CIPHER, XxteaParams::default())),
+        }
+    }
+
+    /// The same key with another cipher.
+    pub fn with_cipher(mut self, cipher: Arc<dyn MetadataCipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Decrypts `data` with the key.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.cipher.decrypt(data, &self.key)
+    }
+
+    /// Encrypts `data` with the key.
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.cipher.encrypt(data, &self.key)
+    }
+}
+
+impl Default for DecryptConfig {
+    fn default() -> Self {
+        Self::with_key(DEFAULT_KEY.to_vec())
+    }
+}
+
+/// The built-in cipher names and their descriptions.
+pub const VARIANTS: [(&str, &str); 6] = [
+    (
+        "xxtea",
+        "XXTEA, standard delta, 6 + 52/n rounds, no length word (default)",
+    ),
+    (
+        "xxtea-len",
+        "XXTEA with a trailing length word, as most XXTEA libraries do",
+    ),
+    ("xxtea-be", "XXTEA over big-endian words"),
+    ("xxtea-32", "XXTEA with a fixed 32 rounds"),
+    (
+        "xxtea-repeat-key",
+        "XXTEA with short keys repeated to 16 bytes instead of zero padded",
+    ),
+    ("none", "Blob stored unencrypted"),
+];
+
+/// Accepts a variant name from VARIANTS or a custom XXTEA spec such as
+/// `xxtea:delta=0x61C88647,rounds=32,length,be,repeat-key`.
+pub fn by_name(spec: &str) -> Result<Arc<dyn MetadataCipher>, String> {
+    let default = XxteaParams::default();
+    let params = match spec {
+        "none" => return Ok(Arc::new(Plain)),
+        "xxtea" => default,
+        "xxtea-len" => XxteaParams {
+            include_length: true,
+            ..default
+        },
+        "xxtea-be" => XxteaParams {
+            big_endian: true,
+            ..default
+        },
+        "xxtea-32" => XxteaParams {
+            rounds: Rounds::Fixed(32),
+            ..default
+        },
+        "xxtea-repeat-key" => XxteaParams {
+            key_schedule: KeySchedule::Repeat,
+            ..default
+        },
+        _ => match spec.strip_prefix("xxtea:") {
+            Some(options) => parse_options(options)?,
+            None => {
+                return Err(format!("Unknown cipher: {} (see --list-ciphers)", spec));
+            }
+        },
+    };
+    Ok(Arc::new(Xxtea::new(spec, params)))
+}
+
+fn parse_options(options: &str) -> Result<XxteaParams, String> {
+    let mut params = XxteaParams::default();
+    for option in options.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        match option.split_once('=') {
+            Some(("delta", value)) => params.delta = parse_number(value)?,
+            Some(("rounds", value)) => match parse_number(value)? {
+                0 => return Err("rounds must be at least 1".to_string()),
+                n => params.rounds = Rounds::Fixed(n),
+            },
+            None if option == "length" => params.include_length = true,
+            None if option == "be" => params.big_endian = true,
+            None if option == "repeat-key" => params.key_schedule = KeySchedule::Repeat,
+            _ => return Err(format!("Unknown cipher option: {}", option)),
+        }
+    }
+    Ok(params)
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("Invalid number in cipher spec: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &[u8] = b"global-metadata.dat, 32 bytes in";
+
+    fn error(spec: &str) -> String {
+        by_name(spec).err().unwrap()
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        let mut ciphertexts = Vec::new();
+        for (name, _) in VARIANTS {
+            let cipher = by_name(name).unwrap();
+            let encrypted = cipher.encrypt(PLAIN, DEFAULT_KEY).unwrap();
+            assert_eq!(
+                cipher.decrypt(&encrypted, DEFAULT_KEY).unwrap(),
+                PLAIN,
+                "{}",
+                name
+            );
+            if name != "none" {
+                assert!(!ciphertexts.contains(&encrypted), "{}", name);
+            }
+            ciphertexts.push(encrypted);
+        }
+        assert_eq!(ciphertexts.last().unwrap(), PLAIN);
+    }
+
+    #[test]
+    fn custom_spec_round_trips() {
+        let cipher = by_name("xxtea:delta=0x61C88647, rounds=32,length,be,repeat-key").unwrap();
+        let encrypted = cipher.encrypt(PLAIN, DEFAULT_KEY).unwrap();
+        assert_eq!(encrypted.len(), PLAIN.len() + 4);
+        assert_eq!(cipher.decrypt(&encrypted, DEFAULT_KEY).unwrap(), PLAIN);
+
+        let standard = by_name("xxtea:delta=2654435769").unwrap();
+        assert_eq!(
+            standard.encrypt(PLAIN, DEFAULT_KEY),
+            by_name("xxtea").unwrap().encrypt(PLAIN, DEFAULT_KEY)
+        );
+    }
+
+    #[test]
+    fn bad_specs_are_rejected() {
+        assert_eq!(error("aes"), "Unknown cipher: aes (see --list-ciphers)");
+        assert_eq!(error("xxtea:fast"), "Unknown cipher option: fast");
+        assert_eq!(error("xxtea:rounds"), "Unknown cipher option: rounds");
+        assert_eq!(
+            error("xxtea:delta=0xZZ"),
+            "Invalid number in cipher spec: 0xZZ"
+        );
+        assert_eq!(error("xxtea:delta=-1"), "Invalid number in cipher spec: -1");
+        assert_eq!(error("xxtea:rounds=0"), "rounds must be at least 1");
+    }
+}
@@ -0,0 +1,1018 @@
+use crate::metadata::{
+    AttributeValue, ConstantValue, CustomAttribute, ElementType, EventDefinition, GlobalMetadata,
+    MetadataVersion, MethodDefinition, ParameterDefinition, PropertyDefinition, TypeDefinition,
+};
+use crate::registration::CodeAddress;
+
+const METHOD_ATTRIBUTE_MEMBER_ACCESS_MASK: u16 = 0x0007;
+const METHOD_ATTRIBUTE_PRIVATE: u16 = 0x0001;
+const METHOD_ATTRIBUTE_FAM_AND_ASSEM: u16 = 0x0002;
+const METHOD_ATTRIBUTE_ASSEM: u16 = 0x0003;
+const METHOD_ATTRIBUTE_FAMILY: u16 = 0x0004;
+const METHOD_ATTRIBUTE_FAM_OR_ASSEM: u16 = 0x0005;
+const METHOD_ATTRIBUTE_PUBLIC: u16 = 0x0006;
+/// MethodAttributes: the method is static.
+pub const METHOD_ATTRIBUTE_STATIC: u16 = 0x0010;
+const METHOD_ATTRIBUTE_FINAL: u16 = 0x0020;
+const METHOD_ATTRIBUTE_VIRTUAL: u16 = 0x0040;
+const METHOD_ATTRIBUTE_NEW_SLOT: u16 = 0x0100;
+/// MethodAttributes: the method has no body.
+pub const METHOD_ATTRIBUTE_ABSTRACT: u16 = 0x0400;
+const METHOD_ATTRIBUTE_PINVOKE_IMPL: u16 = 0x2000;
+
+const TYPE_ATTRIBUTE_VISIBILITY_MASK: u32 = 0x0000_0007;
+const TYPE_ATTRIBUTE_PUBLIC: u32 = 0x0000_0001;
+const TYPE_ATTRIBUTE_NESTED_PUBLIC: u32 = 0x0000_0002;
+const TYPE_ATTRIBUTE_NESTED_PRIVATE: u32 = 0x0000_0003;
+const TYPE_ATTRIBUTE_NESTED_FAMILY: u32 = 0x0000_0004;
+const TYPE_ATTRIBUTE_NESTED_FAM_AND_ASSEM: u32 = 0x0000_0006;
+const TYPE_ATTRIBUTE_NESTED_FAM_OR_ASSEM: u32 = 0x0000_0007;
+const TYPE_ATTRIBUTE_INTERFACE: u32 = 0x0000_0020;
+const TYPE_ATTRIBUTE_ABSTRACT: u32 = 0x0000_0080;
+const TYPE_ATTRIBUTE_SEALED: u32 = 0x0000_0100;
+const TYPE_ATTRIBUTE_SERIALIZABLE: u32 = 0x2000;
+
+const FIELD_ATTRIBUTE_FIELD_ACCESS_MASK: u16 = 0x0007;
+const FIELD_ATTRIBUTE_PRIVATE: u16 = 0x0001;
+const FIELD_ATTRIBUTE_FAM_AND_ASSEM: u16 = 0x0002;
+const FIELD_ATTRIBUTE_ASSEMBLY: u16 = 0x0003;
+const FIELD_ATTRIBUTE_FAMILY: u16 = 0x0004;
+const FIELD_ATTRIBUTE_FAM_OR_ASSEM: u16 = 0x0005;
+const FIELD_ATTRIBUTE_PUBLIC: u16 = 0x0006;
+/// FieldAttributes: the field is static.
+pub const FIELD_ATTRIBUTE_STATIC: u16 = 0x0010;
+const FIELD_ATTRIBUTE_INIT_ONLY: u16 = 0x0020;
+/// FieldAttributes: the field is a constant.
+pub const FIELD_ATTRIBUTE_LITERAL: u16 = 0x0040;
+
+const GENERIC_PARAMETER_ATTRIBUTE_COVARIANT: u16 = 0x0001;
+const GENERIC_PARAMETER_ATTRIBUTE_CONTRAVARIANT: u16 = 0x0002;
+const GENERIC_PARAMETER_ATTRIBUTE_REFERENCE_TYPE_CONSTRAINT: u16 = 0x0004;
+const GENERIC_PARAMETER_ATTRIBUTE_NOT_NULLABLE_VALUE_TYPE_CONSTRAINT: u16 = 0x0008;
+const GENERIC_PARAMETER_ATTRIBUTE_DEFAULT_CONSTRUCTOR_CONSTRAINT: u16 = 0x0010;
+
+const PARAM_ARRAY_ATTRIBUTE: &str = "System.ParamArrayAttribute";
+
+/// Type indices point into the binary's type table, so names and constant
+/// encodings come from whatever has the binary loaded.
+pub trait TypeResolver {
+    /// The C# name of the type at `type_index`.
+    fn type_name(&self, metadata: &GlobalMetadata, type_index: i32) -> String;
+
+    /// How a constant of this type is encoded; the underlying type for enums.
+    fn constant_type(&self, metadata: &GlobalMetadata, type_index: i32) -> Option<ElementType>;
+
+    /// "ref ", "out " or "in " for by-reference parameters.
+    fn parameter_modifier(&self, _metadata: &GlobalMetadata, _type_index: i32) -> &'static str {
+        ""
+    }
+
+    /// The attrs IL2CPP keeps on the type rather than the member: field flags
+    /// for a field's type, parameter flags for a parameter's.
+    fn type_attrs(&self, _metadata: &GlobalMetadata, _type_index: i32) -> Option<u16> {
+        None
+    }
+
+    /// Where the method's code is, when the binary has been searched.
+    fn method_address(&self, _method_index: usize) -> Option<CodeAddress> {
+        None
+    }
+}
+
+/// The metadata on its own: types are named by index, and defaults other
+/// than null are left out since their encoding depends on the type.
+pub struct UnresolvedTypes;
+
+impl TypeResolver for UnresolvedTypes {
+    fn type_name(&self, _metadata: &GlobalMetadata, type_index: i32) -> String {
+        format!("Type{}", type_index)
+    }
+
+    fn constant_type(&self, _metadata: &GlobalMetadata, _type_index: i32) -> Option<ElementType> {
+        None
+    }
+}
+
+/// `List<T>` for the metadata name ``List`1``.
+pub fn type_name(metadata: &GlobalMetadata, type_def: &TypeDefinition) -> String {
+    let name = metadata.type_name(type_def).unwrap_or("");
+    format!(
+        "{}{}",
+        strip_arity(name),
+        generic_arguments(metadata, type_def.generic_container_index, false)
+    )
+}
+
+/// `Outer.Inner<T>` for the type definition at `type_index`, walking out
+/// through its declaring types.
+pub fn nested_type_name(metadata: &GlobalMetadata, type_index: usize) -> String {
+    let (names, _) = enclosing_names(metadata, type_index);
+    names.join(".")
+}
+
+/// `Game.Outer.Inner<T>`: the nested name under the namespace of the
+/// outermost type.
+pub fn full_type_name(metadata: &GlobalMetadata, type_index: usize) -> String {
+    let (names, outermost) = enclosing_names(metadata, type_index);
+    qualified_name(metadata, &names, outermost)
+}
+
+/// `System.Collections.Generic.List<int>`: the full name with the type's own
+/// parameters replaced by `arguments`.
+pub fn generic_instance_name(
+    metadata: &GlobalMetadata,
+    type_index: usize,
+    arguments: &[String],
+) -> String {
+    let (mut names, outermost) = enclosing_names(metadata, type_index);
+    if let Some(last) = names.last_mut() {
+        let name = metadata.type_name(&metadata.type_definitions[type_index]);
+        *last = format!(
+            "{}<{}>",
+            strip_arity(name.unwrap_or("")),
+            arguments.join(", ")
+        );
+    }
+    qualified_name(metadata, &names, outermost)
+}
+
+/// The namespace a type is declared in, which for nested types is that of
+/// the outermost one.
+pub fn type_namespace(metadata: &GlobalMetadata, type_index: usize) -> &str {
+    let (_, outermost) = enclosing_names(metadata, type_index);
+    declared_namespace(metadata, outermost)
+}
+
+fn declared_namespace(metadata: &GlobalMetadata, type_index: usize) -> &str {
+    metadata
+        .type_definitions
+        .get(type_index)
+        .and_then(|t| metadata.get_string(t.namespace_index))
+        .unwrap_or("")
+}
+
+fn qualified_name(metadata: &GlobalMetadata, names: &[String], outermost: usize) -> String {
+    let namespace = declared_namespace(metadata, outermost);
+    if namespace.is_empty() {
+        names.join(".")
+    } else {
+        format!("{}.{}", namespace, names.join("."))
+    }
+}
+
+fn enclosing_names(metadata: &GlobalMetadata, type_index: usize) -> (Vec<String>, usize) {
+    let mut names = Vec::new();
+    let mut index = type_index;
+    // Bounded by the type count in case a corrupt nesting table loops.
+    for _ in 0..=metadata.type_definitions.len() {
+        let Some(type_def) = metadata.type_definitions.get(index) else {
+            break;
+        };
+        names.push(type_name(metadata, type_def));
+        match metadata.declaring_type(index) {
+            Some(parent) => index = parent,
+            None => break,
+        }
+    }
+    names.reverse();
+    (names, index)
+}
+
+/// The C# spelling of a built-in type.
+pub fn keyword(element: ElementType) -> Option<&'static str> {
+    Some(match element {
+        ElementType::Void => "void",
+        ElementType::Boolean => "bool",
+        ElementType::Char => "char",
+        ElementType::I1 => "sbyte",
+        ElementType::U1 => "byte",
+        ElementType::I2 => "short",
+        ElementType::U2 => "ushort",
+        ElementType::I4 => "int",
+        ElementType::U4 => "uint",
+        ElementType::I8 => "long",
+        ElementType::U8 => "ulong",
+        ElementType::R4 => "float",
+        ElementType::R8 => "double",
+        ElementType::String => "string",
+        ElementType::Object => "object",
+        ElementType::I => "IntPtr",
+        ElementType::U => "UIntPtr",
+        ElementType::TypedByRef => "TypedReference",
+        _ => return None,
+    })
+}
+
+/// `IPool<out T> : IDisposable where T : class`, as the type is declared.
+pub fn type_declaration(
+    metadata: &GlobalMetadata,
+    type_def: &TypeDefinition,
+    bases: &[String],
+    types: &dyn TypeResolver,
+) -> String {
+    let name = metadata.type_name(type_def).unwrap_or("");
+    let bases = if bases.is_empty() {
+        String::new()
+    } else {
+        format!(" : {}", bases.join(", "))
+    };
+    format!(
+        "{}{}{}{}",
+        strip_arity(name),
+        generic_arguments(metadata, type_def.generic_container_index, true),
+        bases,
+        constraint_clauses(metadata, type_def.generic_container_index, types)
+    )
+}
+
+/// `public sealed class`, `internal struct`, `public static class` and so on.
+pub fn type_modifiers(type_def: &TypeDefinition) -> String {
+    let flags = type_def.flags;
+    let mut modifiers = String::from(match flags & TYPE_ATTRIBUTE_VISIBILITY_MASK {
+        TYPE_ATTRIBUTE_PUBLIC | TYPE_ATTRIBUTE_NESTED_PUBLIC => "public ",
+        TYPE_ATTRIBUTE_NESTED_PRIVATE => "private ",
+        TYPE_ATTRIBUTE_NESTED_FAMILY => "protected ",
+        TYPE_ATTRIBUTE_NESTED_FAM_AND_ASSEM => "private protected ",
+        TYPE_ATTRIBUTE_NESTED_FAM_OR_ASSEM => "protected internal ",
+        // NotPublic and NestedAssembly.
+        _ => "internal ",
+    });
+    let is_interface = flags & TYPE_ATTRIBUTE_INTERFACE != 0;
+    let is_abstract = flags & TYPE_ATTRIBUTE_ABSTRACT != 0;
+    let is_sealed = flags & TYPE_ATTRIBUTE_SEALED != 0;
+    // Value types are always sealed, and interfaces always abstract.
+    if !is_interface && !type_def.is_value_type() {
+        if is_abstract && is_sealed {
+            modifiers += "static ";
+        } else if is_abstract {
+            modifiers += "abstract ";
+        } else if is_sealed {
+            modifiers += "sealed ";
+        }
+    }
+    modifiers += if is_interface {
+        "interface"
+    } else if type_def.is_enum() {
+        "enum"
+    } else if type_def.is_value_type() {
+        "struct"
+    } else {
+        "class"
+    };
+    modifiers
+}
+
+// Generic type names carry their arity after a backtick.
+fn strip_arity(name: &str) -> &str {
+    name.split('`').next().unwrap_or(name)
+}
+
+fn generic_arguments(metadata: &GlobalMetadata, container_index: i32, variance: bool) -> String {
+    let Some(container) = metadata.generic_container(container_index) else {
+        return String::new();
+    };
+    let arguments = metadata
+        .container_parameters(container)
+        .iter()
+        .map(|parameter| {
+            let prefix = match parameter.flags {
+                _ if !variance => "",
+                f if f & GENERIC_PARAMETER_ATTRIBUTE_COVARIANT != 0 => "out ",
+                f if f & GENERIC_PARAMETER_ATTRIBUTE_CONTRAVARIANT != 0 => "in ",
+                _ => "",
+            };
+            format!(
+                "{}{}",
+                prefix,
+                metadata.generic_parameter_name(parameter).unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("<{}>", arguments.join(", "))
+}
+
+// ` where T : class, IDisposable, new()`, one clause per constrained
+// parameter in the order C# requires.
+fn constraint_clauses(
+    metadata: &GlobalMetadata,
+    container_index: i32,
+    types: &dyn TypeResolver,
+) -> String {
+    let Some(container) = metadata.generic_container(container_index) else {
+        return String::new();
+    };
+    let mut clauses = String::new();
+    for parameter in metadata.container_parameters(container) {
+        let is_struct =
+            parameter.flags & GENERIC_PARAMETER_ATTRIBUTE_NOT_NULLABLE_VALUE_TYPE_CONSTRAINT != 0;
+        let mut constraints = Vec::new();
+        if parameter.flags & GENERIC_PARAMETER_ATTRIBUTE_REFERENCE_TYPE_CONSTRAINT != 0 {
+            constraints.push("class".to_string());
+        }
+        if is_struct {
+            constraints.push("struct".to_string());
+        }
+        for &type_index in metadata.parameter_constraints(parameter) {
+            let name = types.type_name(metadata, type_index);
+            // The struct constraint is also recorded as a ValueType base.
+            if !(is_struct && name.ends_with("ValueType")) {
+                constraints.push(name);
+            }
+        }
+        // new() is implied by struct and may not be repeated.
+        if parameter.flags & GENERIC_PARAMETER_ATTRIBUTE_DEFAULT_CONSTRUCTOR_CONSTRAINT != 0
+            && !is_struct
+        {
+            constraints.push("new()".to_string());
+        }
+        if !constraints.is_empty() {
+            clauses += &format!(
+                " where {} : {}",
+                metadata.generic_parameter_name(parameter).unwrap_or(""),
+                constraints.join(", ")
+            );
+        }
+    }
+    clauses
+}
+
+/// The C# modifiers for MethodAttributes, each followed by a space.
+pub fn method_modifiers(flags: u16) -> String {
+    let mut modifiers = String::from(match flags & METHOD_ATTRIBUTE_MEMBER_ACCESS_MASK {
+        METHOD_ATTRIBUTE_PRIVATE => "private ",
+        METHOD_ATTRIBUTE_PUBLIC => "public ",
+        METHOD_ATTRIBUTE_FAMILY => "protected ",
+        METHOD_ATTRIBUTE_ASSEM => "internal ",
+        METHOD_ATTRIBUTE_FAM_AND_ASSEM => "private protected ",
+        METHOD_ATTRIBUTE_FAM_OR_ASSEM => "protected internal ",
+        _ => "",
+    });
+    if flags & METHOD_ATTRIBUTE_STATIC != 0 {
+        modifiers += "static ";
+    }
+    let new_slot = flags & METHOD_ATTRIBUTE_NEW_SLOT != 0;
+    if flags & METHOD_ATTRIBUTE_ABSTRACT != 0 {
+        modifiers += if new_slot {
+            "abstract "
+        } else {
+            "abstract override "
+        };
+    } else if flags & METHOD_ATTRIBUTE_FINAL != 0 {
+        if !new_slot {
+            modifiers += "sealed override ";
+        }
+    } else if flags & METHOD_ATTRIBUTE_VIRTUAL != 0 {
+        modifiers += if new_slot { "virtual " } else { "override " };
+    }
+    if flags & METHOD_ATTRIBUTE_PINVOKE_IMPL != 0 {
+        modifiers += "extern ";
+    }
+    modifiers
+}
+
+/// The C# modifiers for FieldAttributes, each followed by a space.
+pub fn field_modifiers(attrs: u16) -> String {
+    let mut modifiers = String::from(match attrs & FIELD_ATTRIBUTE_FIELD_ACCESS_MASK {
+        FIELD_ATTRIBUTE_PRIVATE => "private ",
+        FIELD_ATTRIBUTE_PUBLIC => "public ",
+        FIELD_ATTRIBUTE_FAMILY => "protected ",
+        FIELD_ATTRIBUTE_ASSEMBLY => "internal ",
+        FIELD_ATTRIBUTE_FAM_AND_ASSEM => "private protected ",
+        FIELD_ATTRIBUTE_FAM_OR_ASSEM => "protected internal ",
+        _ => "",
+    });
+    if attrs & FIELD_ATTRIBUTE_LITERAL != 0 {
+        modifiers += "const ";
+    } else {
+        if attrs & FIELD_ATTRIBUTE_STATIC != 0 {
+            modifiers += "static ";
+        }
+        if attrs & FIELD_ATTRIBUTE_INIT_ONLY != 0 {
+            modifiers += "readonly ";
+        }
+    }
+    modifiers
+}
+
+/// `public const int Max = 10` for the field at `field_index`. Field flags
+/// live in the binary, so without it only the type and name are known.
+pub fn field_declaration(
+    metadata: &GlobalMetadata,
+    field_index: usize,
+    types: &dyn TypeResolver,
+) -> Option<String> {
+    let field = metadata.fields.get(field_index)?;
+    let mut rendered = format!(
+        "{}{} {}",
+        types
+            .type_attrs(metadata, field.type_index)
+            .map_or(String::new(), field_modifiers),
+        types.type_name(metadata, field.type_index),
+        metadata.field_name(field).unwrap_or("")
+    );
+    if let Some(value) = field_default(metadata, field_index as i32, types) {
+        rendered += &format!(" = {}", value);
+    }
+    Some(rendered)
+}
+
+/// The constant of the field at `field_index`, if it has one.
+pub fn field_default(
+    metadata: &GlobalMetadata,
+    field_index: i32,
+    types: &dyn TypeResolver,
+) -> Option<ConstantValue> {
+    let default = metadata.field_default_value(field_index)?;
+    if default.data_index < 0 {
+        return Some(ConstantValue::Null);
+    }
+    let element = types.constant_type(metadata, default.type_index)?;
+    metadata.default_value(default.data_index, element).ok()
+}
+
+/// `public static int Foo(string a, int b = 3)` for the method at
+/// `method_index`, or `T Take<T>() where T : new()` for generic ones.
+pub fn method_signature(
+    metadata: &GlobalMetadata,
+    method_index: usize,
+    types: &dyn TypeResolver,
+) -> Option<String> {
+    let method = metadata.methods.get(method_index)?;
+    let image = usize::try_from(method.declaring_type)
+        .ok()
+        .and_then(|type_index| metadata.type_image(type_index));
+    let parameters = metadata
+        .method_parameters(method)
+        .iter()
+        .enumerate()
+        .map(|(i, parameter)| {
+            let params =
+                image.is_some_and(|image| is_param_array(metadata, image, parameter, types));
+            let mut rendered = format!(
+                "{}{}{} {}",
+                if params { "params " } else { "" },
+                types.parameter_modifier(metadata, parameter.type_index),
+                types.type_name(metadata, parameter.type_index),
+                metadata.parameter_name(parameter).unwrap_or("")
+            );
+            let parameter_index = method.parameter_start + i as i32;
+            if let Some(value) = parameter_default(metadata, parameter_index, types) {
+                rendered += &format!(" = {}", value);
+            }
+            rendered
+        })
+        .collect::<Vec<_>>();
+    Some(format!(
+        "{}{} {}{}({}){}",
+        method_modifiers(method.flags),
+        types.type_name(metadata, method.return_type),
+        metadata.method_name(method).unwrap_or(""),
+        generic_arguments(metadata, method.generic_container_index, false),
+        parameters.join(", "),
+        constraint_clauses(metadata, method.generic_container_index, types)
+    ))
+}
+
+/// `public int Hp { get; set; }`. The type comes from the getter's return
+/// or the setter's value parameter, the modifiers from the first accessor.
+pub fn property_declaration(
+    metadata: &GlobalMetadata,
+    type_def: &TypeDefinition,
+    property: &PropertyDefinition,
+    types: &dyn TypeResolver,
+) -> String {
+    let getter = accessor(metadata, type_def, property.get);
+    let setter = accessor(metadata, type_def, property.set);
+    let type_name = match (getter, setter) {
+        (Some(get), _) => types.type_name(metadata, get.return_type),
+        (None, Some(set)) => metadata
+            .method_parameters(set)
+            .last()
+            .map(|value| types.type_name(metadata, value.type_index))
+            .unwrap_or_default(),
+        (None, None) => String::new(),
+    };
+    let mut accessors = String::new();
+    if getter.is_some() {
+        accessors += "get; ";
+    }
+    if setter.is_some() {
+        accessors += "set; ";
+    }
+    format!(
+        "{}{} {} {{ {}}}",
+        getter
+            .or(setter)
+            .map_or(String::new(), |m| method_modifiers(m.flags)),
+        type_name,
+        metadata.property_name(property).unwrap_or(""),
+        accessors
+    )
+}
+
+/// `public event Action OnDeath`, with the modifiers of the first accessor.
+pub fn event_declaration(
+    metadata: &GlobalMetadata,
+    type_def: &TypeDefinition,
+    event: &EventDefinition,
+    types: &dyn TypeResolver,
+) -> String {
+    let first = [event.add, event.remove, event.raise]
+        .into_iter()
+        .find_map(|relative| accessor(metadata, type_def, relative));
+    format!(
+        "{}event {} {}",
+        first.map_or(String::new(), |m| method_modifiers(m.flags)),
+        types.type_name(metadata, event.type_index),
+        metadata.event_name(event).unwrap_or("")
+    )
+}
+
+fn accessor<'a>(
+    metadata: &'a GlobalMetadata,
+    type_def: &TypeDefinition,
+    relative: i32,
+) -> Option<&'a MethodDefinition> {
+    metadata
+        .type_method_index(type_def, relative)
+        .map(|index| &metadata.methods[index])
+}
+
+fn parameter_default(
+    metadata: &GlobalMetadata,
+    parameter_index: i32,
+    types: &dyn TypeResolver,
+) -> Option<ConstantValue> {
+    let default = metadata.parameter_default_value(parameter_index)?;
+    if default.data_index < 0 {
+        return Some(ConstantValue::Null);
+    }
+    let element = types.constant_type(metadata, default.type_index)?;
+    metadata.default_value(default.data_index, element).ok()
+}
+
+// `params` leaves no flag on the parameter, only a ParamArrayAttribute.
+fn is_param_array(
+    metadata: &GlobalMetadata,
+    image_index: usize,
+    parameter: &ParameterDefinition,
+    types: &dyn TypeResolver,
+) -> bool {
+    let Some(range) = metadata.attribute_range(
+        image_index,
+        parameter.token,
+        parameter.custom_attribute_index,
+    ) else {
+        return false;
+    };
+    if metadata.version < MetadataVersion::V29_0 {
+        return metadata
+            .attribute_type_indices(range)
+            .iter()
+            .any(|&type_index| types.type_name(metadata, type_index) == PARAM_ARRAY_ATTRIBUTE);
+    }
+    let enum_type = |type_index| types.constant_type(metadata, type_index);
+    metadata
+        .attributes(range, &enum_type)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|attribute| metadata.methods.get(attribute.constructor))
+        .filter_map(|constructor| usize::try_from(constructor.declaring_type).ok())
+        .any(|type_index| full_type_name(metadata, type_index) == PARAM_ARRAY_ATTRIBUTE)
+}
+
+/// The attribute lines for a type: `[Serializable]` from its flags, its
+/// custom attributes, and the `[Token(...)]` Il2CppDumper's dummy assemblies
+/// carry.
+pub fn type_attributes(
+    metadata: &GlobalMetadata,
+    type_index: usize,
+    types: &dyn TypeResolver,
+) -> Vec<String> {
+    let Some(type_def) = metadata.type_definitions.get(type_index) else {
+        return Vec::new();
+    };
+    let mut attributes = Vec::new();
+    if type_def.flags & TYPE_ATTRIBUTE_SERIALIZABLE != 0 {
+        attributes.push("[Serializable]".to_string());
+    }
+    if let Some(image) = metadata.type_image(type_index) {
+        attributes.extend(custom_attributes(
+            metadata,
+            image,
+            type_def.token,
+            type_def.custom_attribute_index,
+            types,
+        ));
+    }
+    attributes.push(token_attribute(type_def.token));
+    attributes
+}
+
+/// The attribute lines dump.cs puts above a method, its token last.
+pub fn method_attributes(
+    metadata: &GlobalMetadata,
+    method_index: usize,
+    types: &dyn TypeResolver,
+) -> Vec<String> {
+    let Some(method) = metadata.methods.get(method_index) else {
+        return Vec::new();
+    };
+    let mut attributes = usize::try_from(method.declaring_type)
+        .ok()
+        .and_then(|type_index| metadata.type_image(type_index))
+        .map(|image| {
+            custom_attributes(
+                metadata,
+                image,
+                method.token,
+                method.custom_attribute_index,
+                types,
+            )
+        })
+        .unwrap_or_default();
+    attributes.push(token_attribute(method.token));
+    attributes
+}
+
+/// Fields do not record their declaring type, so the caller passes it.
+pub fn field_attributes(
+    metadata: &GlobalMetadata,
+    type_index: usize,
+    field_index: usize,
+    types: &dyn TypeResolver,
+) -> Vec<String> {
+    let Some(field) = metadata.fields.get(field_index) else {
+        return Vec::new();
+    };
+    let mut attributes = metadata
+        .type_image(type_index)
+        .map(|image| {
+            custom_attributes(
+                metadata,
+                image,
+                field.token,
+                field.custom_attribute_index,
+                types,
+            )
+        })
+        .unwrap_or_default();
+    attributes.push(token_attribute(field.token));
+    attributes
+}
+
+/// The `[Token]` attribute dump.cs gives every member.
+pub fn token_attribute(token: u32) -> String {
+    format!("[Token(Token = \"0x{:X}\")]", token)
+}
+
+// Before 29 only the attribute types are in the metadata; the argument
+// values were compiled into code.
+fn custom_attributes(
+    metadata: &GlobalMetadata,
+    image_index: usize,
+    token: u32,
+    legacy_index: i32,
+    types: &dyn TypeResolver,
+) -> Vec<String> {
+    let Some(range) = metadata.attribute_range(image_index, token, legacy_index) else {
+        return Vec::new();
+    };
+    if metadata.version < MetadataVersion::V29_0 {
+        return metadata
+            .attribute_type_indices(range)
+            .iter()
+            .map(|&type_index| {
+                format!(
+                    "[{}]",
+                    attribute_name(&types.type_name(metadata, type_index))
+                )
+            })
+            .collect();
+    }
+    let enum_type = |type_index| types.constant_type(metadata, type_index);
+    match metadata.attributes(range, &enum_type) {
+        Ok(attributes) => attributes
+            .iter()
+            .map(|attribute| render_attribute(metadata, attribute, types))
+            .collect(),
+        Err(e) => vec![format!("// Failed to decode attributes: {}", e)],
+    }
+}
+
+fn render_attribute(
+    metadata: &GlobalMetadata,
+    attribute: &CustomAttribute,
+    types: &dyn TypeResolver,
+) -> String {
+    let name = metadata
+        .methods
+        .get(attribute.constructor)
+        .and_then(|m| usize::try_from(m.declaring_type).ok())
+        .map(|type_index| nested_type_name(metadata, type_index))
+        .unwrap_or_default();
+    let mut arguments = attribute
+        .arguments
+        .iter()
+        .map(|value| render_attribute_value(metadata, value, types))
+        .collect::<Vec<_>>();
+    arguments.extend(attribute.named_arguments.iter().map(|named| {
+        format!(
+            "{} = {}",
+            named.name,
+            render_attribute_value(metadata, &named.value, types)
+        )
+    }));
+    if arguments.is_empty() {
+        format!("[{}]", attribute_name(&name))
+    } else {
+        format!("[{}({})]", attribute_name(&name), arguments.join(", "))
+    }
+}
+
+fn render_attribute_value(
+    metadata: &GlobalMetadata,
+    value: &AttributeValue,
+    types: &dyn TypeResolver,
+) -> String {
+    match value {
+        AttributeValue::Constant(constant) => constant.to_string(),
+        AttributeValue::Enum { type_index, value } => {
+            format!("({}){}", types.type_name(metadata, *type_index), value)
+        }
+        AttributeValue::Type(-1) => "null".to_string(),
+        AttributeValue::Type(type_index) => {
+            format!("typeof({})", types.type_name(metadata, *type_index))
+        }
+        AttributeValue::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| render_attribute_value(metadata, item, types))
+                .collect::<Vec<_>>();
+            format!("new[] {{ {} }}", items.join(", "))
+        }
+    }
+}
+
+// C# lets `ObsoleteAttribute` be written as `Obsolete`.
+fn attribute_name(name: &str) -> &str {
+    match name.strip_suffix("Attribute") {
+        Some(short) if !short.is_empty() => short,
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MetadataBuilder, il2cpp_type, registration};
+    use crate::types::{Il2CppType, TypeKind, TypeTable};
+
+    const INT: i32 = 0;
+    const STRING: i32 = 1;
+
+    // System.Int32 and System.String as types 0 and 1.
+    fn system_types(builder: &mut MetadataBuilder) {
+        builder.add_type("System", "Int32");
+        builder.add_type("System", "String");
+    }
+
+    // The built metadata with the type definitions' own types registered
+    // first, then `extra`.
+    fn table(builder: &MetadataBuilder, extra: Vec<Il2CppType>) -> (GlobalMetadata, TypeTable) {
+        let metadata = builder.build();
+        let mut types: Vec<_> = (0..metadata.type_definitions.len())
+            .map(|index| il2cpp_type(TypeKind::Definition(index)))
+            .collect();
+        types.extend(extra);
+        let table = TypeTable::from_registration(&metadata, registration(types));
+        (metadata, table)
+    }
+
+    fn by_ref(kind: TypeKind, attrs: u16) -> Il2CppType {
+        Il2CppType {
+            kind,
+            attrs,
+            byref: true,
+        }
+    }
+
+    #[test]
+    fn parameters_render_modifiers_and_defaults() {
+        let mut b = MetadataBuilder::new();
+        system_types(&mut b);
+        let param_array = b.add_type("System", "ParamArrayAttribute");
+        let constructor = b.add_method(param_array, ".ctor", INT);
+        let player = b.add_type("Game", "Player");
+        let (out_int, in_int, ref_int, strings) = (4, 5, 6, 7);
+        let method = b.add_method(player, "Move", INT);
+        b.add_parameter(method, "x", out_int);
+        b.add_parameter(method, "y", in_int);
+        b.add_parameter(method, "z", ref_int);
+        let speed = b.add_parameter(method, "speed", INT);
+        b.parameter_default(speed, INT, Some(&[0x06]));
+        let name = b.add_parameter(method, "name", STRING);
+        b.parameter_default(name, STRING, Some(&[0x04, b'h', b'i']));
+        let target = b.add_parameter(method, "target", STRING);
+        b.parameter_default(target, STRING, None);
+        let tags = b.add_parameter(method, "tags", strings);
+        let token = b.parameters[tags].token;
+        let mut blob = vec![0x01];
+        blob.extend((constructor as u32).to_le_bytes());
+        blob.extend([0x00, 0x00, 0x00]);
+        b.attribute(token, &blob);
+
+        let (metadata, types) = table(
+            &b,
+            vec![
+                by_ref(TypeKind::Definition(0), 0x0002),
+                by_ref(TypeKind::Definition(0), 0x0001),
+                by_ref(TypeKind::Definition(0), 0),
+                il2cpp_type(TypeKind::SzArray(Box::new(il2cpp_type(
+                    TypeKind::Definition(1),
+                )))),
+            ],
+        );
+        assert_eq!(
+            method_signature(&metadata, method, &types).unwrap(),
+            "public int Move(out int x, in int y, ref int z, int speed = 3, \
+             string name = \"hi\", string target = null, params string[] tags)"
+        );
+    }
+
+    #[test]
+    fn variance_is_declared_on_types_only() {
+        let mut b = MetadataBuilder::new();
+        system_types(&mut b);
+        let converter = b.add_type("Game", "IConverter`2");
+        b.type_definitions[converter].flags = 0xA1;
+        let parameters = b.make_generic(converter, false, &[("TIn", 0x2), ("TOut", 0x1)]);
+        let (t_in, t_out) = (3, 4);
+        let method = b.add_method(converter, "Convert", t_out);
+        b.add_parameter(method, "value", t_in);
+
+        let (metadata, types) = table(
+            &b,
+            vec![
+                il2cpp_type(TypeKind::GenericParameter(parameters)),
+                il2cpp_type(TypeKind::GenericParameter(parameters + 1)),
+            ],
+        );
+        let type_def = &metadata.type_definitions[converter];
+        assert_eq!(type_modifiers(type_def), "public interface");
+        assert_eq!(
+            type_declaration(&metadata, type_def, &[], &types),
+            "IConverter<in TIn, out TOut>"
+        );
+        assert_eq!(
+            method_signature(&metadata, method, &types).unwrap(),
+            "public TOut Convert(TIn value)"
+        );
+    }
+
+    #[test]
+    fn constraints_follow_csharp_order() {
+        let mut b = MetadataBuilder::new();
+        system_types(&mut b);
+        let disposable = b.add_type("System", "IDisposable") as i32;
+        let value_type = b.add_type("System", "ValueType") as i32;
+        let factory = b.add_type("Game", "Factory");
+        let t = 5;
+        let method = b.add_method(factory, "Create", t);
+        let parameters = b.make_generic(method, true, &[("T", 0x14), ("U", 0x18)]);
+        b.add_constraint(parameters, disposable);
+        b.add_constraint(parameters + 1, value_type);
+
+        let (metadata, types) = table(
+            &b,
+            vec![il2cpp_type(TypeKind::GenericParameter(parameters))],
+        );
+        assert_eq!(
+            method_signature(&metadata, method, &types).unwrap(),
+            "public T Create<T, U>() where T : class, System.IDisposable, new() where U : struct"
+        );
+    }
+
+    #[test]
+    fn modifiers_follow_the_flags() {
+        let mut b = MetadataBuilder::new();
+        let flags = [
+            (0x0001, 0, "public class"),
+            (0x0101, 0, "public sealed class"),
+            (0x0081, 0, "public abstract class"),
+            (0x0181, 0, "public static class"),
+            (0x0000, 0, "internal class"),
+            (0x0003, 0, "private class"),
+            (0x0007, 0, "protected internal class"),
+            (0x0101, 1, "public struct"),
+            (0x0101, 3, "public enum"),
+        ];
+        for &(type_flags, bitfield, _) in &flags {
+            let index = b.add_type("Game", "T");
+            b.type_definitions[index].flags = type_flags;
+            b.type_definitions[index].bitfield = bitfield;
+        }
+        let metadata = b.build();
+        let rendered: Vec<_> = metadata
+            .type_definitions
+            .iter()
+            .map(type_modifiers)
+            .collect();
+        let expected: Vec<_> = flags.iter().map(|&(_, _, modifiers)| modifiers).collect();
+        assert_eq!(rendered, expected);
+
+        assert_eq!(method_modifiers(0x0006), "public ");
+        assert_eq!(method_modifiers(0x0001), "private ");
+        assert_eq!(method_modifiers(0x0016), "public static ");
+        assert_eq!(method_modifiers(0x01C6), "public virtual ");
+        assert_eq!(method_modifiers(0x00C6), "public override ");
+        assert_eq!(method_modifiers(0x00E6), "public sealed override ");
+        assert_eq!(method_modifiers(0x05C6), "public abstract ");
+        assert_eq!(method_modifiers(0x04C4), "protected abstract override ");
+        assert_eq!(method_modifiers(0x2013), "internal static extern ");
+
+        assert_eq!(field_modifiers(0x0001), "private ");
+        assert_eq!(field_modifiers(0x0031), "private static readonly ");
+        assert_eq!(field_modifiers(0x0056), "public const ");
+        assert_eq!(field_modifiers(0x0024), "protected readonly ");
+    }
+
+    #[test]
+    fn attributes_render_arguments_in_order() {
+        let mut b = MetadataBuilder::new();
+        system_types(&mut b);
+        let mode = b.add_type("Game", "Mode");
+        b.type_definitions[mode].bitfield = 3;
+        b.type_definitions[mode].element_type_index = INT;
+        let range = b.add_type("Game", "RangeAttribute");
+        let range_constructor = b.add_method(range, ".ctor", INT);
+        let set_label = b.add_method(range, "set_Label", INT);
+        b.add_parameter(set_label, "value", STRING);
+        b.add_field(range, "Step", INT);
+        b.add_property(range, "Label", None, Some(set_label));
+        let obsolete = b.add_type("System", "ObsoleteAttribute");
+        let obsolete_constructor = b.add_method(obsolete, ".ctor", INT);
+        let player = b.add_type("Game", "Player");
+        let mut blob = vec![0x02];
+        blob.extend((range_constructor as u32).to_le_bytes());
+        blob.extend((obsolete_constructor as u32).to_le_bytes());
+        blob.extend([0x04, 0x01, 0x01]);
+        // 1, (Game.Mode)2, typeof(string) and new[] { 1, 2 }.
+        blob.extend([0x08, 0x02]);
+        blob.extend([0x55, 0x04, 0x04]);
+        blob.extend([0xFF, 0x02]);
+        blob.extend([0x1D, 0x04, 0x08, 0x00, 0x02, 0x04]);
+        // Step = 5 and Label = "x", each by its index in the attribute type.
+        blob.extend([0x08, 0x0A, 0x00]);
+        blob.extend([0x0E, 0x02, b'x', 0x00]);
+        blob.extend([0x00, 0x00, 0x00]);
+        let token = b.type_definitions[player].token;
+        b.attribute(token, &blob);
+
+        let (metadata, types) = table(&b, Vec::new());
+        assert_eq!(
+            type_attributes(&metadata, player, &types),
+            [
+                "[Range(1, (Game.Mode)2, typeof(string), new[] { 1, 2 }, Step = 5, Label = \"x\")]",
+                "[Obsolete]",
+                "[Token(Token = \"0x2000006\")]",
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_types_are_named_through_their_outer_types() {
+        let mut b = MetadataBuilder::new();
+        system_types(&mut b);
+        let outer = b.add_type("Game", "Outer`1");
+        b.make_generic(outer, false, &[("T", 0)]);
+        let inner = b.add_type("", "Inner");
+        let tag = b.add_type("", "TagAttribute");
+        let tag_constructor = b.add_method(tag, ".ctor", INT);
+        let deeper = b.add_type("", "Deeper");
+        b.nest(outer, inner);
+        b.nest(outer, tag);
+        b.nest(inner, deeper);
+        let mut blob = vec![0x01];
+        blob.extend((tag_constructor as u32).to_le_bytes());
+        blob.extend([0x00, 0x00, 0x00]);
+        let token = b.type_definitions[deeper].token;
+        b.attribute(token, &blob);
+
+        let (metadata, types) = table(&b, Vec::new());
+        assert_eq!(nested_type_name(&metadata, deeper), "Outer<T>.Inner.Deeper");
+        assert_eq!(
+            full_type_name(&metadata, deeper),
+            "Game.Outer<T>.Inner.Deeper"
+        );
+        assert_eq!(type_namespace(&metadata, deeper), "Game");
+        assert_eq!(
+            generic_instance_name(&metadata, outer, &["int".to_string()]),
+            "Game.Outer<int>"
+        );
+        assert_eq!(
+            type_attributes(&metadata, deeper, &types)[0],
+            "[Outer<T>.Tag]"
+        );
+    }
+}
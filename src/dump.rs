@@ -0,0 +1,293 @@
+use crate::csharp::{self, METHOD_ATTRIBUTE_ABSTRACT, TypeResolver};
+use crate::metadata::GlobalMetadata;
+
+/// C# pseudo-source in the spirit of Il2CppDumper's dump.cs: a section per
+/// image, then every type it defines with its attributes, fields, properties,
+/// events and methods. Nested types follow as `Outer.Inner`.
+pub fn dump_cs(metadata: &GlobalMetadata, types: &dyn TypeResolver) -> String {
+    let mut out = String::new();
+    for (image_index, image) in metadata.images.iter().enumerate() {
+        out += &format!(
+            "// Image {}: {} - {}\n",
+            image_index,
+            metadata.get_string(image.name_index).unwrap_or(""),
+            image.type_start
+        );
+        let Ok(start) = usize::try_from(image.type_start) else {
+            continue;
+        };
+        let end = (start + image.type_count as usize).min(metadata.type_definitions.len());
+        for type_index in start..end {
+            out += "\n";
+            out += &type_source(metadata, type_index, types);
+        }
+        out += "\n";
+    }
+    out
+}
+
+fn type_source(metadata: &GlobalMetadata, type_index: usize, types: &dyn TypeResolver) -> String {
+    let type_def = &metadata.type_definitions[type_index];
+    let mut out = format!(
+        "// Namespace: {}\n",
+        csharp::type_namespace(metadata, type_index)
+    );
+    for attribute in csharp::type_attributes(metadata, type_index, types) {
+        out += &format!("{}\n", attribute);
+    }
+
+    let mut bases = Vec::new();
+    if type_def.is_enum() {
+        // Only spelled out when it is not the default.
+        let underlying = types.type_name(metadata, type_def.element_type_index);
+        if underlying != "int" {
+            bases.push(underlying);
+        }
+    } else if !type_def.is_value_type() && type_def.parent_index >= 0 {
+        let parent = types.type_name(metadata, type_def.parent_index);
+        if parent != "object" {
+            bases.push(parent);
+        }
+    }
+    bases.extend(
+        metadata
+            .type_interfaces(type_def)
+            .iter()
+            .map(|&interface| types.type_name(metadata, interface)),
+    );
+    let outer = metadata
+        .declaring_type(type_index)
+        .map(|outer| format!("{}.", csharp::nested_type_name(metadata, outer)))
+        .unwrap_or_default();
+    out += &format!(
+        "{} {}{} // TypeDefIndex: {}\n{{\n",
+        csharp::type_modifiers(type_def),
+        outer,
+        csharp::type_declaration(metadata, type_def, &bases, types),
+        type_index
+    );
+
+    // Blank-line separated, as Il2CppDumper lays them out.
+    let mut sections = Vec::new();
+
+    let field_start = usize::try_from(type_def.field_start).unwrap_or(0);
+    let mut fields = String::new();
+    for field_index in field_start..field_start + metadata.type_fields(type_def).len() {
+        let field = &metadata.fields[field_index];
+        if type_def.is_enum() {
+            // The instance field holding the value is implied by `enum`.
+            let name = metadata.field_name(field).unwrap_or("");
+            if name == "value__" {
+                continue;
+            }
+            match csharp::field_default(metadata, field_index as i32, types) {
+                Some(value) => fields += &format!("\t{} = {},\n", name, value),
+                None => fields += &format!("\t{},\n", name),
+            }
+            continue;
+        }
+        for attribute in csharp::field_attributes(metadata, type_index, field_index, types) {
+            fields += &format!("\t{}\n", attribute);
+        }
+        if let Some(declaration) = csharp::field_declaration(metadata, field_index, types) {
+            fields += &format!("\t{};\n", declaration);
+        }
+    }
+    if !fields.is_empty() {
+        sections.push(format!("\t// Fields\n{}", fields));
+    }
+
+    let mut properties = String::new();
+    for property in metadata.type_properties(type_def) {
+        properties += &format!(
+            "\t{}\n\t{}\n",
+            csharp::token_attribute(property.token),
+            csharp::property_declaration(metadata, type_def, property, types)
+        );
+    }
+    if !properties.is_empty() {
+        sections.push(format!("\t// Properties\n{}", properties));
+    }
+
+    let mut events = String::new();
+    for event in metadata.type_events(type_def) {
+        events += &format!(
+            "\t{}\n\t{};\n",
+            csharp::token_attribute(event.token),
+            csharp::event_declaration(metadata, type_def, event, types)
+        );
+    }
+    if !events.is_empty() {
+        sections.push(format!("\t// Events\n{}", events));
+    }
+
+    let accessors = metadata.accessor_methods(type_def);
+    let method_start = usize::try_from(type_def.method_start).unwrap_or(0);
+    let mut methods = String::new();
+    for method_index in method_start..method_start + metadata.type_methods(type_def).len() {
+        if accessors.contains(&method_index) {
+            continue;
+        }
+        let Some(signature) = csharp::method_signature(metadata, method_index, types) else {
+            continue;
+        };
+        methods += "\n";
+        for attribute in csharp::method_attributes(metadata, method_index, types) {
+            methods += &format!("\t{}\n", attribute);
+        }
+        if let Some(address) = types.method_address(method_index) {
+            methods += &format!(
+                "\t// RVA: 0x{:X} Offset: 0x{:X} VA: 0x{:X}\n",
+                address.rva, address.offset, address.va
+            );
+        }
+        let body = if metadata.methods[method_index].flags & METHOD_ATTRIBUTE_ABSTRACT != 0 {
+            ";"
+        } else {
+            " { }"
+        };
+        methods += &format!("\t{}{}\n", signature, body);
+    }
+    if !methods.is_empty() {
+        sections.push(format!("\t// Methods\n{}", methods));
+    }
+
+    out += &sections.join("\n");
+    out += "}\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registration::CodeAddress;
+    use crate::testing::{MetadataBuilder, il2cpp_type, registration};
+    use crate::types::{Il2CppType, TypeKind, TypeTable};
+
+    const VOID: i32 = 0;
+    const INT: i32 = 1;
+    const ACTION: i32 = 3;
+
+    fn with_attrs(index: usize, attrs: u16) -> Il2CppType {
+        Il2CppType {
+            attrs,
+            ..il2cpp_type(TypeKind::Definition(index))
+        }
+    }
+
+    #[test]
+    fn types_are_dumped_with_their_members() {
+        let mut b = MetadataBuilder::new();
+        b.add_type("System", "Void");
+        b.add_type("System", "Int32");
+        b.add_type("System", "String");
+        b.add_type("System", "Action");
+        let (const_int, private_string, t) = (7, 8, 9);
+
+        let pool = b.add_type("Game", "IPool`1");
+        b.type_definitions[pool].flags = 0xA1;
+        let parameter = b.make_generic(pool, false, &[("T", 0x5)]);
+        let take = b.add_method(pool, "Take", t);
+        b.methods[take].flags = 0x5C6;
+
+        let player = b.add_type("Game", "Player");
+        b.type_definitions[player].flags = 0x101;
+        let max = b.add_field(player, "Max", const_int);
+        b.field_default(max, INT, Some(&[0x14]));
+        b.add_field(player, "name", private_string);
+        let get_hp = b.add_method(player, "get_Hp", INT);
+        let set_hp = b.add_method(player, "set_Hp", VOID);
+        b.add_parameter(set_hp, "value", INT);
+        let add = b.add_method(player, "add_OnDeath", VOID);
+        b.add_parameter(add, "value", ACTION);
+        let remove = b.add_method(player, "remove_OnDeath", VOID);
+        b.add_parameter(remove, "value", ACTION);
+        let attack = b.add_method(player, "Attack", VOID);
+        let damage = b.add_parameter(attack, "damage", INT);
+        b.parameter_default(damage, INT, Some(&[0x06]));
+        b.add_property(player, "Hp", Some(get_hp), Some(set_hp));
+        b.add_event(player, "OnDeath", ACTION, add, remove);
+
+        let state = b.add_type("", "State");
+        b.type_definitions[state].flags = 0x102;
+        b.type_definitions[state].bitfield = 3;
+        b.type_definitions[state].element_type_index = INT;
+        b.add_field(state, "value__", INT);
+        for (name, value) in [("Idle", 0x00), ("Dead", 0x02)] {
+            let field = b.add_field(state, name, state as i32);
+            b.field_default(field, state as i32, Some(&[value]));
+        }
+        b.nest(player, state);
+        let metadata = b.build();
+
+        let mut types: Vec<_> = (0..metadata.type_definitions.len())
+            .map(|index| il2cpp_type(TypeKind::Definition(index)))
+            .collect();
+        types.push(with_attrs(1, 0x0056));
+        types.push(with_attrs(2, 0x0001));
+        types.push(il2cpp_type(TypeKind::GenericParameter(parameter)));
+        let mut registration = registration(types);
+        registration.method_pointers.insert(
+            attack,
+            CodeAddress {
+                rva: 0x1234,
+                offset: 0x834,
+                va: 0x1_8000_1234,
+            },
+        );
+        let types = TypeTable::from_registration(&metadata, registration);
+
+        let dump = dump_cs(&metadata, &types);
+        let game = dump.find("// Namespace: Game").unwrap();
+        assert!(dump.starts_with("// Image 0: Test.dll - 0\n\n// Namespace: System\n"));
+        assert_eq!(
+            dump[game..].lines().collect::<Vec<_>>(),
+            [
+                "// Namespace: Game",
+                "[Token(Token = \"0x2000005\")]",
+                "public interface IPool<out T> where T : class // TypeDefIndex: 4",
+                "{",
+                "\t// Methods",
+                "",
+                "\t[Token(Token = \"0x6000001\")]",
+                "\tpublic abstract T Take();",
+                "}",
+                "",
+                "// Namespace: Game",
+                "[Token(Token = \"0x2000006\")]",
+                "public sealed class Player // TypeDefIndex: 5",
+                "{",
+                "\t// Fields",
+                "\t[Token(Token = \"0x4000001\")]",
+                "\tpublic const int Max = 10;",
+                "\t[Token(Token = \"0x4000002\")]",
+                "\tprivate string name;",
+                "",
+                "\t// Properties",
+                "\t[Token(Token = \"0x17000001\")]",
+                "\tpublic int Hp { get; set; }",
+                "",
+                "\t// Events",
+                "\t[Token(Token = \"0x14000001\")]",
+                "\tpublic event System.Action OnDeath;",
+                "",
+                "\t// Methods",
+                "",
+                "\t[Token(Token = \"0x6000006\")]",
+                "\t// RVA: 0x1234 Offset: 0x834 VA: 0x180001234",
+                "\tpublic void Attack(int damage = 3) { }",
+                "}",
+                "",
+                "// Namespace: Game",
+                "[Token(Token = \"0x2000007\")]",
+                "public enum Player.State // TypeDefIndex: 6",
+                "{",
+                "\t// Fields",
+                "\tIdle = 0,",
+                "\tDead = 1,",
+                "}",
+                "",
+            ]
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use crate::utils::{read_u16, read_u16_be, read_u32, read_u32_be, read_u64, read_u64_be};
+
+const ELF_MAGIC: &[u8] = b"\x7FELF";
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PF_X: u32 = 0x1;
+
+pub const EM_386: u16 = 3;
+pub const EM_ARM: u16 = 40;
+pub const EM_X86_64: u16 = 62;
+pub const EM_AARCH64: u16 = 183;
+
+const SHT_RELA: u32 = 4;
+const DT_NULL: u64 = 0;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+const R_X86_64_RELATIVE: u32 = 8;
+const R_AARCH64_RELATIVE: u32 = 1027;
+
+struct ElfReader<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+impl ElfReader<'_> {
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        if self.big_endian {
+            read_u16_be(self.data, offset)
+        } else {
+            read_u16(self.data, offset)
+        }
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        if self.big_endian {
+            read_u32_be(self.data, offset)
+        } else {
+            read_u32(self.data, offset)
+        }
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, String> {
+        if self.big_endian {
+            read_u64_be(self.data, offset)
+        } else {
+            read_u64(self.data, offset)
+        }
+    }
+
+    // Address and offset sized fields: 4 bytes in ELF32, 8 bytes in ELF64.
+    fn word(&self, offset: usize) -> Result<u64, String> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    // Where entry `index` of a table at `start` begins. All three come from
+    // the file, so a crafted header errors out here instead of overflowing.
+    fn entry(&self, start: u64, index: u64, size: u64) -> Result<usize, String> {
+        index
+            .checked_mul(size)
+            .and_then(|at| at.checked_add(start))
+            .and_then(|at| usize::try_from(at).ok())
+            .filter(|&at| at <= self.data.len())
+            .ok_or_else(|| {
+                format!(
+                    "Table entry out of bounds: {} + {} * {} > {}",
+                    start,
+                    index,
+                    size,
+                    self.data.len()
+                )
+            })
+    }
+}
+
+pub struct SectionHeader {
+    pub name: String,
+    pub sh_type: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl SectionHeader {
+    pub fn is_searchable(&self) -> bool {
+        self.sh_type == SHT_PROGBITS
+            && self.flags & SHF_ALLOC != 0
+            && self.flags & SHF_EXECINSTR == 0
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.flags & SHF_EXECINSTR != 0
+    }
+}
+
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub filesz: u64,
+}
+
+pub struct ElfFile {
+    pub is_64: bool,
+    pub machine: u16,
+    pub sections: Vec<SectionHeader>,
+    pub segments: Vec<ProgramHeader>,
+    // (address, value) for each relative relocation. Position independent
+    // ELF64 libraries leave their data pointers zero on disk and have the
+    // loader write them from these.
+    pub relocations: Vec<(u64, u64)>,
+}
+
+impl ElfFile {
+    pub fn is_elf(data: &[u8]) -> bool {
+        data.starts_with(ELF_MAGIC)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if !Self::is_elf(data) {
+            return Err("Not an ELF file: missing \\x7FELF signature".to_string());
+        }
+        let is_64 = match data.get(4) {
+            Some(&ELFCLASS32) => false,
+            Some(&ELFCLASS64) => true,
+            other => return Err(format!("Unknown ELF class: {:?}", other)),
+        };
+        let big_endian = match data.get(5) {
+            Some(&ELFDATA2LSB) => false,
+            Some(&ELFDATA2MSB) => true,
+            other => return Err(format!("Unknown ELF data encoding: {:?}", other)),
+        };
+        let r = ElfReader {
+            data,
+            is_64,
+            big_endian,
+        };
+
+        let machine = r.u16(18)?;
+        let (phoff, shoff, sizes) = if is_64 {
+            (r.u64(32)?, r.u64(40)?, 54)
+        } else {
+            (r.u32(28)? as u64, r.u32(32)? as u64, 42)
+        };
+        let phentsize = r.u16(sizes)? as u64;
+        let phnum = r.u16(sizes + 2)? as usize;
+        let shentsize = r.u16(sizes + 4)? as u64;
+        let shnum = r.u16(sizes + 6)? as usize;
+        let shstrndx = r.u16(sizes + 8)? as usize;
+
+        let mut segments = Vec::with_capacity(phnum);
+        for i in 0..phnum {
+            segments.push(read_program_header(
+                &r,
+                r.entry(phoff, i as u64, phentsize)?,
+            )?);
+        }
+
+        // Stripped or packed libraries may carry a bogus section table; the
+        // segments are still enough to locate the blob, so it is not fatal.
+        let sections =
+            read_section_headers(&r, shoff, shentsize, shnum, shstrndx).unwrap_or_default();
+
+        let mut elf = Self {
+            is_64,
+            machine,
+            sections,
+            segments,
+            relocations: Vec::new(),
+        };
+        if is_64 {
+            elf.relocations = elf.read_relocations(&r).unwrap_or_default();
+        }
+        Ok(elf)
+    }
+
+    // ELF32 targets use REL, whose addends already sit in the pointers, so
+    // only ELF64 RELA tables need applying.
+    fn read_relocations(&self, r: &ElfReader) -> Result<Vec<(u64, u64)>, String> {
+        let relative = match self.machine {
+            EM_X86_64 => R_X86_64_RELATIVE,
+            EM_AARCH64 => R_AARCH64_RELATIVE,
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut tables = Vec::new();
+        if let Some(dynamic) = self.segments.iter().find(|s| s.p_type == PT_DYNAMIC) {
+            let (mut rela, mut size, mut entry) = (0, 0, 24);
+            for i in 0..dynamic.filesz / 16 {
+                let at = r.entry(dynamic.offset, i, 16)?;
+                match r.u64(at)? {
+                    DT_NULL => break,
+                    DT_RELA => rela = r.u64(at + 8)?,
+                    DT_RELASZ => size = r.u64(at + 8)?,
+                    DT_RELAENT => entry = r.u64(at + 8)?,
+                    _ => {}
+                }
+            }
+            if let Some(offset) = self.offset_for_vaddr(rela).filter(|_| size > 0) {
+                tables.push((offset, size, entry));
+            }
+        }
+        // Without a dynamic segment the section table is the only index.
+        if tables.is_empty() {
+            tables = self
+                .sections
+                .iter()
+                .filter(|s| s.sh_type == SHT_RELA)
+                .map(|s| (s.offset, s.size, 24))
+                .collect();
+        }
+
+        let mut relocations = Vec::new();
+        for (offset, size, entry) in tables {
+            if entry < 24 {
+                continue;
+            }
+            for i in 0..size / entry {
+                let at = r.entry(offset, i, entry)?;
+                if r.u64(at + 8)? as u32 == relative {
+                    relocations.push((r.u64(at)?, r.u64(at + 16)?));
+                }
+            }
+        }
+        Ok(relocations)
+    }
+
+    pub fn machine_name(&self) -> &'static str {
+        match self.machine {
+            EM_386 => "x86",
+            EM_ARM => "arm",
+            EM_X86_64 => "x86_64",
+            EM_AARCH64 => "arm64",
+            _ => "unknown",
+        }
+    }
+
+    pub fn offset_for_vaddr(&self, vaddr: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .filter(|s| s.p_type == PT_LOAD)
+            .find(|s| vaddr >= s.vaddr && vaddr - s.vaddr < s.filesz)
+            .and_then(|s| s.offset.checked_add(vaddr - s.vaddr))
+    }
+}
+
+fn read_program_header(r: &ElfReader, offset: usize) -> Result<ProgramHeader, String> {
+    if r.is_64 {
+        Ok(ProgramHeader {
+            p_type: r.u32(offset)?,
+            flags: r.u32(offset + 4)?,
+            offset: r.u64(offset + 8)?,
+            vaddr: r.u64(offset + 16)?,
+            filesz: r.u64(offset + 32)?,
+        })
+    } else {
+        Ok(ProgramHeader {
+            p_type: r.u32(offset)?,
+            offset: r.u32(offset + 4)? as u64,
+            vaddr: r.u32(offset + 8)? as u64,
+            filesz: r.u32(offset + 16)? as u64,
+            flags: r.u32(offset + 24)?,
+        })
+    }
+}
+
+fn read_section_headers(
+    r: &ElfReader,
+    shoff: u64,
+    shentsize: u64,
+    shnum: usize,
+    shstrndx: usize,
+) -> Result<Vec<SectionHeader>, String> {
+    let (flags_at, addr_at, offset_at, size_at) = if r.is_64 {
+        (8, 16, 24, 32)
+    } else {
+        (8, 12, 16, 20)
+    };
+
+    let mut raw = Vec::with_capacity(shnum);
+    for i in 0..shnum {
+        let base = r.entry(shoff, i as u64, shentsize)?;
+        raw.push((
+            r.u32(base)?,
+            SectionHeader {
+                name: String::new(),
+                sh_type: r.u32(base + 4)?,
+                flags: r.word(base + flags_at)?,
+                addr: r.word(base + addr_at)?,
+                offset: r.word(base + offset_at)?,
+                size: r.word(base + size_at)?,
+            },
+        ));
+    }
+
+    let names = raw
+        .get(shstrndx)
+        .and_then(|(_, s)| {
+            r.data
+                .get(s.offset as usize..s.offset.saturating_add(s.size) as usize)
+        })
+        .unwrap_or_default();
+
+    Ok(raw
+        .into_iter()
+        .map(|(name_offset, mut section)| {
+            section.name = names
+                .get(name_offset as usize..)
+                .map(|rest| {
+                    let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+                    String::from_utf8_lossy(&rest[..end]).into_owned()
+                })
+                .unwrap_or_default();
+            section
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An ELF64 little-endian x86-64 header with the given program header
+    // table and no sections.
+    fn header(phoff: u64, phentsize: u16, phnum: u16) -> Vec<u8> {
+        let mut data = vec![0; 64];
+        data[..4].copy_from_slice(ELF_MAGIC);
+        data[4] = ELFCLASS64;
+        data[5] = ELFDATA2LSB;
+        data[18..20].copy_from_slice(&EM_X86_64.to_le_bytes());
+        data[32..40].copy_from_slice(&phoff.to_le_bytes());
+        data[54..56].copy_from_slice(&phentsize.to_le_bytes());
+        data[56..58].copy_from_slice(&phnum.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn program_header_table_past_the_address_space_is_an_error() {
+        let data = header(u64::MAX - 8, 56, 2);
+        assert!(ElfFile::parse(&data).is_err());
+    }
+
+    #[test]
+    fn dynamic_segment_past_the_address_space_is_ignored() {
+        let mut data = header(64, 56, 1);
+        let mut segment = vec![0; 56];
+        segment[..4].copy_from_slice(&PT_DYNAMIC.to_le_bytes());
+        segment[8..16].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        segment[32..40].copy_from_slice(&64u64.to_le_bytes());
+        data.extend(segment);
+        let elf = ElfFile::parse(&data).unwrap();
+        assert!(elf.relocations.is_empty());
+    }
+}
@@ -1,50 +1,478 @@
+use crate::binary::Binary;
+use crate::cipher::{DecryptConfig, MetadataCipher};
+use crate::keysearch::{self, SearchReport};
+use crate::metadata::{self, GlobalMetadata, GlobalMetadataHeader};
+use crate::utils::{find_pattern, read_u32};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+pub use crate::binary::Format;
+
+pub(crate) const HEAD_PATTERN: &[u8] = b"C\0F\0G\0\0\0\0\0";
+const TAIL_PATTERN: &[u8] = b"\0\0\0\0";
+const MAX_ZERO_RUN_CANDIDATES: usize = 64;
+
+/// How the end of a blob was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// From the size stored before the marker.
+    SizeField,
+    /// At the nth run of zeros after the marker, the blob decrypting validly.
+    ZeroRun(usize),
+    /// At the end of the section, the blob decrypting validly.
+    SectionEnd,
+    /// At the first run of zeros, no end decrypting validly.
+    Unvalidated,
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Boundary::SizeField => write!(f, "size field before marker"),
+            Boundary::ZeroRun(n) => write!(f, "zero run #{} (validated)", n),
+            Boundary::SectionEnd => write!(f, "section end (validated)"),
+            Boundary::Unvalidated => write!(f, "first zero run (unvalidated)"),
+        }
+    }
+}
+
+/// Where a blob was found in the binary.
+pub struct BlobLocation {
+    /// The name of the section holding it.
+    pub section: String,
+    /// Its address, an RVA for PE and a VA otherwise.
+    pub address: u64,
+    /// Its offset in the file.
+    pub file_offset: usize,
+    /// The fat Mach-O slice holding it, if any.
+    pub slice: Option<String>,
+    /// How its end was found.
+    pub boundary: Boundary,
+}
+
+/// A blob behind a `CFG` marker, still encrypted, and how metadata-like it
+/// decrypts.
+pub struct Candidate {
+    /// Where it was found.
+    pub location: BlobLocation,
+    /// The blob, still encrypted.
+    pub data: Vec<u8>,
+    /// How metadata-like it decrypts; higher is better.
+    pub score: u32,
+}
+
+/// Finds the encrypted metadata blobs in an IL2CPP binary and picks the one
+/// to use.
 pub struct Extractor {
-    dll_path: String,
-    valid_data: Vec<u8>,
+    path: String,
+    file_data: Option<Vec<u8>>,
+    format: Option<Format>,
+    arch: String,
+    candidates: Vec<Candidate>,
+    selected: usize,
 }
 
 impl Extractor {
-    pub fn new<P: AsRef<Path>>(dll_path: P) -> Self {
+    /// An extractor for the binary at `path`, read when processed.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_string_lossy().into_owned(),
+            file_data: None,
+            format: None,
+            arch: String::new(),
+            candidates: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    /// An extractor for a binary already in memory, `name` standing in for
+    /// its path in messages.
+    pub fn from_bytes(name: String, data: Vec<u8>) -> Self {
         Self {
-            dll_path: dll_path.as_ref().to_string_lossy().into_owned(),
-            valid_data: Vec::new(),
+            path: name,
+            file_data: Some(data),
+            format: None,
+            arch: String::new(),
+            candidates: Vec::new(),
+            selected: 0,
         }
     }
 
-    pub fn process(&mut self) -> Result<(), String> {
-        self.extract_data_pattern()
+    /// The path, or the name given to `from_bytes`.
+    pub fn get_source(&self) -> &str {
+        &self.path
     }
 
+    /// Collects every candidate blob, trial-decrypting each with `config`,
+    /// and selects the best scoring one.
+    pub fn process(&mut self, config: &DecryptConfig) -> Result<(), String> {
+        let file_data = self.take_data()?;
+        let result = self.extract_data_pattern(&file_data, config);
+        self.file_data = Some(file_data);
+        result?;
+        self.selected = self.best_index().unwrap_or(0);
+        Ok(())
+    }
+
+    /// Searches the binary for the key the blob was encrypted with.
+    pub fn find_keys(
+        &mut self,
+        wordlist: &[Vec<u8>],
+        cipher: &dyn MetadataCipher,
+    ) -> Result<SearchReport, String> {
+        let file_data = self.take_data()?;
+        let result = keysearch::search(&file_data, wordlist, cipher);
+        self.file_data = Some(file_data);
+        result
+    }
+
+    /// The binary itself, once processed.
+    pub fn get_file_data(&self) -> &[u8] {
+        self.file_data.as_deref().unwrap_or_default()
+    }
+
+    /// The selected blob, still encrypted.
     pub fn get_valid_data(&self) -> &[u8] {
-        &self.valid_data
+        self.candidates
+            .get(self.selected)
+            .map(|c| c.data.as_slice())
+            .unwrap_or_default()
     }
 
-    fn extract_data_pattern(&mut self) -> Result<(), String> {
-        let mut f = File::open(&self.dll_path).map_err(|e| format!("File not found: {}", e))?;
-        let mut dll_data = Vec::new();
-        f.read_to_end(&mut dll_data).map_err(|e| e.to_string())?;
+    /// Where the selected blob was found.
+    pub fn get_location(&self) -> Option<&BlobLocation> {
+        self.candidates.get(self.selected).map(|c| &c.location)
+    }
 
-        let head_pattern = b"C\0F\0G\0\0\0\0\0";
-        let tail_pattern = b"\0\0\0\0";
+    /// The binary's format, once processed.
+    pub fn get_format(&self) -> Option<Format> {
+        self.format
+    }
 
-        let head_pos = dll_data
-            .windows(head_pattern.len())
-            .position(|window| window == head_pattern)
-            .ok_or("Head pattern not found")?;
+    /// The architecture of the binary, or of the slice the blob came from.
+    pub fn get_arch(&self) -> &str {
+        &self.arch
+    }
 
-        let start_pos = head_pos + head_pattern.len();
+    /// Every candidate blob, in the order found.
+    pub fn get_candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
 
-        let tail_pos = dll_data[start_pos..]
-            .windows(tail_pattern.len())
-            .position(|window| window == tail_pattern)
-            .map(|pos| pos + start_pos)
-            .ok_or("Tail pattern not found")?;
+    /// The index of the selected candidate.
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
 
-        self.valid_data = dll_data[start_pos..tail_pos].to_vec();
+    /// Picks another candidate than the best scoring one.
+    pub fn select(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.candidates.len() {
+            return Err(format!(
+                "Candidate {} out of range ({} found)",
+                index,
+                self.candidates.len()
+            ));
+        }
+        self.selected = index;
         Ok(())
     }
+
+    fn best_index(&self) -> Option<usize> {
+        self.candidates
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, c)| c.score)
+            .map(|(i, _)| i)
+    }
+
+    fn take_data(&mut self) -> Result<Vec<u8>, String> {
+        if let Some(data) = self.file_data.take() {
+            return Ok(data);
+        }
+        let mut f = File::open(&self.path).map_err(|e| format!("File not found: {}", e))?;
+        let mut data = Vec::new();
+        f.read_to_end(&mut data).map_err(|e| e.to_string())?;
+        Ok(data)
+    }
+
+    fn extract_data_pattern(
+        &mut self,
+        file_data: &[u8],
+        config: &DecryptConfig,
+    ) -> Result<(), String> {
+        let binary = Binary::parse(file_data)?;
+        self.format = Some(binary.format);
+        self.arch = binary.arch.clone();
+
+        self.candidates.clear();
+        for section in binary.searchable_sections() {
+            let raw = section.raw(file_data);
+
+            let mut search_pos = 0;
+            while let Some(found) = find_pattern(&raw[search_pos..], HEAD_PATTERN) {
+                let head_pos = search_pos + found;
+                let start_pos = head_pos + HEAD_PATTERN.len();
+                search_pos = start_pos;
+
+                let Some((end_pos, boundary, decrypted)) =
+                    find_blob_end(raw, head_pos, start_pos, config)
+                else {
+                    continue;
+                };
+                let data = raw[start_pos..end_pos].to_vec();
+                search_pos = end_pos;
+                let score = match decrypted {
+                    Some(plain) => score(&plain),
+                    None => config
+                        .decrypt(&data)
+                        .map(|plain| score(&plain))
+                        .unwrap_or(0),
+                };
+
+                self.candidates.push(Candidate {
+                    location: BlobLocation {
+                        section: section.name.clone(),
+                        address: section.address + start_pos as u64,
+                        file_offset: section.file_offset + start_pos,
+                        slice: section.slice.clone(),
+                        boundary,
+                    },
+                    data,
+                    score,
+                });
+            }
+        }
+
+        if self.candidates.is_empty() {
+            return Err("Head pattern not found in any data section".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Valid magic dominates the score; the header offsets break ties between
+// candidates that both decrypt to something metadata-shaped.
+fn score(plain: &[u8]) -> u32 {
+    let mut score = 0;
+    if metadata::has_valid_magic(plain) {
+        score += 100;
+    }
+    if let Ok(header) = GlobalMetadataHeader::parse(plain) {
+        if (16..=31).contains(&header.version) {
+            score += 10;
+        }
+        score += header
+            .sections()
+            .iter()
+            .filter(|&&(offset, size)| {
+                offset >= 0 && size >= 0 && (offset as usize + size as usize) <= plain.len()
+            })
+            .count() as u32;
+    }
+    score
+}
+
+type BlobEnd = (usize, Boundary, Option<Vec<u8>>);
+
+// Tries the boundary strategies from most to least trustworthy. Every
+// candidate except the last resort has to decrypt into metadata that parses.
+fn find_blob_end(
+    raw: &[u8],
+    head_pos: usize,
+    start: usize,
+    config: &DecryptConfig,
+) -> Option<BlobEnd> {
+    let ends = blob_ends(raw, head_pos);
+    for &(end, boundary) in &ends {
+        if let Some(plain) = validate(&raw[start..end], config) {
+            return Some((end, boundary, Some(plain)));
+        }
+    }
+    // A size field still pins the blob when the key is wrong.
+    if let Some(&(end, Boundary::SizeField)) = ends.first() {
+        return Some((end, Boundary::SizeField, None));
+    }
+
+    let first = find_pattern(&raw[start..], TAIL_PATTERN)?;
+    Some((start + first, Boundary::Unvalidated, None))
+}
+
+/// Every plausible end for the blob behind the marker at `head_pos`, in the
+/// order they should be trusted. A size field pins the blob exactly, so when
+/// there is one it is the only end. None of them are validated here.
+pub fn blob_ends(raw: &[u8], head_pos: usize) -> Vec<(usize, Boundary)> {
+    let start = head_pos + HEAD_PATTERN.len();
+    if let Some(size) = head_pos.checked_sub(4).and_then(|p| read_u32(raw, p).ok()) {
+        let end = start.saturating_add(size as usize);
+        if size >= 8 && size % 4 == 0 && end <= raw.len() {
+            return vec![(end, Boundary::SizeField)];
+        }
+    }
+
+    let mut ends = Vec::new();
+
+    let zero_runs = zero_run_ends(raw, start);
+    for (n, &end) in zero_runs.iter().take(MAX_ZERO_RUN_CANDIDATES).enumerate() {
+        ends.push((end, Boundary::ZeroRun(n + 1)));
+    }
+
+    let trimmed = raw[start..]
+        .iter()
+        .rposition(|&b| b != 0)
+        .map(|p| start + align4(p + 1))
+        .unwrap_or(start)
+        .min(raw.len());
+    if trimmed > start {
+        ends.push((trimmed, Boundary::SectionEnd));
+    }
+    ends
+}
+
+// Candidate ends at every zero run after the marker, rounded up to a whole
+// XXTEA word so ciphertext that happens to end in zero bytes is not clipped.
+fn zero_run_ends(raw: &[u8], start: usize) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut pos = start;
+    while let Some(found) = find_pattern(&raw[pos..], TAIL_PATTERN) {
+        let end = (start + align4(pos + found - start)).min(raw.len());
+        if end > start && ends.last() != Some(&end) {
+            ends.push(end);
+        }
+        pos += found + TAIL_PATTERN.len();
+        while pos < raw.len() && raw[pos] == 0 {
+            pos += 1;
+        }
+    }
+    ends
+}
+
+fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+fn validate(blob: &[u8], config: &DecryptConfig) -> Option<Vec<u8>> {
+    let decrypted = config.decrypt(blob).ok()?;
+    let valid = metadata::has_valid_magic(&decrypted)
+        && GlobalMetadata::parse(&decrypted).is_ok_and(|m| m.is_valid());
+    valid.then_some(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::Plain;
+    use crate::testing::{self, metadata_file};
+    use std::sync::Arc;
+
+    const PAIRS: usize = 31;
+
+    // `prefix`, the marker and `blob`, then zeros and filler so the blob
+    // does not run to the end of the section.
+    fn marked(prefix: &[u8], blob: &[u8]) -> Vec<u8> {
+        let mut bytes = prefix.to_vec();
+        bytes.extend_from_slice(HEAD_PATTERN);
+        bytes.extend_from_slice(blob);
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(&[0x22; 16]);
+        bytes
+    }
+
+    // A PE whose .data section holds one marked blob.
+    fn binary(prefix: &[u8], blob: &[u8]) -> Vec<u8> {
+        let section = marked(prefix, blob);
+        testing::pe(&section, section.len())
+    }
+
+    fn sized(size: usize) -> Vec<u8> {
+        let mut prefix = vec![0x11; 12];
+        prefix.extend((size as u32).to_le_bytes());
+        prefix
+    }
+
+    fn extract(data: Vec<u8>, config: &DecryptConfig) -> Extractor {
+        let mut extractor = Extractor::from_bytes("test".to_string(), data);
+        extractor.process(config).unwrap();
+        extractor
+    }
+
+    fn boundary(extractor: &Extractor) -> Boundary {
+        extractor.get_location().unwrap().boundary
+    }
+
+    #[test]
+    fn size_field_bounds_the_blob() {
+        let config = DecryptConfig::default();
+        let blob = config.encrypt(&metadata_file(29, PAIRS, &[])).unwrap();
+        let extractor = extract(binary(&sized(blob.len()), &blob), &config);
+        assert_eq!(boundary(&extractor), Boundary::SizeField);
+        assert_eq!(extractor.get_valid_data(), blob);
+    }
+
+    #[test]
+    fn size_field_is_the_only_end_tried() {
+        let config = DecryptConfig::default();
+        let blob = config.encrypt(&metadata_file(29, PAIRS, &[])).unwrap();
+        // The zero run after the blob would validate; the size field wins.
+        let extractor = extract(binary(&sized(blob.len() - 8), &blob), &config);
+        assert_eq!(boundary(&extractor), Boundary::SizeField);
+        assert_eq!(extractor.get_valid_data(), &blob[..blob.len() - 8]);
+
+        let wrong = DecryptConfig::with_key(b"wrong".to_vec());
+        let extractor = extract(binary(&sized(blob.len()), &blob), &wrong);
+        assert_eq!(boundary(&extractor), Boundary::SizeField);
+        assert_eq!(extractor.get_valid_data(), blob);
+    }
+
+    #[test]
+    fn zero_runs_are_tried_until_one_validates() {
+        let config = DecryptConfig::default().with_cipher(Arc::new(Plain));
+        // Unencrypted, the header's empty sections are zero runs of their own.
+        let plain = metadata_file(29, PAIRS, &[(PAIRS - 1, b"tail")]);
+        let extractor = extract(binary(&[0x11; 16], &plain), &config);
+        assert!(matches!(boundary(&extractor), Boundary::ZeroRun(n) if n > 1));
+        assert_eq!(extractor.get_valid_data(), plain);
+    }
+
+    #[test]
+    fn unvalidated_blob_ends_at_the_first_zero_run() {
+        let config = DecryptConfig::default();
+        let blob: Vec<u8> = (1..=64).collect();
+        let extractor = extract(binary(&[0x11; 16], &blob), &config);
+        assert_eq!(boundary(&extractor), Boundary::Unvalidated);
+        assert_eq!(extractor.get_valid_data(), blob);
+    }
+
+    #[test]
+    fn valid_candidate_outscores_garbage() {
+        let config = DecryptConfig::default();
+        let blob = config.encrypt(&metadata_file(29, PAIRS, &[])).unwrap();
+        let garbage: Vec<u8> = (1..=64).collect();
+        let mut section = marked(&[0x11; 16], &garbage);
+        section.extend(marked(&sized(blob.len()), &blob));
+        let extractor = extract(testing::pe(&section, section.len()), &config);
+
+        let candidates = extractor.get_candidates();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].score, 0);
+        assert!(candidates[1].score > 100);
+        assert_eq!(extractor.selected_index(), 1);
+        assert_eq!(extractor.get_valid_data(), blob);
+    }
+
+    #[test]
+    fn tied_candidates_keep_the_earliest() {
+        let config = DecryptConfig::default();
+        let blob = config.encrypt(&metadata_file(29, PAIRS, &[])).unwrap();
+        let mut section = marked(&sized(blob.len()), &blob);
+        section.extend(marked(&sized(blob.len()), &blob));
+        let extractor = extract(testing::pe(&section, section.len()), &config);
+
+        let candidates = extractor.get_candidates();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].score, candidates[1].score);
+        assert_eq!(extractor.selected_index(), 0);
+    }
 }
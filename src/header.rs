@@ -0,0 +1,672 @@
+use crate::csharp::{
+    self, FIELD_ATTRIBUTE_LITERAL, FIELD_ATTRIBUTE_STATIC, METHOD_ATTRIBUTE_STATIC, TypeResolver,
+};
+use crate::metadata::{ElementType, GlobalMetadata};
+use crate::types::{self, Il2CppType, TypeKind, TypeTable};
+use std::collections::{HashMap, HashSet};
+
+// Value types nest by value; anything deeper than this is a bad table.
+const MAX_DEPTH: usize = 16;
+
+// Words the header's consumers reserve, which Il2CppDumper prefixes with an
+// underscore when a member is named after one.
+const RESERVED: [&str; 48] = [
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "int",
+    "long",
+    "register",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "inline",
+    "restrict",
+    "bool",
+    "class",
+    "delete",
+    "new",
+    "private",
+    "protected",
+    "public",
+    "template",
+    "this",
+    "throw",
+    "virtual",
+    "friend",
+    "operator",
+    "namespace",
+];
+
+const PRELUDE: &str = "// Class structs (`_c`) are forward-declared only: their layout depends on
+// the runtime version, so they can only be used through pointers.
+typedef void(*Il2CppMethodPointer)();
+typedef uintptr_t il2cpp_array_size_t;
+typedef int32_t il2cpp_array_lower_bound_t;
+struct Il2CppClass;
+struct Il2CppType;
+struct MethodInfo;
+
+struct Il2CppObject {
+\tIl2CppClass *klass;
+\tvoid *monitor;
+};
+
+struct Il2CppArrayBounds {
+\til2cpp_array_size_t length;
+\til2cpp_array_lower_bound_t lower_bound;
+};
+";
+
+// `System.Collections.Generic.List<int>` as the identifier
+// `System_Collections_Generic_List_int_`, the way Il2CppDumper names structs
+// and functions.
+pub fn fix_name(name: &str) -> String {
+    let mut fixed: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if RESERVED.contains(&fixed.as_str()) || fixed.starts_with(|c: char| c.is_ascii_digit()) {
+        fixed.insert(0, '_');
+    }
+    fixed
+}
+
+// Il2CppDumper's il2cpp.h: per type a `_Fields` struct that extends the
+// parent's, the `_o` object layout built on it and the `_StaticFields`, in
+// an order where every value type is complete before it is embedded. Class
+// structs are only declared, since their layout depends on the runtime
+// version.
+pub struct Header<'a> {
+    metadata: &'a GlobalMetadata,
+    types: &'a TypeTable,
+    names: Vec<String>,
+    declared: Vec<String>,
+    declared_set: HashSet<String>,
+    defined: HashSet<String>,
+    body: String,
+    // Static fields go last: nothing embeds them, while they may embed any
+    // value type, including one still being defined.
+    statics: String,
+}
+
+impl<'a> Header<'a> {
+    pub fn new(metadata: &'a GlobalMetadata, types: &'a TypeTable) -> Self {
+        // Types sharing a full name across images keep theirs apart by index.
+        let mut seen = HashMap::new();
+        let names = (0..metadata.type_definitions.len())
+            .map(|index| {
+                let name = fix_name(&csharp::full_type_name(metadata, index));
+                match seen.insert(name.clone(), index) {
+                    Some(_) => format!("{}_{}", name, index),
+                    None => name,
+                }
+            })
+            .collect();
+        Self {
+            metadata,
+            types,
+            names,
+            declared: Vec::new(),
+            declared_set: HashSet::new(),
+            defined: HashSet::new(),
+            body: String::new(),
+            statics: String::new(),
+        }
+    }
+
+    // The structs of every type definition and every generic instance in
+    // the binary's type table, after declarations of everything they refer
+    // to.
+    pub fn finish(mut self) -> String {
+        for index in 0..self.metadata.type_definitions.len() {
+            let ty = definition(index);
+            self.define(&ty, 0);
+        }
+        let instances: Vec<Il2CppType> = self
+            .types
+            .registration()
+            .map(|registration| {
+                registration
+                    .types
+                    .iter()
+                    .filter(|ty| matches!(ty.kind, TypeKind::GenericInstance(..)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for ty in &instances {
+            self.define(ty, 0);
+        }
+
+        let mut out = String::from(PRELUDE);
+        out += "\n";
+        for name in &self.declared {
+            out += &format!("struct {};\n", name);
+        }
+        out += &self.body;
+        out += &self.statics;
+        out
+    }
+
+    pub fn struct_name(&self, ty: &Il2CppType) -> String {
+        match &ty.kind {
+            TypeKind::Definition(index) => self.names[*index].clone(),
+            TypeKind::GenericInstance(..) => fix_name(&self.types.name(self.metadata, ty)),
+            TypeKind::Primitive(element) => match types::system_name(*element) {
+                Some(name) => format!("System_{}", name),
+                None => "Il2CppObject".to_string(),
+            },
+            TypeKind::Array(element, _) | TypeKind::SzArray(element) => {
+                format!("{}_array", self.struct_name(element))
+            }
+            TypeKind::Pointer(_) | TypeKind::GenericParameter(_) | TypeKind::Unknown(_) => {
+                "Il2CppObject".to_string()
+            }
+        }
+    }
+
+    // `int32_t Game_Player__Damage (Game_Player_o* __this, int32_t amount,
+    // const MethodInfo* method);`
+    pub fn method_signature(&mut self, method_index: usize) -> Option<String> {
+        let metadata = self.metadata;
+        let method = metadata.methods.get(method_index)?;
+        let type_index = usize::try_from(method.declaring_type).ok()?;
+        let owner = self.names.get(type_index)?.clone();
+        let mut parameters = Vec::new();
+        if method.flags & METHOD_ATTRIBUTE_STATIC == 0 {
+            parameters.push(format!("{}* __this", self.object_name(&owner)));
+        }
+        for parameter in metadata.method_parameters(method) {
+            parameters.push(format!(
+                "{} {}",
+                self.index_type(parameter.type_index),
+                fix_name(metadata.parameter_name(parameter).unwrap_or(""))
+            ));
+        }
+        parameters.push("const MethodInfo* method".to_string());
+        Some(format!(
+            "{} {}__{} ({});",
+            self.index_type(method.return_type),
+            owner,
+            fix_name(metadata.method_name(method).unwrap_or("")),
+            parameters.join(", ")
+        ))
+    }
+
+    // Il2CppDumper's calling convention string: one letter per argument
+    // after the return, `i` for anything passed in an integer register.
+    pub fn type_signature(&self, method_index: usize) -> Option<String> {
+        let method = self.metadata.methods.get(method_index)?;
+        let mut signature = String::new();
+        signature.push(self.signature_letter(method.return_type));
+        if method.flags & METHOD_ATTRIBUTE_STATIC == 0 {
+            signature.push('i');
+        }
+        for parameter in self.metadata.method_parameters(method) {
+            signature.push(self.signature_letter(parameter.type_index));
+        }
+        signature.push('i');
+        Some(signature)
+    }
+
+    fn signature_letter(&self, type_index: i32) -> char {
+        let Some(ty) = self.types.resolve(type_index) else {
+            return 'i';
+        };
+        if ty.byref {
+            return 'i';
+        }
+        match self.element(&ty) {
+            Some(ElementType::Void) => 'v',
+            Some(ElementType::I8 | ElementType::U8) => 'j',
+            Some(ElementType::R4) => 'f',
+            Some(ElementType::R8) => 'd',
+            _ => 'i',
+        }
+    }
+
+    // The built-in type behind a primitive, System type or enum.
+    fn element(&self, ty: &Il2CppType) -> Option<ElementType> {
+        match ty.kind {
+            TypeKind::Primitive(element) => Some(element),
+            TypeKind::Definition(index) => {
+                if let Some(element) = types::system_type(self.metadata, index) {
+                    return Some(element);
+                }
+                let type_def = &self.metadata.type_definitions[index];
+                if !type_def.is_enum() {
+                    return None;
+                }
+                match self.types.resolve(type_def.element_type_index)?.kind {
+                    TypeKind::Primitive(element) => Some(element),
+                    TypeKind::Definition(index) => types::system_type(self.metadata, index),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn index_type(&mut self, type_index: i32) -> String {
+        match self.types.resolve(type_index) {
+            Some(ty) => self.c_type(&ty, 0),
+            None => "void*".to_string(),
+        }
+    }
+
+    fn c_type(&mut self, ty: &Il2CppType, depth: usize) -> String {
+        let mut rendered = match (&ty.kind, self.element(ty)) {
+            (_, Some(ElementType::String)) => self.object_name("System_String") + "*",
+            (_, Some(element)) => primitive(element).to_string(),
+            (TypeKind::Definition(index), None) => {
+                if self.metadata.type_definitions[*index].is_value_type() {
+                    self.define(ty, depth + 1);
+                    self.object_name(&self.names[*index].clone())
+                } else {
+                    format!("{}*", self.object_name(&self.names[*index].clone()))
+                }
+            }
+            (TypeKind::GenericInstance(index, _), None) => {
+                let name = self.struct_name(ty);
+                if self.metadata.type_definitions[*index].is_value_type() {
+                    self.define(ty, depth + 1);
+                    self.object_name(&name)
+                } else {
+                    format!("{}*", self.object_name(&name))
+                }
+            }
+            (TypeKind::Array(element, _) | TypeKind::SzArray(element), None) => {
+                format!("{}*", self.define_array(element, depth + 1))
+            }
+            (TypeKind::GenericParameter(_), None) => "Il2CppObject*".to_string(),
+            _ => "void*".to_string(),
+        };
+        if ty.byref {
+            rendered += "*";
+        }
+        rendered
+    }
+
+    fn object_name(&mut self, name: &str) -> String {
+        let object = format!("{}_o", name);
+        self.declare(&object);
+        object
+    }
+
+    fn declare(&mut self, name: &str) {
+        if self.declared_set.insert(name.to_string()) {
+            self.declared.push(name.to_string());
+        }
+    }
+
+    fn define_array(&mut self, element: &Il2CppType, depth: usize) -> String {
+        let name = format!("{}_array", self.struct_name(element));
+        self.declare(&name);
+        if depth < MAX_DEPTH && self.defined.insert(name.clone()) {
+            let item = self.c_type(element, depth);
+            self.body += &format!(
+                "\nstruct {} {{\n\tIl2CppObject obj;\n\tIl2CppArrayBounds *bounds;\n\til2cpp_array_size_t max_length;\n\t{} m_Items[65535];\n}};\n",
+                name, item
+            );
+        }
+        name
+    }
+
+    fn define(&mut self, ty: &Il2CppType, depth: usize) {
+        let (index, arguments) = match &ty.kind {
+            TypeKind::Definition(index) => (*index, &[][..]),
+            TypeKind::GenericInstance(index, arguments) => (*index, arguments.as_slice()),
+            _ => return,
+        };
+        let name = self.struct_name(ty);
+        if depth >= MAX_DEPTH || !self.defined.insert(name.clone()) {
+            return;
+        }
+        let metadata = self.metadata;
+        let type_def = &metadata.type_definitions[index];
+
+        let mut parent = None;
+        if !type_def.is_value_type()
+            && let Some(base) = self.types.resolve(type_def.parent_index)
+        {
+            let base = substitute(metadata, &base, arguments);
+            let is_object = matches!(base.kind, TypeKind::Definition(i)
+                if types::system_type(metadata, i) == Some(ElementType::Object));
+            if !is_object
+                && matches!(
+                    base.kind,
+                    TypeKind::Definition(_) | TypeKind::GenericInstance(..)
+                )
+            {
+                self.define(&base, depth + 1);
+                parent = Some(self.struct_name(&base));
+            }
+        }
+
+        let mut instance = String::new();
+        let mut statics = String::new();
+        for field in metadata.type_fields(type_def) {
+            let attrs = self
+                .types
+                .type_attrs(metadata, field.type_index)
+                .unwrap_or(0);
+            if attrs & FIELD_ATTRIBUTE_LITERAL != 0 {
+                continue;
+            }
+            let field_type = match self.types.resolve(field.type_index) {
+                Some(field_type) => {
+                    let field_type = substitute(metadata, &field_type, arguments);
+                    self.c_type(&field_type, depth)
+                }
+                None => "void*".to_string(),
+            };
+            let line = format!(
+                "\t{} {};\n",
+                field_type,
+                fix_name(metadata.field_name(field).unwrap_or(""))
+            );
+            if attrs & FIELD_ATTRIBUTE_STATIC != 0 {
+                statics += &line;
+            } else {
+                instance += &line;
+            }
+        }
+
+        self.declare(&format!("{}_Fields", name));
+        self.declare(&format!("{}_c", name));
+        let object = self.object_name(&name);
+        self.body += &match parent {
+            Some(parent) => format!("\nstruct {}_Fields : {}_Fields {{\n", name, parent),
+            None => format!("\nstruct {}_Fields {{\n", name),
+        };
+        self.body += &instance;
+        self.body += "};\n";
+        self.body += &format!("\nstruct {} {{\n", object);
+        if !type_def.is_value_type() {
+            self.body += &format!("\t{}_c *klass;\n\tvoid *monitor;\n", name);
+        }
+        self.body += &format!("\t{}_Fields fields;\n}};\n", name);
+        if !statics.is_empty() {
+            self.declare(&format!("{}_StaticFields", name));
+            self.statics += &format!("\nstruct {}_StaticFields {{\n{}}};\n", name, statics);
+        }
+    }
+}
+
+fn definition(index: usize) -> Il2CppType {
+    Il2CppType {
+        kind: TypeKind::Definition(index),
+        attrs: 0,
+        byref: false,
+    }
+}
+
+// The C spelling of a built-in type.
+fn primitive(element: ElementType) -> &'static str {
+    match element {
+        ElementType::Void => "void",
+        ElementType::Boolean => "bool",
+        ElementType::Char => "uint16_t",
+        ElementType::I1 => "int8_t",
+        ElementType::U1 => "uint8_t",
+        ElementType::I2 => "int16_t",
+        ElementType::U2 => "uint16_t",
+        ElementType::I4 => "int32_t",
+        ElementType::U4 => "uint32_t",
+        ElementType::I8 => "int64_t",
+        ElementType::U8 => "uint64_t",
+        ElementType::R4 => "float",
+        ElementType::R8 => "double",
+        ElementType::I => "intptr_t",
+        ElementType::U => "uintptr_t",
+        ElementType::Object => "Il2CppObject*",
+        _ => "void*",
+    }
+}
+
+// `ty` with the class type parameters of a generic instance replaced by its
+// arguments.
+fn substitute(metadata: &GlobalMetadata, ty: &Il2CppType, arguments: &[Il2CppType]) -> Il2CppType {
+    let inner = |element: &Il2CppType| Box::new(substitute(metadata, element, arguments));
+    let kind = match &ty.kind {
+        TypeKind::GenericParameter(index) => {
+            let argument = metadata
+                .generic_parameters
+                .get(*index)
+                .and_then(|parameter| arguments.get(parameter.num as usize));
+            match argument {
+                Some(argument) => argument.kind.clone(),
+                None => ty.kind.clone(),
+            }
+        }
+        TypeKind::GenericInstance(index, nested) => TypeKind::GenericInstance(
+            *index,
+            nested
+                .iter()
+                .map(|n| substitute(metadata, n, arguments))
+                .collect(),
+        ),
+        TypeKind::Array(element, rank) => TypeKind::Array(inner(element), *rank),
+        TypeKind::SzArray(element) => TypeKind::SzArray(inner(element)),
+        TypeKind::Pointer(element) => TypeKind::Pointer(inner(element)),
+        kind => kind.clone(),
+    };
+    Il2CppType {
+        kind,
+        attrs: ty.attrs,
+        byref: ty.byref,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MetadataBuilder, il2cpp_type, registration};
+
+    const OBJECT: i32 = 0;
+    const INT: i32 = 1;
+
+    fn with_attrs(index: usize, attrs: u16) -> Il2CppType {
+        Il2CppType {
+            attrs,
+            ..definition(index)
+        }
+    }
+
+    // Game.Player : Game.Entity, which embeds the struct Game.Vector defined
+    // after it, and Game.Box<T> instantiated over int.
+    fn table() -> (GlobalMetadata, TypeTable) {
+        let mut b = MetadataBuilder::new();
+        b.add_type("System", "Object");
+        let int = b.add_type("System", "Int32");
+        b.type_definitions[int].bitfield = 1;
+        b.add_type("System", "String");
+        let (vector, static_int, const_int, strings, t, box_int) = (4, 7, 8, 9, 10, 11);
+        let entity = b.add_type("Game", "Entity");
+        b.type_definitions[entity].parent_index = OBJECT;
+        b.add_field(entity, "pos", vector);
+        b.add_field(entity, "count", static_int);
+        b.add_field(entity, "Max", const_int);
+        b.add_type("Game", "Vector");
+        b.type_definitions[vector as usize].bitfield = 1;
+        b.add_field(vector as usize, "x", INT);
+        b.add_field(vector as usize, "y", INT);
+        let player = b.add_type("Game", "Player");
+        b.type_definitions[player].parent_index = entity as i32;
+        b.add_field(player, "class", strings);
+        b.add_field(player, "item", box_int);
+        let damage = b.add_method(player, "Damage", INT);
+        b.add_parameter(damage, "amount", INT);
+        let create = b.add_method(player, "Create", player as i32);
+        b.methods[create].flags = 0x16;
+        let boxed = b.add_type("Game", "Box`1");
+        b.type_definitions[boxed].parent_index = OBJECT;
+        let parameter = b.make_generic(boxed, false, &[("T", 0)]);
+        b.add_field(boxed, "value", t);
+        let metadata = b.build();
+
+        let mut types: Vec<_> = (0..metadata.type_definitions.len())
+            .map(definition)
+            .collect();
+        types.push(with_attrs(1, 0x0011));
+        types.push(with_attrs(1, 0x0056));
+        types.push(il2cpp_type(TypeKind::SzArray(Box::new(definition(2)))));
+        types.push(il2cpp_type(TypeKind::GenericParameter(parameter)));
+        types.push(il2cpp_type(TypeKind::GenericInstance(
+            boxed,
+            vec![definition(1)],
+        )));
+        let table = TypeTable::from_registration(&metadata, registration(types));
+        (metadata, table)
+    }
+
+    fn position(header: &str, text: &str) -> usize {
+        header
+            .find(text)
+            .unwrap_or_else(|| panic!("{:?} missing from\n{}", text, header))
+    }
+
+    #[test]
+    fn names_are_made_identifiers() {
+        assert_eq!(
+            fix_name("System.Collections.Generic.List<int>"),
+            "System_Collections_Generic_List_int_"
+        );
+        assert_eq!(fix_name("Outer<T>.Inner"), "Outer_T__Inner");
+        assert_eq!(fix_name("int"), "_int");
+        assert_eq!(fix_name("2D"), "_2D");
+        assert_eq!(fix_name("Integer"), "Integer");
+    }
+
+    #[test]
+    fn structs_extend_their_parents_and_embed_value_types() {
+        let (metadata, types) = table();
+        let header = Header::new(&metadata, &types).finish();
+        let entity = position(
+            &header,
+            "\nstruct Game_Entity_Fields {\n\tGame_Vector_o pos;\n};\n\
+             \nstruct Game_Entity_o {\n\tGame_Entity_c *klass;\n\tvoid *monitor;\n\
+             \tGame_Entity_Fields fields;\n};\n",
+        );
+        let vector = position(
+            &header,
+            "\nstruct Game_Vector_Fields {\n\tint32_t x;\n\tint32_t y;\n};\n\
+             \nstruct Game_Vector_o {\n\tGame_Vector_Fields fields;\n};\n",
+        );
+        assert!(vector < entity);
+        position(
+            &header,
+            "\nstruct Game_Player_Fields : Game_Entity_Fields {\n\
+             \tSystem_String_array* _class;\n\tGame_Box_int__o* item;\n};\n",
+        );
+        position(
+            &header,
+            "\nstruct System_String_array {\n\tIl2CppObject obj;\n\
+             \tIl2CppArrayBounds *bounds;\n\til2cpp_array_size_t max_length;\n\
+             \tSystem_String_o* m_Items[65535];\n};\n",
+        );
+        // Constants take no space; statics get their own struct, last.
+        assert!(!header.contains("Max"));
+        assert!(header.ends_with("\nstruct Game_Entity_StaticFields {\n\tint32_t count;\n};\n"));
+    }
+
+    #[test]
+    fn generic_instances_substitute_their_arguments() {
+        let (metadata, types) = table();
+        let header = Header::new(&metadata, &types).finish();
+        position(
+            &header,
+            "\nstruct Game_Box_T__Fields {\n\tIl2CppObject* value;\n};\n",
+        );
+        position(
+            &header,
+            "\nstruct Game_Box_int__Fields {\n\tint32_t value;\n};\n",
+        );
+    }
+
+    #[test]
+    fn class_structs_are_only_declared() {
+        let (metadata, types) = table();
+        let header = Header::new(&metadata, &types).finish();
+        assert!(header.starts_with("// Class structs (`_c`) are forward-declared only"));
+        let declaration = position(&header, "\nstruct Game_Player_c;\n");
+        assert!(declaration < position(&header, "\nstruct Game_Player_o {"));
+        assert!(!header.contains("_c {"));
+    }
+
+    #[test]
+    fn method_signatures_use_the_struct_names() {
+        let (metadata, types) = table();
+        let mut header = Header::new(&metadata, &types);
+        assert_eq!(
+            header.method_signature(0).unwrap(),
+            "int32_t Game_Player__Damage (Game_Player_o* __this, int32_t amount, \
+             const MethodInfo* method);"
+        );
+        assert_eq!(
+            header.method_signature(1).unwrap(),
+            "Game_Player_o* Game_Player__Create (const MethodInfo* method);"
+        );
+        assert_eq!(header.type_signature(0).unwrap(), "iiii");
+        assert_eq!(header.type_signature(1).unwrap(), "ii");
+        assert_eq!(header.method_signature(2), None);
+    }
+
+    #[test]
+    fn type_signatures_follow_the_register_classes() {
+        let mut b = MetadataBuilder::new();
+        for name in ["Void", "Single", "Int64", "Double"] {
+            let index = b.add_type("System", name);
+            b.type_definitions[index].bitfield = 1;
+        }
+        let (void, float, long, double, by_ref_double) = (0, 1, 2, 3, 5);
+        let math = b.add_type("Game", "Math");
+        let lerp = b.add_method(math, "Lerp", double);
+        b.methods[lerp].flags = 0x16;
+        b.add_parameter(lerp, "a", float);
+        b.add_parameter(lerp, "b", long);
+        b.add_parameter(lerp, "c", by_ref_double);
+        let reset = b.add_method(math, "Reset", void);
+        let metadata = b.build();
+        let mut types: Vec<_> = (0..metadata.type_definitions.len())
+            .map(definition)
+            .collect();
+        types.push(Il2CppType {
+            byref: true,
+            ..definition(3)
+        });
+        let types = TypeTable::from_registration(&metadata, registration(types));
+
+        let mut header = Header::new(&metadata, &types);
+        assert_eq!(header.type_signature(lerp).unwrap(), "dfjii");
+        assert_eq!(header.type_signature(reset).unwrap(), "vii");
+        assert_eq!(
+            header.method_signature(lerp).unwrap(),
+            "double Game_Math__Lerp (float a, int64_t b, double* c, const MethodInfo* method);"
+        );
+    }
+}
@@ -1,10 +1,104 @@
-const DELTA: u32 = 0x9E3779B9;
+use crate::cipher::MetadataCipher;
 
-fn fix_key(key: &[u8]) -> Vec<u8> {
+/// The standard XXTEA delta.
+pub const DELTA: u32 = 0x9E3779B9;
+
+/// The key hg builds ship with.
+pub const DEFAULT_KEY: &[u8] = b"E8FF";
+
+/// How many rounds are run over a buffer of n words.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Rounds {
+    /// 6 + 52 / n, as in the reference implementation.
+    Standard,
+    /// The same count whatever the length.
+    Fixed(u32),
+}
+
+impl Rounds {
+    fn count(self, words: usize) -> usize {
+        match self {
+            Rounds::Standard => 6 + 52 / words,
+            Rounds::Fixed(n) => n as usize,
+        }
+    }
+}
+
+/// How a key shorter than 16 bytes is stretched to the four key words.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum KeySchedule {
+    /// Zero bytes fill the rest.
+    ZeroPad,
+    /// The key is repeated to fill it.
+    Repeat,
+}
+
+/// The parameters builds vary the cipher by. The default is hg's: the
+/// standard delta and rounds, little-endian words and no length word.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct XxteaParams {
+    /// The delta added to the sum each round.
+    pub delta: u32,
+    /// How many rounds are run.
+    pub rounds: Rounds,
+    /// Whether the plaintext length is stored in a trailing word.
+    pub include_length: bool,
+    /// Whether data and key words are big-endian.
+    pub big_endian: bool,
+    /// How a short key is stretched.
+    pub key_schedule: KeySchedule,
+}
+
+impl Default for XxteaParams {
+    fn default() -> Self {
+        Self {
+            delta: DELTA,
+            rounds: Rounds::Standard,
+            include_length: false,
+            big_endian: false,
+            key_schedule: KeySchedule::ZeroPad,
+        }
+    }
+}
+
+/// XXTEA as a `MetadataCipher`, under the name `--cipher` gives it.
+pub struct Xxtea {
+    name: String,
+    params: XxteaParams,
+}
+
+impl Xxtea {
+    /// The cipher `params` describe, named `name`.
+    pub fn new(name: &str, params: XxteaParams) -> Self {
+        Self {
+            name: name.to_string(),
+            params,
+        }
+    }
+}
+
+impl MetadataCipher for Xxtea {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+        decrypt(data, key, &self.params)
+    }
+
+    fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+        encrypt(data, key, &self.params)
+    }
+}
+
+fn fix_key(key: &[u8], schedule: KeySchedule) -> Vec<u8> {
     if key.len() == 16 {
         return key.to_vec();
     }
     if key.len() < 16 {
+        if schedule == KeySchedule::Repeat && !key.is_empty() {
+            return key.iter().copied().cycle().take(16).collect();
+        }
         let mut fixed = key.to_vec();
         fixed.resize(16, 0);
         return fixed;
@@ -12,7 +106,12 @@ fn fix_key(key: &[u8]) -> Vec<u8> {
     key[..16].to_vec()
 }
 
-fn to_uint32_array(data: &[u8], include_length: bool) -> Vec<u32> {
+fn byte_shift(i: usize, big_endian: bool) -> usize {
+    let lane = if big_endian { 3 - (i & 3) } else { i & 3 };
+    lane << 3
+}
+
+fn to_uint32_array(data: &[u8], include_length: bool, big_endian: bool) -> Vec<u32> {
     let length = data.len();
     let n = if (length & 3) == 0 {
         length >> 2
@@ -28,12 +127,13 @@ fn to_uint32_array(data: &[u8], include_length: bool) -> Vec<u32> {
     }
 
     for (i, &b) in data.iter().enumerate() {
-        result[i >> 2] |= (b as u32) << ((i & 3) << 3);
+        result[i >> 2] |= (b as u32) << byte_shift(i, big_endian);
     }
     result
 }
 
-fn to_byte_array(data: &[u32], include_length: bool) -> Option<Vec<u8>> {
+// At most the first `limit` bytes.
+fn to_byte_array(data: &[u32], include_length: bool, big_endian: bool) -> Option<Vec<u8>> {
     let mut n = data.len() << 2;
 
     if include_length {
@@ -51,7 +151,7 @@ fn to_byte_array(data: &[u32], include_length: bool) -> Option<Vec<u8>> {
 
     let mut result = vec![0u8; n];
     for i in 0..n {
-        result[i] = ((data[i >> 2] >> ((i & 3) << 3)) & 0xFF) as u8;
+        result[i] = ((data[i >> 2] >> byte_shift(i, big_endian)) & 0xFF) as u8;
     }
     Some(result)
 }
@@ -68,7 +168,7 @@ fn mx(sum_value: u32, y: u32, z: u32, p: usize, e: u32, k: &[u32]) -> u32 {
     (part1.wrapping_add(part2)) ^ (part3.wrapping_add(part4))
 }
 
-fn decrypt_uint32(v: &mut [u32], k: &[u32]) {
+fn decrypt_uint32(v: &mut [u32], k: &[u32], params: &XxteaParams) {
     let n = v.len().wrapping_sub(1);
     if n < 1 {
         return;
@@ -76,11 +176,11 @@ fn decrypt_uint32(v: &mut [u32], k: &[u32]) {
 
     let mut sum_value;
     let mut y = v[0];
-    let q = 6 + 52 / (n + 1);
+    let mut q = params.rounds.count(n + 1);
 
-    sum_value = (q as u32).wrapping_mul(DELTA);
+    sum_value = (q as u32).wrapping_mul(params.delta);
 
-    while sum_value != 0 {
+    while q > 0 {
         let e = (sum_value >> 2) & 3;
 
         for p in (1..=n).rev() {
@@ -96,19 +196,67 @@ fn decrypt_uint32(v: &mut [u32], k: &[u32]) {
         v[0] = v[0].wrapping_sub(mx_val);
         y = v[0];
 
-        sum_value = sum_value.wrapping_sub(DELTA);
+        sum_value = sum_value.wrapping_sub(params.delta);
+        q -= 1;
+    }
+}
+
+fn encrypt_uint32(v: &mut [u32], k: &[u32], params: &XxteaParams) {
+    let n = v.len().wrapping_sub(1);
+    if n < 1 {
+        return;
     }
+
+    let mut sum_value: u32 = 0;
+    let mut z = v[n];
+    let mut q = params.rounds.count(n + 1);
+
+    while q > 0 {
+        sum_value = sum_value.wrapping_add(params.delta);
+        let e = (sum_value >> 2) & 3;
+
+        for p in 0..n {
+            let y = v[p + 1];
+            let mx_val = mx(sum_value, y, z, p, e, k);
+            v[p] = v[p].wrapping_add(mx_val);
+            z = v[p];
+        }
+
+        let y = v[0];
+        let mx_val = mx(sum_value, y, z, n, e, k);
+        v[n] = v[n].wrapping_add(mx_val);
+        z = v[n];
+
+        q -= 1;
+    }
+}
+
+/// Encrypts `data` with `key`, padding it to whole words.
+pub fn encrypt(data: &[u8], key: &[u8], params: &XxteaParams) -> Result<Vec<u8>, String> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let fixed_key = fix_key(key, params.key_schedule);
+    let mut v = to_uint32_array(data, params.include_length, params.big_endian);
+    let k = to_uint32_array(&fixed_key, false, params.big_endian);
+
+    encrypt_uint32(&mut v, &k, params);
+
+    to_byte_array(&v, false, params.big_endian)
+        .ok_or_else(|| "Invalid XXTEA data or key.".to_string())
 }
 
-pub fn decrypt(data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+/// Decrypts `data` with `key`.
+pub fn decrypt(data: &[u8], key: &[u8], params: &XxteaParams) -> Result<Vec<u8>, String> {
     if data.is_empty() {
         return Ok(Vec::new());
     }
-    let fixed_key = fix_key(key);
-    let mut v = to_uint32_array(data, false);
-    let k = to_uint32_array(&fixed_key, false);
+    let fixed_key = fix_key(key, params.key_schedule);
+    let mut v = to_uint32_array(data, false, params.big_endian);
+    let k = to_uint32_array(&fixed_key, false, params.big_endian);
 
-    decrypt_uint32(&mut v, &k);
+    decrypt_uint32(&mut v, &k, params);
 
-    to_byte_array(&v, false).ok_or_else(|| "Invalid XXTEA data or key.".to_string())
+    to_byte_array(&v, params.include_length, params.big_endian)
+        .ok_or_else(|| "Invalid XXTEA data or key.".to_string())
 }
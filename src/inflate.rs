@@ -0,0 +1,323 @@
+const MAX_BITS: usize = 15;
+// DEFLATE cannot expand its input more than this, so a size hint beyond it
+// is a lie and not worth reserving for.
+const MAX_RATIO: usize = 1032;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    // Past the end of input the buffer is padded with zeros so a peek never
+    // fails; `consume` is what reports a truncated stream.
+    fn refill(&mut self) {
+        while self.count <= 56 {
+            let byte = self.data.get(self.pos).copied().unwrap_or(0);
+            self.buf |= (byte as u64) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+    }
+
+    fn peek(&mut self, n: u32) -> u32 {
+        if self.count < n {
+            self.refill();
+        }
+        (self.buf & ((1u64 << n) - 1)) as u32
+    }
+
+    fn consume(&mut self, n: u32) -> Result<(), String> {
+        self.buf >>= n;
+        self.count -= n;
+        if self.bit_position() > self.data.len() * 8 {
+            return Err("Unexpected end of deflate stream".to_string());
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32, String> {
+        if n == 0 {
+            return Ok(0);
+        }
+        let v = self.peek(n);
+        self.consume(n)?;
+        Ok(v)
+    }
+
+    fn bit_position(&self) -> usize {
+        self.pos * 8 - self.count as usize
+    }
+
+    fn align_to_byte(&mut self) {
+        let skip = self.count % 8;
+        self.buf >>= skip;
+        self.count -= skip;
+    }
+
+    fn read_aligned(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let start = self.bit_position() / 8;
+        let bytes = self
+            .data
+            .get(start..start + n)
+            .ok_or("Unexpected end of stored block")?;
+        self.pos = start + n;
+        self.buf = 0;
+        self.count = 0;
+        Ok(bytes)
+    }
+}
+
+// Table indexed by the next `max_len` input bits; each entry packs the symbol
+// above the length of its code.
+struct Huffman {
+    table: Vec<u16>,
+    max_len: u32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let max_len = (1..=MAX_BITS).rev().find(|&l| counts[l] != 0).unwrap_or(1) as u32;
+
+        let mut next_code = [0u32; MAX_BITS + 2];
+        let mut code = 0u32;
+        for bits in 1..=MAX_BITS {
+            code = (code + counts[bits - 1] as u32) << 1;
+            next_code[bits] = code;
+        }
+
+        let mut table = vec![0u16; 1 << max_len];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let len = len as u32;
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            if code >= 1 << len {
+                return Err("Over-subscribed Huffman code".to_string());
+            }
+            let reversed = code.reverse_bits() >> (32 - len);
+            let entry = ((symbol as u16) << 4) | len as u16;
+            let mut index = reversed as usize;
+            while index < table.len() {
+                table[index] = entry;
+                index += 1 << len;
+            }
+        }
+        Ok(Self { table, max_len })
+    }
+
+    fn decode(&self, r: &mut BitReader) -> Result<u16, String> {
+        let entry = self.table[r.peek(self.max_len) as usize];
+        let len = (entry & 0xF) as u32;
+        if len == 0 {
+            return Err("Invalid Huffman code".to_string());
+        }
+        r.consume(len)?;
+        Ok(entry >> 4)
+    }
+}
+
+pub fn inflate(data: &[u8], size_hint: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(size_hint.min(data.len().saturating_mul(MAX_RATIO)));
+    let mut r = BitReader::new(data);
+
+    loop {
+        let is_final = r.bits(1)? == 1;
+        match r.bits(2)? {
+            0 => {
+                r.align_to_byte();
+                let len = r.bits(16)?;
+                let nlen = r.bits(16)?;
+                if len != !nlen & 0xFFFF {
+                    return Err("Corrupt stored block length".to_string());
+                }
+                out.extend_from_slice(r.read_aligned(len as usize)?);
+            }
+            1 => {
+                let (lit, dist) = fixed_tables()?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut r)?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            }
+            _ => return Err("Invalid deflate block type".to_string()),
+        }
+        if is_final {
+            return Ok(out);
+        }
+    }
+}
+
+fn fixed_tables() -> Result<(Huffman, Huffman), String> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn dynamic_tables(r: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let hlit = r.bits(5)? as usize + 257;
+    let hdist = r.bits(5)? as usize + 1;
+    let hclen = r.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in CODE_LENGTH_ORDER.iter().take(hclen) {
+        code_lengths[index] = r.bits(3)? as u8;
+    }
+    let code_table = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; hlit + hdist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let (value, repeat) = match code_table.decode(r)? {
+            sym @ 0..=15 => (sym as u8, 1),
+            16 => {
+                let prev = *lengths[..i]
+                    .last()
+                    .ok_or("Repeat with no previous length")?;
+                (prev, 3 + r.bits(2)? as usize)
+            }
+            17 => (0, 3 + r.bits(3)? as usize),
+            18 => (0, 11 + r.bits(7)? as usize),
+            _ => return Err("Invalid code length symbol".to_string()),
+        };
+        if i + repeat > lengths.len() {
+            return Err("Code lengths overflow".to_string());
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    Ok((
+        Huffman::new(&lengths[..hlit])?,
+        Huffman::new(&lengths[hlit..])?,
+    ))
+}
+
+fn inflate_block(
+    r: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = lit.decode(r)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length =
+                    LENGTH_BASE[index] as usize + r.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let dist_symbol = dist.decode(r)? as usize;
+                if dist_symbol >= DIST_BASE.len() {
+                    return Err("Invalid distance symbol".to_string());
+                }
+                let distance = DIST_BASE[dist_symbol] as usize
+                    + r.bits(DIST_EXTRA[dist_symbol] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("Distance too far back".to_string());
+                }
+                let start = out.len() - distance;
+                if distance >= length {
+                    out.extend_from_within(start..start + length);
+                } else {
+                    for i in 0..length {
+                        out.push(out[start + i]);
+                    }
+                }
+            }
+            _ => return Err("Invalid literal/length symbol".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_block() {
+        let data = [0x01, 0x05, 0x00, 0xFA, 0xFF, b'H', b'e', b'l', b'l', b'o'];
+        assert_eq!(inflate(&data, 5).unwrap(), b"Hello");
+    }
+
+    #[test]
+    fn fixed_huffman_block() {
+        let data = [
+            0xF3, 0x48, 0xCD, 0xC9, 0xC9, 0xD7, 0x51, 0xF0, 0x40, 0xA2, 0x14, 0x01,
+        ];
+        assert_eq!(inflate(&data, 20).unwrap(), b"Hello, Hello, Hello!");
+    }
+
+    #[test]
+    fn dynamic_huffman_block() {
+        let data = [
+            0x2D, 0x8A, 0x81, 0x0D, 0x00, 0x40, 0x10, 0xC1, 0x66, 0x2D, 0xFB, 0xEF, 0xF0, 0xBC,
+            0x13, 0x91, 0x14, 0x80, 0x41, 0x75, 0x62, 0xFA, 0x84, 0x9D, 0x4A, 0xC7, 0xDB, 0xD5,
+            0x6B, 0x63, 0x14, 0xD6, 0x03,
+        ];
+        assert_eq!(
+            inflate(&data, 64).unwrap(),
+            b"aaacaabaababaaaaaaaabaabaaccbabbaaaabaaaabababcaabbcaaababababcb"
+        );
+    }
+
+    #[test]
+    fn truncated_streams_are_errors() {
+        let fixed = [
+            0xF3, 0x48, 0xCD, 0xC9, 0xC9, 0xD7, 0x51, 0xF0, 0x40, 0xA2, 0x14, 0x01,
+        ];
+        for len in 0..fixed.len() {
+            assert!(inflate(&fixed[..len], 20).is_err(), "{} bytes", len);
+        }
+        let stored = [0x01, 0x05, 0x00, 0xFA, 0xFF, b'H', b'e'];
+        assert!(inflate(&stored, 5).is_err());
+    }
+
+    #[test]
+    fn size_hint_is_capped_by_the_input() {
+        let data = [0x01, 0x00, 0x00, 0xFF, 0xFF];
+        assert!(inflate(&data, usize::MAX).unwrap().is_empty());
+    }
+}
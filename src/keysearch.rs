@@ -0,0 +1,485 @@
+use crate::binary::{Binary, Section};
+use crate::cipher::MetadataCipher;
+use crate::extractor::{HEAD_PATTERN, blob_ends};
+use crate::hgxxtea::DEFAULT_KEY;
+use crate::metadata::{self, GlobalMetadataHeader};
+use crate::utils::{MAX_KEY_LEN, find_pattern, parse_key, read_u32};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+const NEAR_MARKER_WINDOW: usize = 1024;
+const MIN_KEY_STRING: usize = 4;
+const MIN_DISTINCT_BYTES: usize = 6;
+const MAX_SPANS_PER_MARKER: usize = 8;
+// The smallest header, version 29's. A shorter span cannot hold metadata.
+const MIN_SPAN: usize = 8 + 31 * 8;
+// Keys gathered from the binary, on top of the default key and the wordlist.
+// Each one costs a full decryption of the span it is tried on.
+const MAX_KEY_CANDIDATES: usize = 1_000;
+// Header sections every metadata version starts with, used for plausibility.
+const CHECKED_SECTIONS: usize = 6;
+
+/// Where a candidate key came from, most trusted first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeySource {
+    /// The stock hg key.
+    Default,
+    /// The user's wordlist.
+    Wordlist,
+    /// A string or 16-byte constant just before a `CFG` marker.
+    NearMarker,
+    /// Data a code instruction points at.
+    CodeReference,
+    /// A short string in a data section.
+    DataString,
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Default => write!(f, "default key"),
+            KeySource::Wordlist => write!(f, "wordlist"),
+            KeySource::NearMarker => write!(f, "near CFG marker"),
+            KeySource::CodeReference => write!(f, "referenced from code"),
+            KeySource::DataString => write!(f, "data string"),
+        }
+    }
+}
+
+/// A key to try.
+pub struct KeyCandidate {
+    /// The key bytes.
+    pub key: Vec<u8>,
+    /// Where it was found.
+    pub source: KeySource,
+}
+
+/// A key that decrypts a span to a plausible metadata header.
+pub struct KeyMatch {
+    /// The key bytes.
+    pub key: Vec<u8>,
+    /// Where it was found.
+    pub source: KeySource,
+    /// Where the span it decrypts starts in the file.
+    pub file_offset: usize,
+    /// The span's length.
+    pub size: usize,
+    /// The metadata version in the decrypted header.
+    pub version: i32,
+}
+
+/// What a key search tried and found.
+pub struct SearchReport {
+    /// How many distinct keys were tried.
+    pub candidates: usize,
+    /// How many blob spans there were to try them on.
+    pub spans: usize,
+    /// Every key that passed, on the first span any passed on.
+    pub matches: Vec<KeyMatch>,
+}
+
+struct Span<'a> {
+    file_offset: usize,
+    data: &'a [u8],
+    // The most trustworthy end for its marker.
+    first: bool,
+}
+
+/// XXTEA mixes every word of the buffer into every other one in each round,
+/// so a header cannot be decrypted on its own and every trial is a full
+/// decryption. The search keeps their number down instead: keys are
+/// deduplicated and capped, spans too short for a header are dropped, only
+/// the exact size-field span is tried when the binary has one, and spans are
+/// tried one at a time from the most trustworthy end. Every key goes on a
+/// marker's first span; its later ones only get the default, wordlist and
+/// near-marker keys. Every key that passes on a span is reported, and the search stops
+/// after the first span that any key passes on.
+pub fn search(
+    data: &[u8],
+    wordlist: &[Vec<u8>],
+    cipher: &dyn MetadataCipher,
+) -> Result<SearchReport, String> {
+    let binary = Binary::parse(data)?;
+    let (markers, spans) = find_spans(data, &binary);
+    if spans.is_empty() {
+        return Err("Head pattern not found in any data section".to_string());
+    }
+
+    let candidates = collect_candidates(data, &binary, &markers, wordlist);
+    let matches = try_candidates(&candidates, &spans, cipher);
+    Ok(SearchReport {
+        candidates: candidates.len(),
+        spans: spans.len(),
+        matches,
+    })
+}
+
+/// Reads keys in `--key` syntax, one per line; blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_wordlist(path: &str) -> Result<Vec<Vec<u8>>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read wordlist {}: {}", path, e))?;
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| parse_key(line).map_err(|e| format!("{}:{}: {}", path, i + 1, e)))
+        .collect()
+}
+
+fn find_spans<'a>(data: &'a [u8], binary: &Binary) -> (Vec<usize>, Vec<Span<'a>>) {
+    let mut markers = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+    for section in binary.searchable_sections() {
+        let raw = section.raw(data);
+        let mut search_pos = 0;
+        while let Some(found) = find_pattern(&raw[search_pos..], HEAD_PATTERN) {
+            let head_pos = search_pos + found;
+            let start = head_pos + HEAD_PATTERN.len();
+            search_pos = start;
+            markers.push(section.file_offset + head_pos);
+
+            for (i, (end, _)) in blob_ends(raw, head_pos)
+                .into_iter()
+                .filter(|&(end, _)| end - start >= MIN_SPAN)
+                .take(MAX_SPANS_PER_MARKER)
+                .enumerate()
+            {
+                let span = Span {
+                    file_offset: section.file_offset + start,
+                    data: &raw[start..end],
+                    first: i == 0,
+                };
+                let duplicate = spans
+                    .iter()
+                    .any(|s| s.file_offset == span.file_offset && s.data.len() == span.data.len());
+                if !duplicate {
+                    spans.push(span);
+                }
+            }
+        }
+    }
+    markers.sort_unstable();
+    (markers, spans)
+}
+
+#[derive(Default)]
+struct Collector {
+    keys: HashMap<Vec<u8>, (KeySource, usize)>,
+}
+
+impl Collector {
+    fn add(&mut self, key: &[u8], source: KeySource, distance: usize) {
+        if key.len() > MAX_KEY_LEN || key.iter().all(|&b| b == 0) {
+            return;
+        }
+        let rank = (source, distance);
+        self.keys
+            .entry(key.to_vec())
+            .and_modify(|r| *r = (*r).min(rank))
+            .or_insert(rank);
+    }
+
+    fn into_candidates(self) -> Vec<KeyCandidate> {
+        let mut ranked: Vec<_> = self.keys.into_iter().collect();
+        ranked.sort_unstable_by(|(a, rank_a), (b, rank_b)| (rank_a, a).cmp(&(rank_b, b)));
+        let given = ranked
+            .iter()
+            .take_while(|(_, (source, _))| *source <= KeySource::Wordlist)
+            .count();
+        ranked.truncate(given + MAX_KEY_CANDIDATES);
+        ranked
+            .into_iter()
+            .map(|(key, (source, _))| KeyCandidate { key, source })
+            .collect()
+    }
+}
+
+fn collect_candidates(
+    data: &[u8],
+    binary: &Binary,
+    markers: &[usize],
+    wordlist: &[Vec<u8>],
+) -> Vec<KeyCandidate> {
+    let mut collector = Collector::default();
+    collector.add(DEFAULT_KEY, KeySource::Default, 0);
+    for (i, word) in wordlist.iter().enumerate() {
+        collector.add(word, KeySource::Wordlist, i);
+    }
+
+    for &marker in markers {
+        let window_start = marker.saturating_sub(NEAR_MARKER_WINDOW);
+        let window = &data[window_start..marker];
+        for (pos, string) in ascii_strings(window) {
+            collector.add(string, KeySource::NearMarker, window.len() - pos);
+        }
+        for pos in
+            (window_start.next_multiple_of(4)..marker.saturating_sub(MAX_KEY_LEN - 1)).step_by(4)
+        {
+            let constant = &data[pos..pos + MAX_KEY_LEN];
+            if is_key_like(constant) {
+                collector.add(constant, KeySource::NearMarker, marker - pos);
+            }
+        }
+    }
+
+    for section in binary.sections.iter().filter(|s| s.executable) {
+        for target in code_references(binary, section, data) {
+            let distance = marker_distance(markers, target);
+            if let Some(string) = string_at(data, target) {
+                collector.add(string, KeySource::CodeReference, distance);
+            } else if let Some(constant) = data.get(target..target + MAX_KEY_LEN)
+                && is_key_like(constant)
+            {
+                collector.add(constant, KeySource::CodeReference, distance);
+            }
+        }
+    }
+
+    for section in binary.searchable_sections() {
+        for (pos, string) in ascii_strings(section.raw(data)) {
+            let distance = marker_distance(markers, section.file_offset + pos);
+            collector.add(string, KeySource::DataString, distance);
+        }
+    }
+
+    collector.into_candidates()
+}
+
+// Spans past a marker's first are guesses at the end; only the keys most
+// likely to be right are worth a full decryption each on them.
+fn try_candidates(
+    candidates: &[KeyCandidate],
+    spans: &[Span],
+    cipher: &dyn MetadataCipher,
+) -> Vec<KeyMatch> {
+    let trusted = candidates
+        .iter()
+        .take_while(|c| c.source <= KeySource::NearMarker)
+        .count();
+    for span in spans {
+        let keys = if span.first {
+            candidates
+        } else {
+            &candidates[..trusted]
+        };
+        let matches = try_span(keys, span, cipher);
+        if !matches.is_empty() {
+            return matches;
+        }
+    }
+    Vec::new()
+}
+
+// Every candidate that decrypts `span` to a plausible header, in candidate
+// order.
+fn try_span(
+    candidates: &[KeyCandidate],
+    span: &Span,
+    cipher: &dyn MetadataCipher,
+) -> Vec<KeyMatch> {
+    let next = AtomicUsize::new(0);
+    let matches = Mutex::new(Vec::new());
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(candidates.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(candidate) = candidates.get(index) else {
+                        break;
+                    };
+                    if let Some(version) = trial(span.data, &candidate.key, cipher) {
+                        let mut matches = matches.lock().unwrap_or_else(|e| e.into_inner());
+                        matches.push((
+                            index,
+                            KeyMatch {
+                                key: candidate.key.clone(),
+                                source: candidate.source,
+                                file_offset: span.file_offset,
+                                size: span.data.len(),
+                                version,
+                            },
+                        ));
+                    }
+                }
+            });
+        }
+    });
+
+    let mut matches = matches.into_inner().unwrap_or_else(|e| e.into_inner());
+    matches.sort_by_key(|(index, _)| *index);
+    matches.into_iter().map(|(_, m)| m).collect()
+}
+
+fn trial(blob: &[u8], key: &[u8], cipher: &dyn MetadataCipher) -> Option<i32> {
+    let plain = cipher.decrypt(blob, key).ok()?;
+    if !metadata::has_valid_magic(&plain) {
+        return None;
+    }
+    let header = GlobalMetadataHeader::parse(&plain).ok()?;
+    let in_bounds = header
+        .sections()
+        .iter()
+        .take(CHECKED_SECTIONS)
+        .all(|&(offset, size)| {
+            offset >= 0 && size >= 0 && offset as usize + size as usize <= blob.len()
+        });
+    ((16..=31).contains(&header.version) && in_bounds).then_some(header.version)
+}
+
+// NUL-terminated printable runs short enough to be a key, with their offset.
+fn ascii_strings(raw: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    for (i, &b) in raw.iter().enumerate() {
+        if is_printable(b) {
+            continue;
+        }
+        let len = i - start;
+        if b == 0 && (MIN_KEY_STRING..=MAX_KEY_LEN).contains(&len) {
+            strings.push((start, &raw[start..i]));
+        }
+        start = i + 1;
+    }
+    strings
+}
+
+fn string_at(data: &[u8], offset: usize) -> Option<&[u8]> {
+    let rest = data.get(offset..)?;
+    let len = rest
+        .iter()
+        .take(MAX_KEY_LEN + 1)
+        .position(|&b| !is_printable(b))?;
+    (len >= MIN_KEY_STRING && rest[len] == 0).then(|| &rest[..len])
+}
+
+fn is_printable(b: u8) -> bool {
+    b.is_ascii_graphic() || b == b' '
+}
+
+// Raw 16-byte keys look random; this skips padding, tables of small
+// integers and the like.
+fn is_key_like(bytes: &[u8]) -> bool {
+    let mut seen = [false; 256];
+    bytes.iter().for_each(|&b| seen[b as usize] = true);
+    seen.iter().filter(|&&s| s).count() >= MIN_DISTINCT_BYTES
+}
+
+fn marker_distance(markers: &[usize], offset: usize) -> usize {
+    let i = markers.partition_point(|&m| m < offset);
+    let after = markers.get(i).map(|&m| m - offset);
+    let before = i.checked_sub(1).map(|j| offset - markers[j]);
+    after.into_iter().chain(before).min().unwrap_or(usize::MAX)
+}
+
+// File offsets of data addressed by x86-64 RIP-relative LEA and arm64
+// ADRP+ADD pairs. Other architectures contribute no references.
+fn code_references(binary: &Binary, section: &Section, data: &[u8]) -> Vec<usize> {
+    let raw = section.raw(data);
+    let slice = section.slice.as_deref();
+    let mut targets = Vec::new();
+    match binary.section_arch(section) {
+        "x86_64" => {
+            for i in 0..raw.len().saturating_sub(6) {
+                if matches!(raw[i], 0x48 | 0x4C) && raw[i + 1] == 0x8D && raw[i + 2] & 0xC7 == 0x05
+                {
+                    let disp = read_u32(raw, i + 3).unwrap_or(0) as i32;
+                    let next = section.address + i as u64 + 7;
+                    let target = next.wrapping_add_signed(disp as i64);
+                    targets.extend(binary.offset_for_address(target, slice));
+                }
+            }
+        }
+        "arm64" | "arm64e" => {
+            for i in (0..raw.len().saturating_sub(7)).step_by(4) {
+                let (Ok(adrp), Ok(add)) = (read_u32(raw, i), read_u32(raw, i + 4)) else {
+                    break;
+                };
+                let rd = adrp & 0x1F;
+                if adrp & 0x9F00_0000 != 0x9000_0000
+                    || add & 0xFFC0_0000 != 0x9100_0000
+                    || (add >> 5) & 0x1F != rd
+                {
+                    continue;
+                }
+                let imm = ((((adrp >> 5) & 0x7_FFFF) << 2) | ((adrp >> 29) & 3)) as i64;
+                let imm = (imm << 43) >> 31;
+                let pc = section.address + i as u64;
+                let page = (pc & !0xFFF).wrapping_add_signed(imm);
+                let target = page + ((add >> 10) & 0xFFF) as u64;
+                targets.extend(binary.offset_for_address(target, slice));
+            }
+        }
+        _ => {}
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::DecryptConfig;
+    use crate::testing::{self, SECTION_OFFSET, metadata_file};
+
+    const KEY: &[u8] = b"Xk9#mQ2p";
+
+    // A PE holding `before`, a size field, the marker and metadata
+    // encrypted with KEY. Returns the file, where the blob starts in it and
+    // its size.
+    fn binary(before: &[u8]) -> (Vec<u8>, usize, usize) {
+        let blob = DecryptConfig::with_key(KEY.to_vec())
+            .encrypt(&metadata_file(29, 31, &[]))
+            .unwrap();
+        let mut section = vec![0; 16];
+        section.extend_from_slice(before);
+        section.extend((blob.len() as u32).to_le_bytes());
+        section.extend_from_slice(HEAD_PATTERN);
+        let start = SECTION_OFFSET + section.len();
+        section.extend_from_slice(&blob);
+        section.extend_from_slice(&[0; 16]);
+        (testing::pe(&section, section.len()), start, blob.len())
+    }
+
+    fn find(data: &[u8], wordlist: &[&[u8]]) -> SearchReport {
+        let wordlist: Vec<Vec<u8>> = wordlist.iter().map(|w| w.to_vec()).collect();
+        let config = DecryptConfig::default();
+        search(data, &wordlist, &*config.cipher).unwrap()
+    }
+
+    #[test]
+    fn key_before_the_marker_is_recovered() {
+        let (data, start, size) = binary(b"Xk9#mQ2p\0");
+        let report = find(&data, &[]);
+        assert_eq!(report.spans, 1);
+        assert_eq!(report.matches.len(), 1);
+        let found = &report.matches[0];
+        assert_eq!(found.key, KEY);
+        assert_eq!(found.source, KeySource::NearMarker);
+        assert_eq!((found.file_offset, found.size), (start, size));
+        assert_eq!(found.version, 29);
+    }
+
+    #[test]
+    fn wordlist_key_is_recovered() {
+        let (data, _, _) = binary(&[]);
+        let report = find(&data, &[b"wrong", KEY]);
+        assert_eq!(report.matches.len(), 1);
+        assert_eq!(report.matches[0].key, KEY);
+        assert_eq!(report.matches[0].source, KeySource::Wordlist);
+    }
+
+    #[test]
+    fn wrong_wordlist_finds_nothing() {
+        let (data, _, _) = binary(&[]);
+        let report = find(&data, &[b"wrong", b"Xk9#mQ2q"]);
+        assert!(report.candidates >= 3);
+        assert!(report.matches.is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+//! Extracts, decrypts and parses the `global-metadata.dat` hg builds embed
+//! in their IL2CPP binaries.
+//!
+//! The metadata is stored XXTEA-encrypted behind a `CFG` marker inside
+//! `GameAssembly.dll`, `libil2cpp.so` or `UnityFramework`. [`Extractor`]
+//! finds the blob, [`DecryptConfig`] decrypts it, [`GlobalMetadata`] parses
+//! the result and gives access to its tables and string literals, and
+//! [`GlobalMetadata::write`] produces the file to save. [`pipeline`] chains
+//! these steps, and the dump and repack outputs, the way the command line
+//! does.
+//!
+//! ```no_run
+//! use hg_metadata_dumper::{
+//!     DecryptConfig, Extractor, GlobalMetadata, LiteralDecryption, ParseOptions,
+//! };
+//!
+//! let config = DecryptConfig::default();
+//! let mut extractor = Extractor::new("GameAssembly.dll");
+//! extractor.process(&config)?;
+//! let plain = config.decrypt(extractor.get_valid_data())?;
+//!
+//! let options = ParseOptions {
+//!     literals: LiteralDecryption::Detect,
+//! };
+//! let metadata = GlobalMetadata::parse_with(&plain, options)?;
+//! for literal in &metadata.string_literals {
+//!     println!("{}", literal);
+//! }
+//! std::fs::write(
+//!     "global-metadata.dat",
+//!     metadata.write(&plain, metadata.literal_scheme),
+//! )
+//! .map_err(|e| e.to_string())?;
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! Errors are reported as `String`s ready to show to a user.
+
+#![warn(missing_docs)]
+
+/// Reading the IL2CPP binaries out of APK, XAPK and IPA archives.
+pub mod archive;
+mod binary;
+/// Ciphers the metadata blob can be stored with, and the key to use.
+pub mod cipher;
+/// C# names and declarations for metadata types and members.
+pub mod csharp;
+/// `dump.cs`, the metadata as C# pseudo-source.
+pub mod dump;
+mod elf;
+/// Locating the encrypted blob in a binary.
+pub mod extractor;
+mod header;
+/// The XXTEA variant hg builds use, and its configurable parameters.
+pub mod hgxxtea;
+mod inflate;
+/// Recovering an unknown key from the binary.
+pub mod keysearch;
+/// String literal obfuscation schemes and their detection.
+pub mod literals;
+mod macho;
+/// Parsing `global-metadata.dat`.
+pub mod metadata;
+mod pe;
+/// Extraction, dumping and repacking end to end, as the command line runs
+/// them.
+pub mod pipeline;
+/// The binary's CodeRegistration and MetadataRegistration tables.
+pub mod registration;
+/// Writing a re-encrypted blob back into a binary.
+pub mod repack;
+/// Il2CppDumper's `script.json`, `il2cpp.h` and `stringliteral.json`.
+pub mod script;
+/// Exporting string literals as JSON, CSV or JSON Lines.
+pub mod strings;
+#[cfg(test)]
+mod testing;
+/// Il2CppType resolution from the metadata and the binary.
+pub mod types;
+/// Byte reading, key parsing and formatting helpers.
+pub mod utils;
+
+pub use cipher::DecryptConfig;
+pub use extractor::Extractor;
+pub use literals::LiteralScheme;
+pub use metadata::{GlobalMetadata, LiteralDecryption, ParseOptions};
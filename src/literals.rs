@@ -0,0 +1,154 @@
+use std::fmt;
+
+/// What the hg builds this tool started with use.
+pub const DEFAULT_SCHEME: LiteralScheme = LiteralScheme::LengthXor(0x2E);
+
+// Literals are sampled evenly across the table, at most this many.
+const MAX_SAMPLES: usize = 512;
+
+// A winner has to decode nearly every sampled literal to printable UTF-8.
+const MIN_SCORE: f64 = 0.9;
+
+// Substrings that turn up in the literals of any Unity title.
+const KNOWN_STRINGS: [&str; 8] = [
+    "UnityEngine",
+    "System",
+    "Assets/",
+    ".dll",
+    "http",
+    "Player",
+    "Exception",
+    "Error",
+];
+
+// The most common characters of identifiers and English text, which a
+// wrong XOR key of a few low bits keeps printable but shuffles away.
+const COMMON_CHARACTERS: &str = " etaoinsrhldcu";
+
+/// How each byte of a literal is XORed, keyed by a constant, the literal's
+/// length or index in the table, or a key that rolls on by one per byte.
+/// The key derived from a length or index is its low byte XOR the constant.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LiteralScheme {
+    /// XOR with a constant key.
+    Xor(u8),
+    /// XOR with the literal's length and the constant.
+    LengthXor(u8),
+    /// XOR with the literal's index and the constant.
+    IndexXor(u8),
+    /// XOR with a key starting at the constant.
+    Rolling(u8),
+}
+
+impl LiteralScheme {
+    /// XOR is its own inverse, so this also encrypts.
+    pub fn apply(&self, bytes: &mut [u8], index: usize, length: u32) {
+        match *self {
+            LiteralScheme::Xor(key) => xor(bytes, key),
+            LiteralScheme::LengthXor(constant) => xor(bytes, length as u8 ^ constant),
+            LiteralScheme::IndexXor(constant) => xor(bytes, index as u8 ^ constant),
+            LiteralScheme::Rolling(start) => {
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte ^= start.wrapping_add(i as u8);
+                }
+            }
+        }
+    }
+
+    // Every scheme the detector tries, the default first so that it wins
+    // ties. `Xor(0)` stands for literals stored as plaintext.
+    fn candidates() -> impl Iterator<Item = Self> {
+        let constants = 0..=u8::MAX;
+        std::iter::once(DEFAULT_SCHEME)
+            .chain(std::iter::once(LiteralScheme::Xor(0)))
+            .chain(constants.clone().map(LiteralScheme::LengthXor))
+            .chain(constants.clone().skip(1).map(LiteralScheme::Xor))
+            .chain(constants.clone().map(LiteralScheme::IndexXor))
+            .chain(constants.map(LiteralScheme::Rolling))
+    }
+}
+
+impl fmt::Display for LiteralScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LiteralScheme::Xor(0) => write!(f, "none"),
+            LiteralScheme::Xor(key) => write!(f, "XOR 0x{:02X}", key),
+            LiteralScheme::LengthXor(constant) => write!(f, "length XOR 0x{:02X}", constant),
+            LiteralScheme::IndexXor(constant) => write!(f, "index XOR 0x{:02X}", constant),
+            LiteralScheme::Rolling(start) => write!(f, "rolling XOR from 0x{:02X}", start),
+        }
+    }
+}
+
+/// The scheme detection picked and how plausible its text is.
+#[derive(Clone, Copy)]
+pub struct Detection {
+    /// The best scoring scheme.
+    pub scheme: LiteralScheme,
+    /// The mean per-literal score, from 0 to about 2.
+    pub score: f64,
+    /// How many literals were scored.
+    pub samples: usize,
+}
+
+/// Tries every candidate scheme on a sample of `literals`, given as
+/// (index, bytes) pairs, and returns the best one if it decodes them to
+/// plausible text.
+pub fn detect(literals: &[(usize, &[u8])]) -> Option<Detection> {
+    let non_empty: Vec<_> = literals.iter().filter(|(_, b)| !b.is_empty()).collect();
+    let step = non_empty.len().div_ceil(MAX_SAMPLES).max(1);
+    let samples: Vec<_> = non_empty.into_iter().step_by(step).collect();
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(LiteralScheme, f64)> = None;
+    let mut buffer = Vec::new();
+    for scheme in LiteralScheme::candidates() {
+        let mut total = 0.0;
+        for &&(index, bytes) in &samples {
+            buffer.clear();
+            buffer.extend_from_slice(bytes);
+            scheme.apply(&mut buffer, index, bytes.len() as u32);
+            total += score(&buffer);
+        }
+        let mean = total / samples.len() as f64;
+        if best.is_none_or(|(_, score)| mean > score) {
+            best = Some((scheme, mean));
+        }
+    }
+    best.filter(|&(_, score)| score >= MIN_SCORE)
+        .map(|(scheme, score)| Detection {
+            scheme,
+            score,
+            samples: samples.len(),
+        })
+}
+
+// 0 for invalid UTF-8, otherwise the printable fraction, plus up to 0.5
+// for common characters and 0.5 for a known substring.
+fn score(bytes: &[u8]) -> f64 {
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return 0.0;
+    };
+    let count = text.chars().count() as f64;
+    let printable = text
+        .chars()
+        .filter(|&c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+        .count() as f64;
+    let common = text
+        .chars()
+        .filter(|&c| COMMON_CHARACTERS.contains(c.to_ascii_lowercase()))
+        .count() as f64;
+    let mut score = printable / count + 0.5 * common / count;
+    if KNOWN_STRINGS.iter().any(|known| text.contains(known)) {
+        score += 0.5;
+    }
+    score
+}
+
+fn xor(bytes: &mut [u8], key: u8) {
+    for byte in bytes {
+        *byte ^= key;
+    }
+}
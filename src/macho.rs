@@ -0,0 +1,491 @@
+use crate::utils::{read_u16, read_u16_be, read_u32, read_u32_be, read_u64, read_u64_be};
+
+const FAT_MAGIC: u32 = 0xCAFE_BABE;
+const FAT_MAGIC_64: u32 = 0xCAFE_BABF;
+const MH_MAGIC: u32 = 0xFEED_FACE;
+const MH_CIGAM: u32 = 0xCEFA_EDFE;
+const MH_MAGIC_64: u32 = 0xFEED_FACF;
+const MH_CIGAM_64: u32 = 0xCFFA_EDFE;
+
+const LC_SEGMENT: u32 = 0x1;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_DYLD_CHAINED_FIXUPS: u32 = 0x8000_0034;
+
+const DYLD_CHAINED_PTR_ARM64E: u16 = 1;
+const DYLD_CHAINED_PTR_64: u16 = 2;
+const DYLD_CHAINED_PTR_64_OFFSET: u16 = 6;
+const DYLD_CHAINED_PTR_ARM64E_USERLAND: u16 = 9;
+const DYLD_CHAINED_PTR_ARM64E_USERLAND24: u16 = 12;
+const DYLD_CHAINED_PTR_START_NONE: u16 = 0xFFFF;
+
+const SECTION_TYPE: u32 = 0x0000_00FF;
+const S_ZEROFILL: u32 = 0x1;
+const S_GB_ZEROFILL: u32 = 0xC;
+const S_THREAD_LOCAL_ZEROFILL: u32 = 0x12;
+const S_ATTR_PURE_INSTRUCTIONS: u32 = 0x8000_0000;
+const S_ATTR_SOME_INSTRUCTIONS: u32 = 0x0000_0400;
+
+pub const CPU_TYPE_X86: u32 = 7;
+pub const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+pub const CPU_TYPE_ARM: u32 = 12;
+pub const CPU_TYPE_ARM64: u32 = 0x0100_000C;
+pub const CPU_TYPE_ARM64_32: u32 = 0x0200_000C;
+
+const SEARCHED_SEGMENTS: [&str; 4] = ["__TEXT", "__DATA_CONST", "__DATA", "__DATA_DIRTY"];
+
+pub struct Section {
+    pub segname: String,
+    pub sectname: String,
+    pub addr: u64,
+    pub size: u64,
+    pub offset: u32,
+    pub flags: u32,
+}
+
+impl Section {
+    pub fn is_code(&self) -> bool {
+        self.flags & (S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS) != 0
+    }
+
+    pub fn is_zerofill(&self) -> bool {
+        matches!(
+            self.flags & SECTION_TYPE,
+            S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL
+        )
+    }
+
+    pub fn is_searchable(&self) -> bool {
+        SEARCHED_SEGMENTS.contains(&self.segname.as_str()) && !self.is_code() && !self.is_zerofill()
+    }
+}
+
+struct Segment {
+    name: String,
+    vmaddr: u64,
+    fileoff: u64,
+    filesize: u64,
+}
+
+// One architecture. For a thin file `offset` is 0; inside a fat binary it is
+// where the slice starts, and every file offset below is relative to it.
+pub struct MachOSlice {
+    pub cputype: u32,
+    pub cpusubtype: u32,
+    pub is_64: bool,
+    pub offset: usize,
+    pub sections: Vec<Section>,
+    /// Pointer values the chained fixups encode, keyed by file offset, or
+    /// why they cannot be decoded.
+    pub fixups: Result<Vec<(usize, u64)>, String>,
+}
+
+impl MachOSlice {
+    pub fn arch_name(&self) -> &'static str {
+        cpu_name(self.cputype, self.cpusubtype)
+    }
+}
+
+pub struct MachOFile {
+    pub is_fat: bool,
+    pub slices: Vec<MachOSlice>,
+}
+
+impl MachOFile {
+    pub fn is_macho(data: &[u8]) -> bool {
+        matches!(read_u32_be(data, 0), Ok(FAT_MAGIC | FAT_MAGIC_64))
+            || matches!(
+                read_u32(data, 0),
+                Ok(MH_MAGIC | MH_CIGAM | MH_MAGIC_64 | MH_CIGAM_64)
+            )
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let magic = read_u32_be(data, 0)?;
+        if magic != FAT_MAGIC && magic != FAT_MAGIC_64 {
+            return Ok(Self {
+                is_fat: false,
+                slices: vec![parse_slice(data, 0, data.len())?],
+            });
+        }
+
+        let is_64 = magic == FAT_MAGIC_64;
+        let nfat_arch = read_u32_be(data, 4)? as usize;
+        let entry_size = if is_64 { 32 } else { 20 };
+        let mut slices = Vec::with_capacity(nfat_arch);
+        for i in 0..nfat_arch {
+            let entry = 8 + i * entry_size;
+            let (offset, size) = if is_64 {
+                (
+                    read_u64_be(data, entry + 8)?,
+                    read_u64_be(data, entry + 16)?,
+                )
+            } else {
+                (
+                    read_u32_be(data, entry + 8)? as u64,
+                    read_u32_be(data, entry + 12)? as u64,
+                )
+            };
+            let (offset, size) = (offset as usize, size as usize);
+            if offset.saturating_add(size) > data.len() {
+                return Err(format!("Fat slice {} out of bounds", i));
+            }
+            slices.push(parse_slice(data, offset, size)?);
+        }
+
+        Ok(Self {
+            is_fat: true,
+            slices,
+        })
+    }
+}
+
+struct SliceReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl SliceReader<'_> {
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        if self.big_endian {
+            read_u16_be(self.data, offset)
+        } else {
+            read_u16(self.data, offset)
+        }
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        if self.big_endian {
+            read_u32_be(self.data, offset)
+        } else {
+            read_u32(self.data, offset)
+        }
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, String> {
+        if self.big_endian {
+            read_u64_be(self.data, offset)
+        } else {
+            read_u64(self.data, offset)
+        }
+    }
+
+    fn name(&self, offset: usize) -> Result<String, String> {
+        let raw = self
+            .data
+            .get(offset..offset + 16)
+            .ok_or("Mach-O name out of bounds")?;
+        let len = raw.iter().position(|&b| b == 0).unwrap_or(16);
+        Ok(String::from_utf8_lossy(&raw[..len]).into_owned())
+    }
+}
+
+fn parse_slice(data: &[u8], offset: usize, size: usize) -> Result<MachOSlice, String> {
+    let slice = &data[offset..offset + size];
+    let (is_64, big_endian) = match read_u32(slice, 0)? {
+        MH_MAGIC => (false, false),
+        MH_CIGAM => (false, true),
+        MH_MAGIC_64 => (true, false),
+        MH_CIGAM_64 => (true, true),
+        magic => return Err(format!("Unknown Mach-O magic: 0x{:08X}", magic)),
+    };
+    let r = SliceReader {
+        data: slice,
+        big_endian,
+    };
+
+    let cputype = r.u32(4)?;
+    let cpusubtype = r.u32(8)? & 0x00FF_FFFF;
+    let ncmds = r.u32(16)? as usize;
+    let mut cmd_offset = if is_64 { 32 } else { 28 };
+
+    let mut segments = Vec::new();
+    let mut sections = Vec::new();
+    let mut chained_fixups = None;
+    for _ in 0..ncmds {
+        let cmd = r.u32(cmd_offset)?;
+        let cmdsize = r.u32(cmd_offset + 4)? as usize;
+        if cmdsize < 8 {
+            return Err(format!("Malformed load command at 0x{:X}", cmd_offset));
+        }
+
+        if cmd == LC_SEGMENT_64 {
+            segments.push(Segment {
+                name: r.name(cmd_offset + 8)?,
+                vmaddr: r.u64(cmd_offset + 24)?,
+                fileoff: r.u64(cmd_offset + 40)?,
+                filesize: r.u64(cmd_offset + 48)?,
+            });
+            let nsects = r.u32(cmd_offset + 64)? as usize;
+            for i in 0..nsects {
+                let sect = cmd_offset + 72 + i * 80;
+                sections.push(Section {
+                    sectname: r.name(sect)?,
+                    segname: r.name(sect + 16)?,
+                    addr: r.u64(sect + 32)?,
+                    size: r.u64(sect + 40)?,
+                    offset: r.u32(sect + 48)?,
+                    flags: r.u32(sect + 64)?,
+                });
+            }
+        } else if cmd == LC_SEGMENT {
+            segments.push(Segment {
+                name: r.name(cmd_offset + 8)?,
+                vmaddr: r.u32(cmd_offset + 24)? as u64,
+                fileoff: r.u32(cmd_offset + 32)? as u64,
+                filesize: r.u32(cmd_offset + 36)? as u64,
+            });
+            let nsects = r.u32(cmd_offset + 48)? as usize;
+            for i in 0..nsects {
+                let sect = cmd_offset + 56 + i * 68;
+                sections.push(Section {
+                    sectname: r.name(sect)?,
+                    segname: r.name(sect + 16)?,
+                    addr: r.u32(sect + 32)? as u64,
+                    size: r.u32(sect + 36)? as u64,
+                    offset: r.u32(sect + 40)?,
+                    flags: r.u32(sect + 56)?,
+                });
+            }
+        } else if cmd == LC_DYLD_CHAINED_FIXUPS {
+            chained_fixups = Some(r.u32(cmd_offset + 8)? as usize);
+        }
+
+        cmd_offset += cmdsize;
+    }
+
+    let fixups = match chained_fixups {
+        Some(dataoff) => read_chained_fixups(&r, &segments, dataoff),
+        None => Ok(Vec::new()),
+    };
+    Ok(MachOSlice {
+        cputype,
+        cpusubtype,
+        is_64,
+        offset,
+        sections,
+        fixups,
+    })
+}
+
+// Walks the pointer chains of every page the fixups name. Without this the
+// pointers in __DATA read as dyld's encoding rather than addresses.
+fn read_chained_fixups(
+    r: &SliceReader,
+    segments: &[Segment],
+    dataoff: usize,
+) -> Result<Vec<(usize, u64)>, String> {
+    // Offset-encoded targets are relative to the mach header, which __TEXT
+    // maps at its start.
+    let base = segments
+        .iter()
+        .find(|s| s.fileoff == 0 && s.filesize > 0)
+        .map_or(0, |s| s.vmaddr);
+    let starts = dataoff + r.u32(dataoff + 4)? as usize;
+    let segment_count = r.u32(starts)? as usize;
+
+    let mut fixups = Vec::new();
+    for (i, segment) in segments.iter().enumerate().take(segment_count) {
+        let info = r.u32(starts + 4 + i * 4)? as usize;
+        if info == 0 {
+            continue;
+        }
+        let info = starts + info;
+        let page_size = r.u16(info + 4)? as usize;
+        let format = r.u16(info + 6)?;
+        let page_count = r.u16(info + 20)? as usize;
+        let stride = match format {
+            DYLD_CHAINED_PTR_64 | DYLD_CHAINED_PTR_64_OFFSET => 4,
+            DYLD_CHAINED_PTR_ARM64E
+            | DYLD_CHAINED_PTR_ARM64E_USERLAND
+            | DYLD_CHAINED_PTR_ARM64E_USERLAND24 => 8,
+            _ => {
+                return Err(format!(
+                    "Chained fixup pointer format {} in {} is not supported \
+                     (only the 64-bit and arm64e formats are)",
+                    format, segment.name
+                ));
+            }
+        };
+
+        for page in 0..page_count {
+            let start = r.u16(info + 22 + page * 2)?;
+            if start == DYLD_CHAINED_PTR_START_NONE {
+                continue;
+            }
+            let mut offset = segment.fileoff as usize + page * page_size + start as usize;
+            loop {
+                let (value, next) = chained_pointer(format, r.u64(offset)?, base);
+                fixups.push((offset, value));
+                if next == 0 {
+                    break;
+                }
+                offset += next * stride;
+            }
+        }
+    }
+    Ok(fixups)
+}
+
+// The pointer a chained fixup stands for, and the distance to the next one
+// in strides. Binds to other images have no address here and read as 0.
+fn chained_pointer(format: u16, raw: u64, base: u64) -> (u64, usize) {
+    let bits = |shift: u32, width: u32| (raw >> shift) & ((1 << width) - 1);
+    if matches!(format, DYLD_CHAINED_PTR_64 | DYLD_CHAINED_PTR_64_OFFSET) {
+        let next = bits(51, 12) as usize;
+        if bits(63, 1) != 0 {
+            return (0, next);
+        }
+        let target = match format {
+            DYLD_CHAINED_PTR_64_OFFSET => base + bits(0, 36),
+            _ => bits(0, 36),
+        };
+        return (target | bits(36, 8) << 56, next);
+    }
+
+    let next = bits(51, 11) as usize;
+    let (auth, bind) = (bits(63, 1) != 0, bits(62, 1) != 0);
+    if bind {
+        (0, next)
+    } else if auth {
+        (base + bits(0, 32), next)
+    } else {
+        let target = match format {
+            DYLD_CHAINED_PTR_ARM64E => bits(0, 43),
+            _ => base + bits(0, 43),
+        };
+        (target | bits(43, 8) << 56, next)
+    }
+}
+
+pub fn cpu_name(cputype: u32, cpusubtype: u32) -> &'static str {
+    match (cputype, cpusubtype) {
+        (CPU_TYPE_X86, _) => "i386",
+        (CPU_TYPE_X86_64, _) => "x86_64",
+        (CPU_TYPE_ARM, 9) => "armv7",
+        (CPU_TYPE_ARM, 11) => "armv7s",
+        (CPU_TYPE_ARM, _) => "arm",
+        (CPU_TYPE_ARM64, 2) => "arm64e",
+        (CPU_TYPE_ARM64, _) => "arm64",
+        (CPU_TYPE_ARM64_32, _) => "arm64_32",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u64 = 0x1_0000_0000;
+    const DATA: usize = 0x1000;
+    const FIXUPS: usize = 0x2000;
+
+    // A thin arm64 dylib: __TEXT from the start of the file, __DATA holding
+    // `chain` at DATA, and chained fixups of `format` starting the chain at
+    // the first pointer of the page.
+    fn dylib(format: u16, chain: &[u64]) -> Vec<u8> {
+        let mut data = vec![0; FIXUPS + 0x100];
+        let put32 = |data: &mut Vec<u8>, at: usize, v: u32| {
+            data[at..at + 4].copy_from_slice(&v.to_le_bytes());
+        };
+        let put64 = |data: &mut Vec<u8>, at: usize, v: u64| {
+            data[at..at + 8].copy_from_slice(&v.to_le_bytes());
+        };
+        put32(&mut data, 0, MH_MAGIC_64);
+        put32(&mut data, 4, CPU_TYPE_ARM64);
+        put32(&mut data, 12, 6);
+        put32(&mut data, 16, 3);
+
+        let text = 32;
+        put32(&mut data, text, LC_SEGMENT_64);
+        put32(&mut data, text + 4, 72);
+        data[text + 8..text + 14].copy_from_slice(b"__TEXT");
+        put64(&mut data, text + 24, BASE);
+        put64(&mut data, text + 48, DATA as u64);
+
+        let segment = text + 72;
+        put32(&mut data, segment, LC_SEGMENT_64);
+        put32(&mut data, segment + 4, 72 + 80);
+        data[segment + 8..segment + 14].copy_from_slice(b"__DATA");
+        put64(&mut data, segment + 24, BASE + DATA as u64);
+        put64(&mut data, segment + 40, DATA as u64);
+        put64(&mut data, segment + 48, 0x1000);
+        put32(&mut data, segment + 64, 1);
+        let section = segment + 72;
+        data[section..section + 6].copy_from_slice(b"__data");
+        data[section + 16..section + 22].copy_from_slice(b"__DATA");
+        put64(&mut data, section + 32, BASE + DATA as u64);
+        put64(&mut data, section + 40, 0x1000);
+        put32(&mut data, section + 48, DATA as u32);
+
+        let command = section + 80;
+        put32(&mut data, command, LC_DYLD_CHAINED_FIXUPS);
+        put32(&mut data, command + 4, 16);
+        put32(&mut data, command + 8, FIXUPS as u32);
+        put32(&mut data, command + 12, 0x100);
+
+        // The fixups header, the per-segment offsets and __DATA's starts.
+        let starts = FIXUPS + 32;
+        put32(&mut data, FIXUPS + 4, 32);
+        put32(&mut data, starts, 2);
+        put32(&mut data, starts + 8, 16);
+        let info = starts + 16;
+        put32(&mut data, info, 24);
+        data[info + 4..info + 6].copy_from_slice(&0x1000u16.to_le_bytes());
+        data[info + 6..info + 8].copy_from_slice(&format.to_le_bytes());
+        put64(&mut data, info + 8, DATA as u64);
+        data[info + 20..info + 22].copy_from_slice(&1u16.to_le_bytes());
+
+        for (i, &pointer) in chain.iter().enumerate() {
+            put64(&mut data, DATA + i * 8, pointer);
+        }
+        data
+    }
+
+    fn fixups(data: &[u8]) -> Result<Vec<(usize, u64)>, String> {
+        let mut macho = MachOFile::parse(data).unwrap();
+        macho.slices.remove(0).fixups
+    }
+
+    #[test]
+    fn chained_rebases_are_decoded() {
+        let chain = [
+            // Rebase to __DATA, next pointer two strides (8 bytes) on.
+            (2 << 51) | (BASE + 0x1100),
+            // Bind, next two strides on.
+            (1 << 63) | (2 << 51),
+            // Rebase with a tag in the top byte, end of chain.
+            (0x5A << 36) | (BASE + 0x1200),
+        ];
+        let decoded = fixups(&dylib(DYLD_CHAINED_PTR_64, &chain)).unwrap();
+        assert_eq!(
+            decoded,
+            [
+                (DATA, BASE + 0x1100),
+                (DATA + 8, 0),
+                (DATA + 16, 0x5A00_0000_0000_0000 | (BASE + 0x1200)),
+            ]
+        );
+    }
+
+    #[test]
+    fn offset_targets_are_relative_to_the_image() {
+        let chain = [
+            // Plain rebase, next pointer one stride (8 bytes) on.
+            (1 << 51) | 0x1100,
+            // Authenticated rebase, end of chain.
+            (1 << 63) | (0x1234 << 32) | 0x1200,
+        ];
+        let decoded = fixups(&dylib(DYLD_CHAINED_PTR_ARM64E_USERLAND, &chain)).unwrap();
+        assert_eq!(decoded, [(DATA, BASE + 0x1100), (DATA + 8, BASE + 0x1200)]);
+
+        let decoded = fixups(&dylib(DYLD_CHAINED_PTR_64_OFFSET, &[0x1100])).unwrap();
+        assert_eq!(decoded, [(DATA, BASE + 0x1100)]);
+    }
+
+    #[test]
+    fn unsupported_pointer_format_is_reported() {
+        let error = fixups(&dylib(3, &[0x1100])).unwrap_err();
+        assert!(error.contains("format 3"));
+        assert!(error.contains("__DATA"));
+    }
+}
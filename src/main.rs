@@ -1,20 +1,41 @@
-mod extractor;
-mod hgxxtea;
-mod metadata;
-mod utils;
-
-use extractor::Extractor;
-use metadata::GlobalMetadata;
+use hg_metadata_dumper::pipeline::{self, DumpOptions};
+use hg_metadata_dumper::strings::StringsFormat;
+use hg_metadata_dumper::types::TypeTable;
+use hg_metadata_dumper::utils::{self, fmt_bytes_hex, fmt_size};
+use hg_metadata_dumper::{
+    DecryptConfig, Extractor, GlobalMetadata, LiteralDecryption, ParseOptions,
+};
+use hg_metadata_dumper::{archive, cipher, keysearch};
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use utils::{fmt_bytes_hex, fmt_size};
+use std::path::Path;
+
+const DEFAULT_INPUTS: [&str; 2] = ["GameAssembly.dll", "libil2cpp.so"];
+
+enum Command {
+    Extract,
+    Repack { metadata: String },
+}
 
 struct Args {
+    command: Command,
     input: String,
     output: String,
     verbose: bool,
     decrypt_strings: bool,
+    list_candidates: bool,
+    candidate: Option<usize>,
+    abi: Option<String>,
+    all_abis: bool,
+    key: Option<String>,
+    key_file: Option<String>,
+    find_key: bool,
+    wordlist: Option<String>,
+    cipher: Option<String>,
+    dump: Option<String>,
+    strings: Option<String>,
+    strings_format: Option<StringsFormat>,
 }
 
 impl Args {
@@ -22,97 +43,459 @@ impl Args {
         let args: Vec<String> = env::args().collect();
         if args.iter().any(|arg| arg == "--help" || arg == "-h") {
             println!("Usage: {} [OPTIONS] [INPUT] [OUTPUT]", args[0]);
+            println!(
+                "       {} repack [OPTIONS] <BINARY> <METADATA> <OUTPUT>",
+                args[0]
+            );
             println!("\nArguments:");
-            println!("  [INPUT]     Input PE file path (default: GameAssembly.dll)");
+            println!(
+                "  [INPUT]     Input PE, ELF or Mach-O file, or an APK/XAPK/IPA/ZIP archive (default: GameAssembly.dll or libil2cpp.so)"
+            );
             println!("  [OUTPUT]    Output decrypted file path (default: global-metadata.dat)");
+            println!("\nRepack arguments:");
+            println!("  <BINARY>    PE, ELF or Mach-O file holding the original blob");
+            println!("  <METADATA>  Decrypted global-metadata.dat to encrypt and write back");
+            println!("  <OUTPUT>    Patched binary path");
             println!("\nOptions:");
-            println!("  -d, --decrypt-strings  Decrypt string literals");
-            println!("  -v, --verbose          Show detailed metadata info");
-            println!("  -h, --help             Show this help message");
+            println!(
+                "  -c, --candidate <INDEX>  Use the candidate blob at INDEX instead of the best one"
+            );
+            println!("  -l, --list-candidates    List candidate blobs and exit");
+            println!(
+                "  -a, --abi <ABI>          Pick the library for ABI from an archive (e.g. arm64-v8a)"
+            );
+            println!("  -A, --all-abis           Process every library found in an archive");
+            println!(
+                "  -k, --key <KEY>          XXTEA key as ASCII, or hex with a 0x prefix (default: E8FF)"
+            );
+            println!(
+                "      --key-file <PATH>    Read the key from a file (text as for --key, or raw bytes)"
+            );
+            println!(
+                "      --cipher <NAME>      Cipher variant or custom XXTEA spec (default: xxtea)"
+            );
+            println!("      --list-ciphers       List the built-in cipher variants");
+            println!("  -f, --find-key           Search the binary for the key before extracting");
+            println!(
+                "  -w, --wordlist <PATH>    Extra keys to try, one per line (implies --find-key)"
+            );
+            println!(
+                "  -d, --decrypt-strings    Decrypt string literals, detecting how they are obfuscated"
+            );
+            println!(
+                "      --dump <DIR>         Also write Il2CppDumper-style dump.cs, script.json, il2cpp.h and stringliteral.json to DIR"
+            );
+            println!(
+                "      --strings <PATH>     Also export the string literals with their offsets and usage slots to PATH"
+            );
+            println!(
+                "      --strings-format <FMT>  json, csv or jsonl (default: from the PATH extension, else json)"
+            );
+            println!("  -v, --verbose            Show detailed metadata info");
+            println!("  -h, --help               Show this help message");
             std::process::exit(0);
         }
 
-        let verbose = args.iter().any(|arg| arg == "--verbose" || arg == "-v");
-        let decrypt_strings = args.iter().any(|arg| arg == "--decrypt-strings" || arg == "-d");
-        let positional: Vec<&String> = args
-            .iter()
-            .skip(1)
-            .filter(|arg| !arg.starts_with("--") && *arg != "-v" && *arg != "-h" && *arg != "-d")
-            .collect();
+        let mut verbose = false;
+        let mut decrypt_strings = false;
+        let mut list_candidates = false;
+        let mut candidate = None;
+        let mut abi = None;
+        let mut all_abis = false;
+        let mut key = None;
+        let mut key_file = None;
+        let mut find_key = false;
+        let mut wordlist = None;
+        let mut cipher = None;
+        let mut dump = None;
+        let mut strings = None;
+        let mut strings_format = None;
+        let mut positional = Vec::new();
+
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "-v" | "--verbose" => verbose = true,
+                "-d" | "--decrypt-strings" => decrypt_strings = true,
+                "-l" | "--list-candidates" => list_candidates = true,
+                "-c" | "--candidate" => {
+                    let value = iter.next().and_then(|v| v.parse().ok());
+                    candidate =
+                        Some(value.unwrap_or_else(|| fail(&format!("{} expects an index", arg))));
+                }
+                "-a" | "--abi" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    abi = Some(value.unwrap_or_else(|| fail(&format!("{} expects a value", arg))));
+                }
+                "-A" | "--all-abis" => all_abis = true,
+                "-k" | "--key" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    key = Some(value.unwrap_or_else(|| fail(&format!("{} expects a value", arg))));
+                }
+                "--key-file" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    key_file =
+                        Some(value.unwrap_or_else(|| fail(&format!("{} expects a path", arg))));
+                }
+                "-f" | "--find-key" => find_key = true,
+                "-w" | "--wordlist" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    wordlist =
+                        Some(value.unwrap_or_else(|| fail(&format!("{} expects a path", arg))));
+                    find_key = true;
+                }
+                "--cipher" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    cipher =
+                        Some(value.unwrap_or_else(|| fail(&format!("{} expects a name", arg))));
+                }
+                "--dump" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    dump = Some(
+                        value.unwrap_or_else(|| fail(&format!("{} expects a directory", arg))),
+                    );
+                }
+                "--strings" => {
+                    let value = iter.next().map(|v| v.to_string());
+                    strings =
+                        Some(value.unwrap_or_else(|| fail(&format!("{} expects a path", arg))));
+                }
+                "--strings-format" => {
+                    let value = iter.next().map(|v| StringsFormat::by_name(v));
+                    strings_format = Some(match value {
+                        Some(Ok(format)) => format,
+                        Some(Err(e)) => fail(&e),
+                        None => fail(&format!("{} expects a format", arg)),
+                    });
+                }
+                "--list-ciphers" => {
+                    for (name, description) in cipher::VARIANTS {
+                        println!("  {:<18} {}", name, description);
+                    }
+                    println!(
+                        "  {:<18} Custom XXTEA, e.g. xxtea:delta=0x61C88647,rounds=32,length,be,repeat-key",
+                        "xxtea:<options>"
+                    );
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') => fail(&format!("Unknown option: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let (command, input, output) = match positional.as_slice() {
+            [first, rest @ ..] if first.as_str() == "repack" => {
+                let [input, metadata, output] = rest else {
+                    fail("repack expects <BINARY> <METADATA> <OUTPUT>");
+                };
+                let metadata = metadata.to_string();
+                (
+                    Command::Repack { metadata },
+                    input.to_string(),
+                    output.to_string(),
+                )
+            }
+            _ => (
+                Command::Extract,
+                positional
+                    .first()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(default_input),
+                positional
+                    .get(1)
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "global-metadata.dat".to_string()),
+            ),
+        };
 
-        let input = positional
-            .first()
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "GameAssembly.dll".to_string());
-        let output = positional
-            .get(1)
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "global-metadata.dat".to_string());
+        Args {
+            command,
+            input,
+            output,
+            verbose,
+            decrypt_strings,
+            list_candidates,
+            candidate,
+            abi,
+            all_abis,
+            key,
+            key_file,
+            find_key,
+            wordlist,
+            cipher,
+            dump,
+            strings,
+            strings_format,
+        }
+    }
+}
 
-        Args { input, output, verbose, decrypt_strings }
+fn labelled_output(output: &str, label: &str) -> String {
+    let path = Path::new(output);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, label, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, label),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+fn decrypt_config(args: &Args) -> Result<DecryptConfig, String> {
+    let config = match (&args.key, &args.key_file) {
+        (Some(_), Some(_)) => return Err("--key and --key-file are mutually exclusive".to_string()),
+        (Some(key), None) => DecryptConfig::with_key(utils::parse_key(key)?),
+        (None, Some(path)) => DecryptConfig::with_key(utils::read_key_file(path)?),
+        (None, None) => DecryptConfig::default(),
+    };
+    match &args.cipher {
+        Some(name) => Ok(config.with_cipher(cipher::by_name(name)?)),
+        None => Ok(config),
     }
 }
 
+fn recover_key(
+    args: &Args,
+    config: &DecryptConfig,
+    extractor: &mut Extractor,
+) -> Result<DecryptConfig, String> {
+    let wordlist = match &args.wordlist {
+        Some(path) => keysearch::read_wordlist(path)?,
+        None => Vec::new(),
+    };
+    let start_time = std::time::Instant::now();
+    let report = extractor.find_keys(&wordlist, config.cipher.as_ref())?;
+    let duration = start_time.elapsed();
+
+    let Some(first) = report.matches.first() else {
+        return Err(format!(
+            "No key decrypted a metadata header ({} candidate(s) against {} blob span(s))",
+            report.candidates, report.spans
+        ));
+    };
+    println!(
+        "✓ Recovered key in {:.3}s ({} candidate(s) against {} blob span(s))",
+        duration.as_secs_f64(),
+        report.candidates,
+        report.spans
+    );
+    for m in &report.matches {
+        println!("  Key:     {} ({})", utils::format_key(&m.key), m.source);
+        println!(
+            "  Blob:    offset 0x{:08X}, {}, version {}",
+            m.file_offset,
+            fmt_size(m.size),
+            m.version
+        );
+    }
+    println!();
+    Ok(DecryptConfig::with_key(first.key.to_vec()).with_cipher(config.cipher.clone()))
+}
+
+fn default_input() -> String {
+    DEFAULT_INPUTS
+        .iter()
+        .find(|path| Path::new(path).exists())
+        .unwrap_or(&DEFAULT_INPUTS[0])
+        .to_string()
+}
+
+fn print_candidates(extractor: &Extractor) {
+    let label = address_label(extractor);
+    println!(
+        "Found {} candidate blob(s):",
+        extractor.get_candidates().len()
+    );
+    for (i, candidate) in extractor.get_candidates().iter().enumerate() {
+        let marker = if i == extractor.selected_index() {
+            "*"
+        } else {
+            " "
+        };
+        let section = match &candidate.location.slice {
+            Some(slice) => format!("{} {}", slice, candidate.location.section),
+            None => candidate.location.section.clone(),
+        };
+        println!(
+            "{} [{}] {:<20} {:<3} 0x{:08X}  Offset 0x{:08X}  {:>10} bytes  score {:>3}  {}",
+            marker,
+            i,
+            section,
+            label,
+            candidate.location.address,
+            candidate.location.file_offset,
+            candidate.data.len(),
+            candidate.score,
+            candidate.location.boundary
+        );
+    }
+    println!();
+}
+
+fn address_label(extractor: &Extractor) -> &'static str {
+    extractor
+        .get_format()
+        .map(|f| f.address_label())
+        .unwrap_or("RVA")
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("✗ {}", message);
+    std::process::exit(1);
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let mut extractor = Extractor::new(&args.input);
-    if let Err(e) = extractor.process() {
-        eprintln!("✗ Extraction failed: {}", e);
+    let config = decrypt_config(&args).unwrap_or_else(|e| fail(&e));
+
+    if let Command::Repack { metadata } = &args.command {
+        if let Err(e) = repack(&args, &config, metadata) {
+            fail(&e);
+        }
+        return Ok(());
+    }
+
+    if !archive::is_archive(&args.input) {
+        let extractor = Extractor::new(&args.input);
+        if let Err(e) = dump(&args, &config, extractor, None) {
+            fail(&e);
+        }
+        return Ok(());
+    }
+
+    let mut targets = archive::list_targets(&args.input)
+        .unwrap_or_else(|e| fail(&format!("Failed to read archive: {}", e)));
+    if let Some(abi) = &args.abi {
+        targets.retain(|t| &t.label == abi);
+    } else if !args.all_abis {
+        targets.truncate(1);
+    }
+    if targets.is_empty() {
+        fail("No libil2cpp.so, UnityFramework or GameAssembly.dll found in archive");
+    }
+
+    let mut failed = false;
+    for target in &targets {
+        let label = (targets.len() > 1).then_some(target.label.as_str());
+        let result = archive::load_target(&args.input, target).and_then(|data| {
+            let source = format!("{}!{}", args.input, target.display_path());
+            dump(&args, &config, Extractor::from_bytes(source, data), label)
+        });
+        if let Err(e) = result {
+            eprintln!("✗ {}: {}", target.display_path(), e);
+            failed = true;
+        }
+        if targets.len() > 1 {
+            println!();
+        }
+    }
+    if failed {
         std::process::exit(1);
     }
-    let data = extractor.get_valid_data();
-    println!("✓ Extracted encrypted data from {}", args.input);
-    println!();
+    Ok(())
+}
 
-    let key = {
-        let mut k = vec![0u8; 16];
-        k[0] = b'E';
-        k[1] = b'8';
-        k[2] = b'F';
-        k[3] = b'F';
-        k
+fn dump(
+    args: &Args,
+    config: &DecryptConfig,
+    mut extractor: Extractor,
+    label: Option<&str>,
+) -> Result<(), String> {
+    // Outputs for one library out of several carry its ABI in the name.
+    let labelled = |path: &str| match label {
+        Some(label) => labelled_output(path, label),
+        None => path.to_string(),
+    };
+    let output = labelled(&args.output);
+    let recovered;
+    let config = if args.find_key {
+        recovered = recover_key(args, config, &mut extractor)?;
+        &recovered
+    } else {
+        config
     };
+    pipeline::extract(&mut extractor, config, args.candidate)?;
+    if args.list_candidates || extractor.get_candidates().len() > 1 {
+        print_candidates(&extractor);
+        if args.list_candidates {
+            return Ok(());
+        }
+    }
+    let data = extractor.get_valid_data();
+    println!("✓ Extracted encrypted data from {}", extractor.get_source());
+    if let Some(format) = extractor.get_format() {
+        println!("  Format:  {} ({})", format.name(), extractor.get_arch());
+    }
+    if let Some(location) = extractor.get_location() {
+        if let Some(slice) = &location.slice {
+            println!("  Slice:   {}", slice);
+        }
+        println!("  Section: {}", location.section);
+        println!(
+            "  {:<8} 0x{:08X}",
+            format!("{}:", address_label(&extractor)),
+            location.address
+        );
+        println!("  Offset:  0x{:08X}", location.file_offset);
+        println!("  Bounds:  {}", location.boundary);
+    }
+    println!("  Size:    {}", fmt_size(data.len()));
+    println!();
 
     let start_time = std::time::Instant::now();
-    let mut decrypted = match hgxxtea::decrypt(data, &key) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("✗ Decryption failed: {}", e);
-            std::process::exit(1);
-        }
-    };
+    let decrypted = config
+        .decrypt(data)
+        .map_err(|e| format!("Decryption failed: {}", e))?;
     let duration = start_time.elapsed();
     println!("✓ Decrypted in {:.3}s", duration.as_secs_f64());
+    if args.cipher.is_some() {
+        println!("  Cipher:  {}", config.cipher.name());
+    }
     println!();
 
-    match GlobalMetadata::parse(&decrypted) {
-        Ok(metadata) => {
-            if metadata.is_valid() {
-                println!("✓ Valid global-metadata.dat");
-                if args.decrypt_strings {
-                    if let Err(e) = metadata::decrypt_string_literals(&metadata, &mut decrypted) {
-                        eprintln!("  Failed to decrypt string literals: {}", e);
-                    }
-                }
-
-                println!("  Magic:   {}", fmt_bytes_hex(&metadata.magic_bytes()));
-                println!("  Version: {}", metadata.header.version);
-                if args.verbose {
-                    println!("  String Literals:  {}", metadata.string_literals.len());
-                    println!("  Images:           {}", metadata.images.len());
-                    println!("  Assemblies:       {}", metadata.assemblies.len());
-                    println!("  Type Definitions: {}", metadata.type_definitions.len());
-                    println!("  Usage Lists:      {}", metadata.metadata_usage_lists.len());
-                    println!("  Usage Pairs:      {}", metadata.metadata_usage_pairs.len());
-                }
+    let options = DumpOptions {
+        parse: ParseOptions {
+            literals: if args.decrypt_strings {
+                LiteralDecryption::Detect
             } else {
-                eprintln!("⚠ Header magic mismatch");
-                eprintln!("  Expected: AF 1B B1 FA");
-                eprintln!("  Got:      {}", fmt_bytes_hex(&metadata.magic_bytes()));
-                eprintln!("  The decrypted data may not be a valid global-metadata.dat");
+                LiteralDecryption::Keep
+            },
+        },
+        types: args.verbose,
+        files: args.dump.is_some(),
+        strings: args.strings.as_ref().map(|path| {
+            args.strings_format
+                .unwrap_or_else(|| StringsFormat::for_path(path))
+        }),
+    };
+    let dump = pipeline::dump(&extractor, decrypted, &options);
+    let valid = dump.metadata.as_ref().is_ok_and(GlobalMetadata::is_valid);
+    match &dump.metadata {
+        Ok(metadata) if valid => {
+            println!("✓ Valid global-metadata.dat");
+            println!("  Magic:   {}", fmt_bytes_hex(&metadata.magic_bytes()));
+            println!("  Version: {}", metadata.version);
+            if let Some(detection) = metadata.literal_detection {
+                println!(
+                    "  Strings: {} (score {:.2} over {} literal(s))",
+                    detection.scheme, detection.score, detection.samples
+                );
+            } else if args.decrypt_strings {
+                println!("⚠ No string literal scheme found; literals left as they are");
+            }
+            if args.verbose {
+                print_counts(metadata, dump.types.as_ref());
             }
         }
+        Ok(metadata) => {
+            eprintln!("⚠ Header magic mismatch");
+            eprintln!("  Expected: AF 1B B1 FA");
+            eprintln!("  Got:      {}", fmt_bytes_hex(&metadata.magic_bytes()));
+            eprintln!("  The decrypted data may not be a valid global-metadata.dat");
+        }
         Err(e) => {
             eprintln!("⚠ Failed to parse metadata: {}", e);
         }
@@ -120,13 +503,124 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!();
 
     let mut out_file =
-        File::create(&args.output).map_err(|e| format!("Failed to create output file: {}", e))?;
+        File::create(&output).map_err(|e| format!("Failed to create output file: {}", e))?;
     out_file
-        .write_all(&decrypted)
+        .write_all(&dump.data)
         .map_err(|e| format!("Failed to write output file: {}", e))?;
 
-    println!("✓ Saved to {}", args.output);
-    println!("  File size: {}", fmt_size(decrypted.len()));
+    println!("✓ Saved to {}", output);
+    println!("  File size: {}", fmt_size(dump.data.len()));
+
+    if let Some(path) = &args.strings {
+        println!();
+        match (&dump.strings, &dump.metadata) {
+            (Some(contents), Ok(metadata)) => {
+                let path = labelled(path);
+                std::fs::write(&path, contents)
+                    .map_err(|e| format!("Failed to write string literals: {}", e))?;
+                println!(
+                    "✓ Saved {} string literal(s) to {}",
+                    metadata.string_literals.len(),
+                    path
+                );
+                println!("  File size: {}", fmt_size(contents.len()));
+            }
+            _ => eprintln!("⚠ Skipped string literals: no valid metadata"),
+        }
+    }
 
+    if let Some(dir) = &args.dump {
+        println!();
+        if !valid {
+            eprintln!("⚠ Skipped dump: no valid metadata");
+            return Ok(());
+        }
+        let dir = labelled(dir);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create dump directory: {}", e))?;
+        for (name, contents) in &dump.files {
+            std::fs::write(Path::new(&dir).join(name), contents)
+                .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        }
+        println!("✓ Saved dump to {}", dir);
+        for (name, contents) in &dump.files {
+            println!(
+                "  {:<19} {}",
+                format!("{}:", name),
+                fmt_size(contents.len())
+            );
+        }
+        if let Some(Err(e)) = &dump.types {
+            eprintln!("⚠ Wrote dump.cs only: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_counts(metadata: &GlobalMetadata, types: Option<&Result<TypeTable, String>>) {
+    println!("  String Literals:  {}", metadata.string_literals.len());
+    println!("  Images:           {}", metadata.images.len());
+    println!("  Assemblies:       {}", metadata.assemblies.len());
+    println!("  Type Definitions: {}", metadata.type_definitions.len());
+    println!("  Methods:          {}", metadata.methods.len());
+    println!("  Fields:           {}", metadata.fields.len());
+    println!("  Parameters:       {}", metadata.parameters.len());
+    println!("  Properties:       {}", metadata.properties.len());
+    println!("  Events:           {}", metadata.events.len());
+    println!("  Generic Params:   {}", metadata.generic_parameters.len());
+    match types {
+        Some(Ok(types)) => println!("  Binary Types:     {}", types.binary_type_count()),
+        Some(Err(e)) => println!("  Binary Types:     none ({})", e),
+        None => {}
+    }
+    // Usage tables moved into the binary in 27.
+    if metadata.version.major < 27 {
+        println!(
+            "  Usage Lists:      {}",
+            metadata.metadata_usage_lists.len()
+        );
+        println!(
+            "  Usage Pairs:      {}",
+            metadata.metadata_usage_pairs.len()
+        );
+    }
+}
+
+fn repack(args: &Args, config: &DecryptConfig, metadata_path: &str) -> Result<(), String> {
+    if archive::is_archive(&args.input) {
+        return Err("repack needs the extracted binary, not an archive".to_string());
+    }
+    let plain =
+        std::fs::read(metadata_path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+
+    let mut extractor = Extractor::new(&args.input);
+    pipeline::extract(&mut extractor, config, args.candidate)?;
+    let location = extractor.get_location().ok_or("No blob to replace")?;
+    let repack = pipeline::repack(&extractor, config, &plain)?;
+    let patch = &repack.patch;
+
+    println!("✓ Encrypted {} into {}", metadata_path, args.input);
+    println!("  Section: {}", location.section);
+    println!("  Offset:  0x{:08X}", patch.file_offset);
+    println!(
+        "  Size:    {} -> {}",
+        fmt_size(patch.old_size),
+        fmt_size(patch.new_size)
+    );
+    if let Some(grown) = &patch.grown {
+        println!("  Grown:   {}", grown);
+    }
+    println!();
+    println!("✓ Verified round trip");
+    println!();
+
+    let mut out_file =
+        File::create(&args.output).map_err(|e| format!("Failed to create output file: {}", e))?;
+    out_file
+        .write_all(&repack.binary)
+        .map_err(|e| format!("Failed to write output file: {}", e))?;
+    println!("✓ Saved to {}", args.output);
+    println!("  File size: {}", fmt_size(repack.binary.len()));
     Ok(())
 }
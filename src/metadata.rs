@@ -1,12 +1,99 @@
-#![allow(dead_code)]
+use crate::literals::{self, Detection, LiteralScheme};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-const EXPECTED_MAGIC: u32 = 0xFAB11BAF;
-const STRING_LITERAL_INFO_SIZE: usize = 8;
-const IMAGE_DEF_SIZE: usize = 40;
-const ASSEMBLY_DEF_SIZE: usize = 68;
-const TYPE_DEF_SIZE: usize = 100;
-const METADATA_USAGE_LIST_SIZE: usize = 8;
-const METADATA_USAGE_PAIR_SIZE: usize = 8;
+pub(crate) const EXPECTED_MAGIC: u32 = 0xFAB11BAF;
+const V24_2_HEADER_SIZE: i32 = 264;
+
+/// The header only carries the major version. The minor part is the
+/// sub-version Il2CppDumper uses for 24.x releases, which changed record
+/// layouts without bumping the header, so it is worked out from section sizes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MetadataVersion {
+    /// The version in the header.
+    pub major: i32,
+    /// The 24.x sub-version; 0 for every other version.
+    pub minor: i32,
+}
+
+impl MetadataVersion {
+    /// Custom attribute indices sit on the records themselves.
+    pub const V24_0: Self = Self::new(24, 0);
+    /// Custom attributes move into ranges on each image.
+    pub const V24_1: Self = Self::new(24, 1);
+    /// rgctx entries and method code indices move into the binary.
+    pub const V24_2: Self = Self::new(24, 2);
+    /// Assembly names lose their hash value index.
+    pub const V24_4: Self = Self::new(24, 4);
+    /// Metadata usages move into the binary.
+    pub const V27_0: Self = Self::new(27, 0);
+    /// Custom attributes become blobs with their arguments.
+    pub const V29_0: Self = Self::new(29, 0);
+    /// Methods gain a return parameter token.
+    pub const V31_0: Self = Self::new(31, 0);
+
+    /// A version from its major and minor parts.
+    pub const fn new(major: i32, minor: i32) -> Self {
+        Self { major, minor }
+    }
+
+    // 24.3 and 24.5 only changed structures in the binary. From the metadata
+    // alone they look like 24.2 and 24.4 and share their layouts, as do the
+    // 27.x and 29.x point releases.
+    fn detect(data: &[u8], header: &GlobalMetadataHeader) -> Result<Self, String> {
+        match header.version {
+            24 => Ok(Self::detect_v24(data, header)),
+            27 => Ok(Self::V27_0),
+            29 => Ok(Self::V29_0),
+            31 => Ok(Self::V31_0),
+            version => Err(format!(
+                "Unsupported metadata version: {} (expected 24, 27, 29 or 31)",
+                version
+            )),
+        }
+    }
+
+    fn detect_v24(data: &[u8], header: &GlobalMetadataHeader) -> Self {
+        if header.string_literal_offset == V24_2_HEADER_SIZE {
+            // 24.4 dropped hashValueIndex, shrinking assemblies to 64 bytes
+            // while there is still one per image.
+            let images = header.images_count / ImageDefinition::size(Self::V24_2) as i32;
+            let assemblies = header.assemblies_count / AssemblyDefinition::size(Self::V24_2) as i32;
+            return if assemblies < images {
+                Self::V24_4
+            } else {
+                Self::V24_2
+            };
+        }
+
+        // 24.1 moved custom attribute indices out of type definitions (104
+        // to 100 bytes) into a range on each image (32 to 40 bytes).
+        let fits = |version| {
+            (header.type_definitions_count as usize).is_multiple_of(TypeDefinition::size(version))
+                && (header.images_count as usize).is_multiple_of(ImageDefinition::size(version))
+        };
+        match (fits(Self::V24_0), fits(Self::V24_1)) {
+            (true, false) => Self::V24_0,
+            (false, true) => Self::V24_1,
+            // Both sizes divide evenly. Read the images as 24.0, where every
+            // image token is 1; on 24.1 data the stride drifts off the token.
+            _ => {
+                let images = section(data, header.images_offset, header.images_count, "Images")
+                    .unwrap_or_default();
+                let drifted = images
+                    .chunks_exact(ImageDefinition::size(Self::V24_0))
+                    .any(|image| Reader::new(image, 28).read_u32().is_ok_and(|t| t != 1));
+                if drifted { Self::V24_1 } else { Self::V24_0 }
+            }
+        }
+    }
+}
+
+impl fmt::Display for MetadataVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
 
 struct Reader<'a> {
     data: &'a [u8],
@@ -68,161 +155,476 @@ impl<'a> Reader<'a> {
         self.pos += n;
         Ok(slice)
     }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        let bytes = self.read_bytes(8)?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    // The variable-length encoding 29 introduced for blob integers.
+    fn read_compressed_u32(&mut self) -> Result<u32, String> {
+        let first = self.read_u8()? as u32;
+        Ok(match first {
+            _ if first & 0x80 == 0 => first,
+            _ if first & 0xC0 == 0x80 => (first & 0x3F) << 8 | self.read_u8()? as u32,
+            _ if first & 0xE0 == 0xC0 => {
+                let rest = self.read_bytes(3)?;
+                (first & 0x1F) << 24
+                    | (rest[0] as u32) << 16
+                    | (rest[1] as u32) << 8
+                    | rest[2] as u32
+            }
+            0xF0 => self.read_u32()?,
+            0xFE => u32::MAX - 1,
+            0xFF => u32::MAX,
+            _ => return Err(format!("Invalid compressed integer prefix 0x{:02X}", first)),
+        })
+    }
+
+    // Zigzag-style: the low bit is the sign, u32::MAX is i32::MIN.
+    fn read_compressed_i32(&mut self) -> Result<i32, String> {
+        let encoded = self.read_compressed_u32()?;
+        if encoded == u32::MAX {
+            return Ok(i32::MIN);
+        }
+        let magnitude = (encoded >> 1) as i32;
+        Ok(if encoded & 1 != 0 {
+            -magnitude - 1
+        } else {
+            magnitude
+        })
+    }
+
+    fn peek_i32(&self) -> Result<i32, String> {
+        Reader::new(self.data, self.pos).read_i32()
+    }
+
+    // Fields a version lacks read as -1 (no index) without consuming input.
+    fn read_i32_if(&mut self, present: bool) -> Result<i32, String> {
+        if present { self.read_i32() } else { Ok(-1) }
+    }
+
+    fn read_u32_if(&mut self, present: bool) -> Result<u32, String> {
+        if present { self.read_u32() } else { Ok(0) }
+    }
+
+    fn read_section(&mut self, present: bool) -> Result<(i32, i32), String> {
+        if present {
+            Ok((self.read_i32()?, self.read_i32()?))
+        } else {
+            Ok((0, 0))
+        }
+    }
+}
+
+fn section<'a>(data: &'a [u8], offset: i32, size: i32, name: &str) -> Result<&'a [u8], String> {
+    let start = usize::try_from(offset).ok();
+    let len = usize::try_from(size).ok();
+    start
+        .zip(len)
+        .and_then(|(start, len)| data.get(start..start.checked_add(len)?))
+        .ok_or_else(|| format!("{} section out of bounds", name))
+}
+
+// A fixed-size metadata record whose layout may depend on the version.
+trait Record: Sized {
+    fn size(version: MetadataVersion) -> usize;
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String>;
+}
+
+fn read_table<T: Record>(
+    data: &[u8],
+    offset: i32,
+    size: i32,
+    version: MetadataVersion,
+) -> Result<Vec<T>, String> {
+    let bytes = section(data, offset, size, "Table")?;
+    let record_size = T::size(version);
+    let count = bytes.len() / record_size;
+    let mut records = Vec::with_capacity(count);
+    for i in 0..count {
+        records.push(T::read(&mut Reader::new(bytes, i * record_size), version)?);
+    }
+    Ok(records)
 }
 
+/// The file header: the magic, the version and where each section is.
+/// The sections' (offset, size in bytes) pairs are listed by `sections`.
 pub struct GlobalMetadataHeader {
+    /// The magic, 0xFAB11BAF in a valid file.
     pub sanity: u32,
+    /// The major version.
     pub version: i32,
-    pub string_literal_offset: i32,
-    pub string_literal_count: i32,
-    pub string_literal_data_offset: i32,
-    pub string_literal_data_count: i32,
-    pub string_offset: i32,
-    pub string_count: i32,
-    pub events_offset: i32,
-    pub events_count: i32,
-    pub properties_offset: i32,
-    pub properties_count: i32,
-    pub methods_offset: i32,
-    pub methods_count: i32,
-    pub parameter_default_values_offset: i32,
-    pub parameter_default_values_count: i32,
-    pub field_default_values_offset: i32,
-    pub field_default_values_count: i32,
-    pub field_and_parameter_default_value_data_offset: i32,
-    pub field_and_parameter_default_value_data_count: i32,
-    pub field_marshaled_sizes_offset: i32,
-    pub field_marshaled_sizes_count: i32,
-    pub parameters_offset: i32,
-    pub parameters_count: i32,
-    pub fields_offset: i32,
-    pub fields_count: i32,
-    pub generic_parameters_offset: i32,
-    pub generic_parameters_count: i32,
-    pub generic_parameter_constraints_offset: i32,
-    pub generic_parameter_constraints_count: i32,
-    pub generic_containers_offset: i32,
-    pub generic_containers_count: i32,
-    pub nested_types_offset: i32,
-    pub nested_types_count: i32,
-    pub interfaces_offset: i32,
-    pub interfaces_count: i32,
-    pub vtable_methods_offset: i32,
-    pub vtable_methods_count: i32,
-    pub interface_offsets_offset: i32,
-    pub interface_offsets_count: i32,
-    pub type_definitions_offset: i32,
-    pub type_definitions_count: i32,
-    pub rgctx_entries_offset: i32,
-    pub rgctx_entries_count: i32,
-    pub images_offset: i32,
-    pub images_count: i32,
-    pub assemblies_offset: i32,
-    pub assemblies_count: i32,
-    pub metadata_usage_lists_offset: i32,
-    pub metadata_usage_lists_count: i32,
-    pub metadata_usage_pairs_offset: i32,
-    pub metadata_usage_pairs_count: i32,
-    pub field_refs_offset: i32,
-    pub field_refs_count: i32,
-    pub referenced_assemblies_offset: i32,
-    pub referenced_assemblies_count: i32,
-    pub attributes_info_offset: i32,
-    pub attributes_info_count: i32,
-    pub attribute_types_offset: i32,
-    pub attribute_types_count: i32,
-    pub unresolved_virtual_call_parameter_types_offset: i32,
-    pub unresolved_virtual_call_parameter_types_count: i32,
-    pub unresolved_virtual_call_parameter_ranges_offset: i32,
-    pub unresolved_virtual_call_parameter_ranges_count: i32,
-    pub windows_runtime_type_names_offset: i32,
-    pub windows_runtime_type_names_size: i32,
-    pub exported_type_definitions_offset: i32,
-    pub exported_type_definitions_count: i32,
+    pub(crate) string_literal_offset: i32,
+    pub(crate) string_literal_count: i32,
+    pub(crate) string_literal_data_offset: i32,
+    pub(crate) string_literal_data_count: i32,
+    pub(crate) string_offset: i32,
+    pub(crate) string_count: i32,
+    pub(crate) events_offset: i32,
+    pub(crate) events_count: i32,
+    pub(crate) properties_offset: i32,
+    pub(crate) properties_count: i32,
+    pub(crate) methods_offset: i32,
+    pub(crate) methods_count: i32,
+    pub(crate) parameter_default_values_offset: i32,
+    pub(crate) parameter_default_values_count: i32,
+    pub(crate) field_default_values_offset: i32,
+    pub(crate) field_default_values_count: i32,
+    pub(crate) field_and_parameter_default_value_data_offset: i32,
+    pub(crate) field_and_parameter_default_value_data_count: i32,
+    pub(crate) field_marshaled_sizes_offset: i32,
+    pub(crate) field_marshaled_sizes_count: i32,
+    pub(crate) parameters_offset: i32,
+    pub(crate) parameters_count: i32,
+    pub(crate) fields_offset: i32,
+    pub(crate) fields_count: i32,
+    pub(crate) generic_parameters_offset: i32,
+    pub(crate) generic_parameters_count: i32,
+    pub(crate) generic_parameter_constraints_offset: i32,
+    pub(crate) generic_parameter_constraints_count: i32,
+    pub(crate) generic_containers_offset: i32,
+    pub(crate) generic_containers_count: i32,
+    pub(crate) nested_types_offset: i32,
+    pub(crate) nested_types_count: i32,
+    pub(crate) interfaces_offset: i32,
+    pub(crate) interfaces_count: i32,
+    pub(crate) vtable_methods_offset: i32,
+    pub(crate) vtable_methods_count: i32,
+    pub(crate) interface_offsets_offset: i32,
+    pub(crate) interface_offsets_count: i32,
+    pub(crate) type_definitions_offset: i32,
+    pub(crate) type_definitions_count: i32,
+    pub(crate) rgctx_entries_offset: i32,
+    pub(crate) rgctx_entries_count: i32,
+    pub(crate) images_offset: i32,
+    pub(crate) images_count: i32,
+    pub(crate) assemblies_offset: i32,
+    pub(crate) assemblies_count: i32,
+    pub(crate) metadata_usage_lists_offset: i32,
+    pub(crate) metadata_usage_lists_count: i32,
+    pub(crate) metadata_usage_pairs_offset: i32,
+    pub(crate) metadata_usage_pairs_count: i32,
+    pub(crate) field_refs_offset: i32,
+    pub(crate) field_refs_count: i32,
+    pub(crate) referenced_assemblies_offset: i32,
+    pub(crate) referenced_assemblies_count: i32,
+    pub(crate) attributes_info_offset: i32,
+    pub(crate) attributes_info_count: i32,
+    pub(crate) attribute_types_offset: i32,
+    pub(crate) attribute_types_count: i32,
+    pub(crate) attribute_data_offset: i32,
+    pub(crate) attribute_data_size: i32,
+    pub(crate) attribute_data_range_offset: i32,
+    pub(crate) attribute_data_range_count: i32,
+    pub(crate) unresolved_virtual_call_parameter_types_offset: i32,
+    pub(crate) unresolved_virtual_call_parameter_types_count: i32,
+    pub(crate) unresolved_virtual_call_parameter_ranges_offset: i32,
+    pub(crate) unresolved_virtual_call_parameter_ranges_count: i32,
+    pub(crate) windows_runtime_type_names_offset: i32,
+    pub(crate) windows_runtime_type_names_size: i32,
+    pub(crate) windows_runtime_strings_offset: i32,
+    pub(crate) windows_runtime_strings_size: i32,
+    pub(crate) exported_type_definitions_offset: i32,
+    pub(crate) exported_type_definitions_count: i32,
 }
 
 impl GlobalMetadataHeader {
     fn read(r: &mut Reader) -> Result<Self, String> {
+        let sanity = r.read_u32()?;
+        let version = r.read_i32()?;
+        let layout = HeaderLayout::new(version, r.peek_i32()?);
+
+        let (string_literal_offset, string_literal_count) = r.read_section(true)?;
+        let (string_literal_data_offset, string_literal_data_count) = r.read_section(true)?;
+        let (string_offset, string_count) = r.read_section(true)?;
+        let (events_offset, events_count) = r.read_section(true)?;
+        let (properties_offset, properties_count) = r.read_section(true)?;
+        let (methods_offset, methods_count) = r.read_section(true)?;
+        let (parameter_default_values_offset, parameter_default_values_count) =
+            r.read_section(true)?;
+        let (field_default_values_offset, field_default_values_count) = r.read_section(true)?;
+        let (
+            field_and_parameter_default_value_data_offset,
+            field_and_parameter_default_value_data_count,
+        ) = r.read_section(true)?;
+        let (field_marshaled_sizes_offset, field_marshaled_sizes_count) = r.read_section(true)?;
+        let (parameters_offset, parameters_count) = r.read_section(true)?;
+        let (fields_offset, fields_count) = r.read_section(true)?;
+        let (generic_parameters_offset, generic_parameters_count) = r.read_section(true)?;
+        let (generic_parameter_constraints_offset, generic_parameter_constraints_count) =
+            r.read_section(true)?;
+        let (generic_containers_offset, generic_containers_count) = r.read_section(true)?;
+        let (nested_types_offset, nested_types_count) = r.read_section(true)?;
+        let (interfaces_offset, interfaces_count) = r.read_section(true)?;
+        let (vtable_methods_offset, vtable_methods_count) = r.read_section(true)?;
+        let (interface_offsets_offset, interface_offsets_count) = r.read_section(true)?;
+        let (type_definitions_offset, type_definitions_count) = r.read_section(true)?;
+        let (rgctx_entries_offset, rgctx_entries_count) = r.read_section(layout.rgctx)?;
+        let (images_offset, images_count) = r.read_section(true)?;
+        let (assemblies_offset, assemblies_count) = r.read_section(true)?;
+        let (metadata_usage_lists_offset, metadata_usage_lists_count) =
+            r.read_section(layout.usages)?;
+        let (metadata_usage_pairs_offset, metadata_usage_pairs_count) =
+            r.read_section(layout.usages)?;
+        let (field_refs_offset, field_refs_count) = r.read_section(true)?;
+        let (referenced_assemblies_offset, referenced_assemblies_count) = r.read_section(true)?;
+        let (attributes_info_offset, attributes_info_count) =
+            r.read_section(layout.attributes_info)?;
+        let (attribute_types_offset, attribute_types_count) =
+            r.read_section(layout.attributes_info)?;
+        let (attribute_data_offset, attribute_data_size) = r.read_section(layout.attribute_data)?;
+        let (attribute_data_range_offset, attribute_data_range_count) =
+            r.read_section(layout.attribute_data)?;
+        let (
+            unresolved_virtual_call_parameter_types_offset,
+            unresolved_virtual_call_parameter_types_count,
+        ) = r.read_section(true)?;
+        let (
+            unresolved_virtual_call_parameter_ranges_offset,
+            unresolved_virtual_call_parameter_ranges_count,
+        ) = r.read_section(true)?;
+        let (windows_runtime_type_names_offset, windows_runtime_type_names_size) =
+            r.read_section(true)?;
+        let (windows_runtime_strings_offset, windows_runtime_strings_size) =
+            r.read_section(layout.windows_runtime_strings)?;
+        let (exported_type_definitions_offset, exported_type_definitions_count) =
+            r.read_section(true)?;
+
         Ok(Self {
-            sanity: r.read_u32()?,
-            version: r.read_i32()?,
-            string_literal_offset: r.read_i32()?,
-            string_literal_count: r.read_i32()?,
-            string_literal_data_offset: r.read_i32()?,
-            string_literal_data_count: r.read_i32()?,
-            string_offset: r.read_i32()?,
-            string_count: r.read_i32()?,
-            events_offset: r.read_i32()?,
-            events_count: r.read_i32()?,
-            properties_offset: r.read_i32()?,
-            properties_count: r.read_i32()?,
-            methods_offset: r.read_i32()?,
-            methods_count: r.read_i32()?,
-            parameter_default_values_offset: r.read_i32()?,
-            parameter_default_values_count: r.read_i32()?,
-            field_default_values_offset: r.read_i32()?,
-            field_default_values_count: r.read_i32()?,
-            field_and_parameter_default_value_data_offset: r.read_i32()?,
-            field_and_parameter_default_value_data_count: r.read_i32()?,
-            field_marshaled_sizes_offset: r.read_i32()?,
-            field_marshaled_sizes_count: r.read_i32()?,
-            parameters_offset: r.read_i32()?,
-            parameters_count: r.read_i32()?,
-            fields_offset: r.read_i32()?,
-            fields_count: r.read_i32()?,
-            generic_parameters_offset: r.read_i32()?,
-            generic_parameters_count: r.read_i32()?,
-            generic_parameter_constraints_offset: r.read_i32()?,
-            generic_parameter_constraints_count: r.read_i32()?,
-            generic_containers_offset: r.read_i32()?,
-            generic_containers_count: r.read_i32()?,
-            nested_types_offset: r.read_i32()?,
-            nested_types_count: r.read_i32()?,
-            interfaces_offset: r.read_i32()?,
-            interfaces_count: r.read_i32()?,
-            vtable_methods_offset: r.read_i32()?,
-            vtable_methods_count: r.read_i32()?,
-            interface_offsets_offset: r.read_i32()?,
-            interface_offsets_count: r.read_i32()?,
-            type_definitions_offset: r.read_i32()?,
-            type_definitions_count: r.read_i32()?,
-            rgctx_entries_offset: r.read_i32()?,
-            rgctx_entries_count: r.read_i32()?,
-            images_offset: r.read_i32()?,
-            images_count: r.read_i32()?,
-            assemblies_offset: r.read_i32()?,
-            assemblies_count: r.read_i32()?,
-            metadata_usage_lists_offset: r.read_i32()?,
-            metadata_usage_lists_count: r.read_i32()?,
-            metadata_usage_pairs_offset: r.read_i32()?,
-            metadata_usage_pairs_count: r.read_i32()?,
-            field_refs_offset: r.read_i32()?,
-            field_refs_count: r.read_i32()?,
-            referenced_assemblies_offset: r.read_i32()?,
-            referenced_assemblies_count: r.read_i32()?,
-            attributes_info_offset: r.read_i32()?,
-            attributes_info_count: r.read_i32()?,
-            attribute_types_offset: r.read_i32()?,
-            attribute_types_count: r.read_i32()?,
-            unresolved_virtual_call_parameter_types_offset: r.read_i32()?,
-            unresolved_virtual_call_parameter_types_count: r.read_i32()?,
-            unresolved_virtual_call_parameter_ranges_offset: r.read_i32()?,
-            unresolved_virtual_call_parameter_ranges_count: r.read_i32()?,
-            windows_runtime_type_names_offset: r.read_i32()?,
-            windows_runtime_type_names_size: r.read_i32()?,
-            exported_type_definitions_offset: r.read_i32()?,
-            exported_type_definitions_count: r.read_i32()?,
+            sanity,
+            version,
+            string_literal_offset,
+            string_literal_count,
+            string_literal_data_offset,
+            string_literal_data_count,
+            string_offset,
+            string_count,
+            events_offset,
+            events_count,
+            properties_offset,
+            properties_count,
+            methods_offset,
+            methods_count,
+            parameter_default_values_offset,
+            parameter_default_values_count,
+            field_default_values_offset,
+            field_default_values_count,
+            field_and_parameter_default_value_data_offset,
+            field_and_parameter_default_value_data_count,
+            field_marshaled_sizes_offset,
+            field_marshaled_sizes_count,
+            parameters_offset,
+            parameters_count,
+            fields_offset,
+            fields_count,
+            generic_parameters_offset,
+            generic_parameters_count,
+            generic_parameter_constraints_offset,
+            generic_parameter_constraints_count,
+            generic_containers_offset,
+            generic_containers_count,
+            nested_types_offset,
+            nested_types_count,
+            interfaces_offset,
+            interfaces_count,
+            vtable_methods_offset,
+            vtable_methods_count,
+            interface_offsets_offset,
+            interface_offsets_count,
+            type_definitions_offset,
+            type_definitions_count,
+            rgctx_entries_offset,
+            rgctx_entries_count,
+            images_offset,
+            images_count,
+            assemblies_offset,
+            assemblies_count,
+            metadata_usage_lists_offset,
+            metadata_usage_lists_count,
+            metadata_usage_pairs_offset,
+            metadata_usage_pairs_count,
+            field_refs_offset,
+            field_refs_count,
+            referenced_assemblies_offset,
+            referenced_assemblies_count,
+            attributes_info_offset,
+            attributes_info_count,
+            attribute_types_offset,
+            attribute_types_count,
+            attribute_data_offset,
+            attribute_data_size,
+            attribute_data_range_offset,
+            attribute_data_range_count,
+            unresolved_virtual_call_parameter_types_offset,
+            unresolved_virtual_call_parameter_types_count,
+            unresolved_virtual_call_parameter_ranges_offset,
+            unresolved_virtual_call_parameter_ranges_count,
+            windows_runtime_type_names_offset,
+            windows_runtime_type_names_size,
+            windows_runtime_strings_offset,
+            windows_runtime_strings_size,
+            exported_type_definitions_offset,
+            exported_type_definitions_count,
         })
     }
+
+    /// Reads the header at the start of `data`, laid out for its version.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        Self::read(&mut Reader::new(data, 0))
+    }
+
+    /// The (offset, size) pairs present in this header's layout, in file order.
+    pub fn sections(&self) -> Vec<(i32, i32)> {
+        let layout = HeaderLayout::new(self.version, self.string_literal_offset);
+        let mut sections = vec![
+            (self.string_literal_offset, self.string_literal_count),
+            (
+                self.string_literal_data_offset,
+                self.string_literal_data_count,
+            ),
+            (self.string_offset, self.string_count),
+            (self.events_offset, self.events_count),
+            (self.properties_offset, self.properties_count),
+            (self.methods_offset, self.methods_count),
+            (
+                self.parameter_default_values_offset,
+                self.parameter_default_values_count,
+            ),
+            (
+                self.field_default_values_offset,
+                self.field_default_values_count,
+            ),
+            (
+                self.field_and_parameter_default_value_data_offset,
+                self.field_and_parameter_default_value_data_count,
+            ),
+            (
+                self.field_marshaled_sizes_offset,
+                self.field_marshaled_sizes_count,
+            ),
+            (self.parameters_offset, self.parameters_count),
+            (self.fields_offset, self.fields_count),
+            (
+                self.generic_parameters_offset,
+                self.generic_parameters_count,
+            ),
+            (
+                self.generic_parameter_constraints_offset,
+                self.generic_parameter_constraints_count,
+            ),
+            (
+                self.generic_containers_offset,
+                self.generic_containers_count,
+            ),
+            (self.nested_types_offset, self.nested_types_count),
+            (self.interfaces_offset, self.interfaces_count),
+            (self.vtable_methods_offset, self.vtable_methods_count),
+            (self.interface_offsets_offset, self.interface_offsets_count),
+            (self.type_definitions_offset, self.type_definitions_count),
+        ];
+        if layout.rgctx {
+            sections.push((self.rgctx_entries_offset, self.rgctx_entries_count));
+        }
+        sections.push((self.images_offset, self.images_count));
+        sections.push((self.assemblies_offset, self.assemblies_count));
+        if layout.usages {
+            sections.push((
+                self.metadata_usage_lists_offset,
+                self.metadata_usage_lists_count,
+            ));
+            sections.push((
+                self.metadata_usage_pairs_offset,
+                self.metadata_usage_pairs_count,
+            ));
+        }
+        sections.push((self.field_refs_offset, self.field_refs_count));
+        sections.push((
+            self.referenced_assemblies_offset,
+            self.referenced_assemblies_count,
+        ));
+        if layout.attributes_info {
+            sections.push((self.attributes_info_offset, self.attributes_info_count));
+            sections.push((self.attribute_types_offset, self.attribute_types_count));
+        }
+        if layout.attribute_data {
+            sections.push((self.attribute_data_offset, self.attribute_data_size));
+            sections.push((
+                self.attribute_data_range_offset,
+                self.attribute_data_range_count,
+            ));
+        }
+        sections.push((
+            self.unresolved_virtual_call_parameter_types_offset,
+            self.unresolved_virtual_call_parameter_types_count,
+        ));
+        sections.push((
+            self.unresolved_virtual_call_parameter_ranges_offset,
+            self.unresolved_virtual_call_parameter_ranges_count,
+        ));
+        sections.push((
+            self.windows_runtime_type_names_offset,
+            self.windows_runtime_type_names_size,
+        ));
+        if layout.windows_runtime_strings {
+            sections.push((
+                self.windows_runtime_strings_offset,
+                self.windows_runtime_strings_size,
+            ));
+        }
+        sections.push((
+            self.exported_type_definitions_offset,
+            self.exported_type_definitions_count,
+        ));
+        sections
+    }
+}
+
+// Which optional header sections a version carries. Sections outside the
+// layout are not in the file at all and read back as (0, 0).
+struct HeaderLayout {
+    rgctx: bool,
+    usages: bool,
+    attributes_info: bool,
+    attribute_data: bool,
+    windows_runtime_strings: bool,
+}
+
+impl HeaderLayout {
+    fn new(version: i32, string_literal_offset: i32) -> Self {
+        Self {
+            // 24.2 dropped the rgctx section without bumping the version, so
+            // the only tell is the string literals starting right after the
+            // shorter header.
+            rgctx: version < 24 || (version == 24 && string_literal_offset != V24_2_HEADER_SIZE),
+            usages: version <= 24,
+            attributes_info: version <= 27,
+            attribute_data: version >= 29,
+            windows_runtime_strings: version >= 27,
+        }
+    }
 }
 
+/// Where a string literal's bytes are in the literal data.
 pub struct StringLiteralInfo {
+    /// The length in bytes.
     pub length: u32,
+    /// The offset into the literal data.
     pub offset: u32,
 }
 
-impl StringLiteralInfo {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for StringLiteralInfo {
+    fn size(_: MetadataVersion) -> usize {
+        8
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
         Ok(Self {
             length: r.read_u32()?,
             offset: r.read_u32()?,
@@ -230,21 +632,41 @@ impl StringLiteralInfo {
     }
 }
 
+/// An assembly's module and the types it holds.
 pub struct ImageDefinition {
+    /// The file name, an index into the string data.
     pub name_index: i32,
+    /// Index into `assemblies`.
     pub assembly_index: i32,
+    /// The first of its types in `type_definitions`.
     pub type_start: i32,
+    /// How many types it holds.
     pub type_count: u32,
+    /// The first of its exported types.
     pub exported_type_start: i32,
+    /// How many types it exports.
     pub exported_type_count: u32,
+    /// The entry point method, or -1.
     pub entry_point_index: i32,
+    /// The module token, always 1.
     pub token: u32,
+    /// Since 24.1; 0 when absent.
     pub custom_attribute_start: i32,
+    /// How many attribute ranges follow `custom_attribute_start`.
     pub custom_attribute_count: u32,
 }
 
-impl ImageDefinition {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for ImageDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        if version >= MetadataVersion::V24_1 {
+            40
+        } else {
+            32
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        let v24_1 = version >= MetadataVersion::V24_1;
         Ok(Self {
             name_index: r.read_i32()?,
             assembly_index: r.read_i32()?,
@@ -254,32 +676,53 @@ impl ImageDefinition {
             exported_type_count: r.read_u32()?,
             entry_point_index: r.read_i32()?,
             token: r.read_u32()?,
-            custom_attribute_start: r.read_i32()?,
-            custom_attribute_count: r.read_u32()?,
+            custom_attribute_start: if v24_1 { r.read_i32()? } else { 0 },
+            custom_attribute_count: r.read_u32_if(v24_1)?,
         })
     }
 }
 
+/// An assembly's name, version and public key.
 pub struct AssemblyNameDefinition {
+    /// The name, an index into the string data.
     pub name_index: i32,
+    /// The culture, an index into the string data.
     pub culture_index: i32,
+    /// Up to 24.3; -1 when absent.
     pub hash_value_index: i32,
+    /// The public key, an index into the string data.
     pub public_key_index: i32,
+    /// The hash algorithm id.
     pub hash_alg: u32,
+    /// The hash length.
     pub hash_len: i32,
+    /// AssemblyNameFlags.
     pub flags: u32,
+    /// The major version number.
     pub major: i32,
+    /// The minor version number.
     pub minor: i32,
+    /// The build number.
     pub build: i32,
+    /// The revision number.
     pub revision: i32,
+    /// The public key token, or zeros.
     pub public_key_token: [u8; 8],
 }
 
-impl AssemblyNameDefinition {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for AssemblyNameDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        if version >= MetadataVersion::V24_4 {
+            48
+        } else {
+            52
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
         let name_index = r.read_i32()?;
         let culture_index = r.read_i32()?;
-        let hash_value_index = r.read_i32()?;
+        let hash_value_index = r.read_i32_if(version < MetadataVersion::V24_4)?;
         let public_key_index = r.read_i32()?;
         let hash_alg = r.read_u32()?;
         let hash_len = r.read_i32()?;
@@ -308,70 +751,132 @@ impl AssemblyNameDefinition {
     }
 }
 
+/// An assembly: its image, the assemblies it references and its name.
 pub struct AssemblyDefinition {
+    /// Index into `images`.
     pub image_index: i32,
+    /// Since 24.1; 0 when absent.
     pub token: u32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// The first of its references in the referenced assemblies.
     pub referenced_assembly_start: i32,
+    /// How many assemblies it references.
     pub referenced_assembly_count: i32,
+    /// The assembly's name.
     pub aname: AssemblyNameDefinition,
 }
 
-impl AssemblyDefinition {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for AssemblyDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        16 + AssemblyNameDefinition::size(version)
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        let v24_1 = version >= MetadataVersion::V24_1;
         Ok(Self {
             image_index: r.read_i32()?,
-            token: r.read_u32()?,
+            token: r.read_u32_if(v24_1)?,
+            custom_attribute_index: r.read_i32_if(!v24_1)?,
             referenced_assembly_start: r.read_i32()?,
             referenced_assembly_count: r.read_i32()?,
-            aname: AssemblyNameDefinition::read(r)?,
+            aname: AssemblyNameDefinition::read(r, version)?,
         })
     }
 }
 
+/// A type, with ranges into the member tables it owns.
 pub struct TypeDefinition {
+    /// The name, an index into the string data.
     pub name_index: i32,
+    /// The namespace, an index into the string data.
     pub namespace_index: i32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// The type's own type index.
     pub byval_type_index: i32,
+    /// Up to 24.5; -1 when absent.
     pub byref_type_index: i32,
+    /// The enclosing type's type index, or -1.
     pub declaring_type_index: i32,
+    /// The base type's type index, or -1.
     pub parent_index: i32,
+    /// The underlying type of an enum, as a type index.
     pub element_type_index: i32,
+    /// Up to 24.1; -1 and 0 when absent.
     pub rgctx_start_index: i32,
+    /// How many rgctx entries it has.
     pub rgctx_count: i32,
+    /// Index into `generic_containers`, or -1 when not generic.
     pub generic_container_index: i32,
+    /// TypeAttributes.
     pub flags: u32,
+    /// The first of its fields in `fields`.
     pub field_start: i32,
+    /// The first of its methods in `methods`.
     pub method_start: i32,
+    /// The first of its events in `events`.
     pub event_start: i32,
+    /// The first of its properties in `properties`.
     pub property_start: i32,
+    /// The first of its nested types in `nested_types`.
     pub nested_types_start: i32,
+    /// The first of its interfaces in `interfaces`.
     pub interfaces_start: i32,
+    /// The first of its slots in `vtable_methods`.
     pub vtable_start: i32,
+    /// The first of its entries in `interface_offsets`.
     pub interface_offsets_start: i32,
+    /// How many methods it has.
     pub method_count: u16,
+    /// How many properties it has.
     pub property_count: u16,
+    /// How many fields it has.
     pub field_count: u16,
+    /// How many events it has.
     pub event_count: u16,
+    /// How many nested types it has.
     pub nested_type_count: u16,
+    /// How many vtable slots it has.
     pub vtable_count: u16,
+    /// How many interfaces it implements.
     pub interfaces_count: u16,
+    /// How many interface offsets it has.
     pub interface_offsets_count: u16,
+    /// Packed flags, read by `is_value_type` and `is_enum`.
     pub bitfield: u32,
+    /// The TypeDef token.
     pub token: u32,
 }
 
-impl TypeDefinition {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for TypeDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        let mut size = 88;
+        if version < MetadataVersion::V24_1 {
+            size += 4;
+        }
+        if version < MetadataVersion::V24_2 {
+            size += 8;
+        }
+        if version.major < 27 {
+            size += 4;
+        }
+        size
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        let rgctx = version < MetadataVersion::V24_2;
         Ok(Self {
             name_index: r.read_i32()?,
             namespace_index: r.read_i32()?,
+            custom_attribute_index: r.read_i32_if(version < MetadataVersion::V24_1)?,
             byval_type_index: r.read_i32()?,
-            byref_type_index: r.read_i32()?,
+            byref_type_index: r.read_i32_if(version.major < 27)?,
             declaring_type_index: r.read_i32()?,
             parent_index: r.read_i32()?,
             element_type_index: r.read_i32()?,
-            rgctx_start_index: r.read_i32()?,
-            rgctx_count: r.read_i32()?,
+            rgctx_start_index: r.read_i32_if(rgctx)?,
+            rgctx_count: if rgctx { r.read_i32()? } else { 0 },
             generic_container_index: r.read_i32()?,
             flags: r.read_u32()?,
             field_start: r.read_i32()?,
@@ -396,13 +901,754 @@ impl TypeDefinition {
     }
 }
 
+impl TypeDefinition {
+    /// Whether the type is a struct or an enum.
+    pub fn is_value_type(&self) -> bool {
+        self.bitfield & 1 != 0
+    }
+
+    /// Enum fields hold their constants as the underlying integer type, which
+    /// the element type index names.
+    pub fn is_enum(&self) -> bool {
+        self.bitfield & 2 != 0
+    }
+}
+
+/// A method, with its parameters as a range into `parameters`.
+pub struct MethodDefinition {
+    /// The name, an index into the string data.
+    pub name_index: i32,
+    /// Index into `type_definitions`.
+    pub declaring_type: i32,
+    /// The return type's type index.
+    pub return_type: i32,
+    /// Since 31; 0 when absent.
+    pub return_parameter_token: u32,
+    /// The first of its parameters in `parameters`.
+    pub parameter_start: i32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// Index into `generic_containers`, or -1 when not generic.
+    pub generic_container_index: i32,
+    /// Up to 24.1, after which the binary maps tokens to code itself; -1 and
+    /// 0 when absent.
+    pub method_index: i32,
+    /// The invoker's index in the binary, likewise.
+    pub invoker_index: i32,
+    /// The reverse P/Invoke wrapper's index, likewise.
+    pub delegate_wrapper_index: i32,
+    /// The first of its rgctx entries, likewise.
+    pub rgctx_start_index: i32,
+    /// How many rgctx entries it has, likewise.
+    pub rgctx_count: i32,
+    /// The MethodDef token.
+    pub token: u32,
+    /// MethodAttributes.
+    pub flags: u16,
+    /// MethodImplAttributes.
+    pub iflags: u16,
+    /// The vtable slot, or 0xFFFF when not virtual.
+    pub slot: u16,
+    /// How many parameters it takes.
+    pub parameter_count: u16,
+}
+
+impl Record for MethodDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        let mut size = 32;
+        if version < MetadataVersion::V24_1 {
+            size += 4;
+        }
+        if version < MetadataVersion::V24_2 {
+            size += 20;
+        }
+        if version >= MetadataVersion::V31_0 {
+            size += 4;
+        }
+        size
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        let indices = version < MetadataVersion::V24_2;
+        Ok(Self {
+            name_index: r.read_i32()?,
+            declaring_type: r.read_i32()?,
+            return_type: r.read_i32()?,
+            return_parameter_token: r.read_u32_if(version >= MetadataVersion::V31_0)?,
+            parameter_start: r.read_i32()?,
+            custom_attribute_index: r.read_i32_if(version < MetadataVersion::V24_1)?,
+            generic_container_index: r.read_i32()?,
+            method_index: r.read_i32_if(indices)?,
+            invoker_index: r.read_i32_if(indices)?,
+            delegate_wrapper_index: r.read_i32_if(indices)?,
+            rgctx_start_index: r.read_i32_if(indices)?,
+            rgctx_count: if indices { r.read_i32()? } else { 0 },
+            token: r.read_u32()?,
+            flags: r.read_u16()?,
+            iflags: r.read_u16()?,
+            slot: r.read_u16()?,
+            parameter_count: r.read_u16()?,
+        })
+    }
+}
+
+/// A field of a type definition.
+pub struct FieldDefinition {
+    /// The name, an index into the string data.
+    pub name_index: i32,
+    /// The field's type index.
+    pub type_index: i32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// The FieldDef token.
+    pub token: u32,
+}
+
+impl Record for FieldDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        if version < MetadataVersion::V24_1 {
+            16
+        } else {
+            12
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            name_index: r.read_i32()?,
+            type_index: r.read_i32()?,
+            custom_attribute_index: r.read_i32_if(version < MetadataVersion::V24_1)?,
+            token: r.read_u32()?,
+        })
+    }
+}
+
+/// A field's constant value.
+pub struct FieldDefaultValue {
+    /// Index into `fields`.
+    pub field_index: i32,
+    /// The constant's type index.
+    pub type_index: i32,
+    /// Offset into the default value data, or -1 for null.
+    pub data_index: i32,
+}
+
+impl Record for FieldDefaultValue {
+    fn size(_: MetadataVersion) -> usize {
+        12
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            field_index: r.read_i32()?,
+            type_index: r.read_i32()?,
+            data_index: r.read_i32()?,
+        })
+    }
+}
+
+/// The marshaled size of a fixed-size field.
+pub struct FieldMarshaledSize {
+    /// Index into `fields`.
+    pub field_index: i32,
+    /// The field's type index.
+    pub type_index: i32,
+    /// The size in bytes.
+    pub size: i32,
+}
+
+impl Record for FieldMarshaledSize {
+    fn size(_: MetadataVersion) -> usize {
+        12
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            field_index: r.read_i32()?,
+            type_index: r.read_i32()?,
+            size: r.read_i32()?,
+        })
+    }
+}
+
+/// A method parameter.
+pub struct ParameterDefinition {
+    /// The name, an index into the string data.
+    pub name_index: i32,
+    /// The ParamDef token.
+    pub token: u32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// The parameter's type index.
+    pub type_index: i32,
+}
+
+impl Record for ParameterDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        if version < MetadataVersion::V24_1 {
+            16
+        } else {
+            12
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            name_index: r.read_i32()?,
+            token: r.read_u32()?,
+            custom_attribute_index: r.read_i32_if(version < MetadataVersion::V24_1)?,
+            type_index: r.read_i32()?,
+        })
+    }
+}
+
+/// A parameter's default value.
+pub struct ParameterDefaultValue {
+    /// Index into `parameters`.
+    pub parameter_index: i32,
+    /// The constant's type index.
+    pub type_index: i32,
+    /// Offset into the default value data, or -1 for null.
+    pub data_index: i32,
+}
+
+impl Record for ParameterDefaultValue {
+    fn size(_: MetadataVersion) -> usize {
+        12
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            parameter_index: r.read_i32()?,
+            type_index: r.read_i32()?,
+            data_index: r.read_i32()?,
+        })
+    }
+}
+
+/// Accessor methods are indices relative to the declaring type's
+/// `method_start`, or -1 when the accessor does not exist.
+pub struct PropertyDefinition {
+    /// The name, an index into the string data.
+    pub name_index: i32,
+    /// The getter.
+    pub get: i32,
+    /// The setter.
+    pub set: i32,
+    /// PropertyAttributes.
+    pub attrs: u32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// The Property token.
+    pub token: u32,
+}
+
+impl Record for PropertyDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        if version < MetadataVersion::V24_1 {
+            24
+        } else {
+            20
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            name_index: r.read_i32()?,
+            get: r.read_i32()?,
+            set: r.read_i32()?,
+            attrs: r.read_u32()?,
+            custom_attribute_index: r.read_i32_if(version < MetadataVersion::V24_1)?,
+            token: r.read_u32()?,
+        })
+    }
+}
+
+/// An event. Like property accessors, its accessors are relative to the
+/// declaring type's `method_start`, or -1 when they do not exist.
+pub struct EventDefinition {
+    /// The name, an index into the string data.
+    pub name_index: i32,
+    /// The handler's type index.
+    pub type_index: i32,
+    /// The add accessor.
+    pub add: i32,
+    /// The remove accessor.
+    pub remove: i32,
+    /// The raise accessor.
+    pub raise: i32,
+    /// Only in 24.0; -1 when absent.
+    pub custom_attribute_index: i32,
+    /// The Event token.
+    pub token: u32,
+}
+
+impl Record for EventDefinition {
+    fn size(version: MetadataVersion) -> usize {
+        if version < MetadataVersion::V24_1 {
+            28
+        } else {
+            24
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            name_index: r.read_i32()?,
+            type_index: r.read_i32()?,
+            add: r.read_i32()?,
+            remove: r.read_i32()?,
+            raise: r.read_i32()?,
+            custom_attribute_index: r.read_i32_if(version < MetadataVersion::V24_1)?,
+            token: r.read_u32()?,
+        })
+    }
+}
+
+/// The generic parameters of a type or method.
+pub struct GenericContainer {
+    /// A type definition or method index, depending on `is_method`.
+    pub owner_index: i32,
+    /// How many generic parameters it declares.
+    pub type_argc: i32,
+    /// 1 when the owner is a method, 0 for a type.
+    pub is_method: i32,
+    /// The first of its parameters in `generic_parameters`.
+    pub generic_parameter_start: i32,
+}
+
+impl Record for GenericContainer {
+    fn size(_: MetadataVersion) -> usize {
+        16
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            owner_index: r.read_i32()?,
+            type_argc: r.read_i32()?,
+            is_method: r.read_i32()?,
+            generic_parameter_start: r.read_i32()?,
+        })
+    }
+}
+
+/// A generic parameter.
+pub struct GenericParameter {
+    /// Index of its container in `generic_containers`.
+    pub owner_index: i32,
+    /// The name, an index into the string data.
+    pub name_index: i32,
+    /// The first of its constraints in `generic_parameter_constraints`.
+    pub constraints_start: i16,
+    /// How many constraints it has.
+    pub constraints_count: i16,
+    /// Its position in the container.
+    pub num: u16,
+    /// GenericParameterAttributes.
+    pub flags: u16,
+}
+
+impl Record for GenericParameter {
+    fn size(_: MetadataVersion) -> usize {
+        16
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            owner_index: r.read_i32()?,
+            name_index: r.read_i32()?,
+            constraints_start: r.read_u16()? as i16,
+            constraints_count: r.read_u16()? as i16,
+            num: r.read_u16()?,
+            flags: r.read_u16()?,
+        })
+    }
+}
+
+// Index tables such as generic parameter constraints, which hold type
+// indices.
+impl Record for i32 {
+    fn size(_: MetadataVersion) -> usize {
+        4
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        r.read_i32()
+    }
+}
+
+impl Record for u32 {
+    fn size(_: MetadataVersion) -> usize {
+        4
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        r.read_u32()
+    }
+}
+
+/// Where an interface's methods start in a type's vtable.
+pub struct InterfaceOffsetPair {
+    /// The interface's type index.
+    pub interface_type_index: i32,
+    /// The vtable slot of its first method.
+    pub offset: i32,
+}
+
+impl Record for InterfaceOffsetPair {
+    fn size(_: MetadataVersion) -> usize {
+        8
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            interface_type_index: r.read_i32()?,
+            offset: r.read_i32()?,
+        })
+    }
+}
+
+/// The kind packed into the top three bits of vtable entries and metadata
+/// usages.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UsageKind {
+    /// An Il2CppClass, by type index.
+    TypeInfo = 1,
+    /// An Il2CppType, by type index.
+    Il2CppType = 2,
+    /// A method definition.
+    MethodDef = 3,
+    /// A field, by index into `field_refs`.
+    FieldInfo = 4,
+    /// A string literal.
+    StringLiteral = 5,
+    /// A generic method instance, by method spec index.
+    MethodRef = 6,
+}
+
+impl UsageKind {
+    fn from_u32(value: u32) -> Option<Self> {
+        Some(match value {
+            1 => UsageKind::TypeInfo,
+            2 => UsageKind::Il2CppType,
+            3 => UsageKind::MethodDef,
+            4 => UsageKind::FieldInfo,
+            5 => UsageKind::StringLiteral,
+            6 => UsageKind::MethodRef,
+            _ => return None,
+        })
+    }
+}
+
+/// A decoded vtable entry.
+pub enum VtableSlot {
+    /// An index into `methods`.
+    Method(usize),
+    /// A generic method instance, indexing the binary's method specs.
+    MethodSpec(u32),
+    /// No method.
+    Empty,
+}
+
+/// Pre-29 attribute lists: the attribute types applied to one token.
+pub struct CustomAttributeTypeRange {
+    /// Since 24.1; 0 when absent, as 24.0 records index ranges directly.
+    pub token: u32,
+    /// The first of its attributes in `attribute_types`.
+    pub start: i32,
+    /// How many attributes it holds.
+    pub count: i32,
+}
+
+impl Record for CustomAttributeTypeRange {
+    fn size(version: MetadataVersion) -> usize {
+        if version >= MetadataVersion::V24_1 {
+            12
+        } else {
+            8
+        }
+    }
+
+    fn read(r: &mut Reader, version: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            token: r.read_u32_if(version >= MetadataVersion::V24_1)?,
+            start: r.read_i32()?,
+            count: r.read_i32()?,
+        })
+    }
+}
+
+/// From 29, attributes are a blob per token holding constructors and
+/// arguments; each range runs to the start of the next.
+pub struct CustomAttributeDataRange {
+    /// The token of the attributed item.
+    pub token: u32,
+    /// Where its blob starts in the attribute data.
+    pub start_offset: u32,
+}
+
+impl Record for CustomAttributeDataRange {
+    fn size(_: MetadataVersion) -> usize {
+        8
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            token: r.read_u32()?,
+            start_offset: r.read_u32()?,
+        })
+    }
+}
+
+/// A decoded 29+ attribute.
+pub struct CustomAttribute {
+    /// An index into `methods`.
+    pub constructor: usize,
+    /// The constructor arguments.
+    pub arguments: Vec<AttributeValue>,
+    /// The fields and properties it sets.
+    pub named_arguments: Vec<NamedArgument>,
+}
+
+/// A field or property an attribute sets.
+pub struct NamedArgument {
+    /// The field or property name.
+    pub name: String,
+    /// The value it is set to.
+    pub value: AttributeValue,
+}
+
+/// An attribute argument.
+pub enum AttributeValue {
+    /// A constant.
+    Constant(ConstantValue),
+    /// An enum constant; the type index names the enum.
+    Enum {
+        /// The enum's type index.
+        type_index: i32,
+        /// The constant as its underlying type.
+        value: ConstantValue,
+    },
+    /// typeof(T), or -1 for a null Type.
+    Type(i32),
+    /// An array, element by element.
+    Array(Vec<AttributeValue>),
+}
+
+// Blob-only type tags on top of ElementType.
+const ENCODED_TYPE_ENUM: u8 = 0x55;
+const ENCODED_TYPE_INDEX: u8 = 0xFF;
+
+/// Il2CppTypeEnum. Type indices in the metadata point into the binary's type
+/// table, which is where the element type of a field or constant comes from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ElementType {
+    /// The end of a list.
+    End = 0x00,
+    /// `void`.
+    Void = 0x01,
+    /// `bool`.
+    Boolean = 0x02,
+    /// `char`.
+    Char = 0x03,
+    /// `sbyte`.
+    I1 = 0x04,
+    /// `byte`.
+    U1 = 0x05,
+    /// `short`.
+    I2 = 0x06,
+    /// `ushort`.
+    U2 = 0x07,
+    /// `int`.
+    I4 = 0x08,
+    /// `uint`.
+    U4 = 0x09,
+    /// `long`.
+    I8 = 0x0A,
+    /// `ulong`.
+    U8 = 0x0B,
+    /// `float`.
+    R4 = 0x0C,
+    /// `double`.
+    R8 = 0x0D,
+    /// `string`.
+    String = 0x0E,
+    /// An unmanaged pointer.
+    Ptr = 0x0F,
+    /// A by-reference type.
+    ByRef = 0x10,
+    /// A value type definition.
+    ValueType = 0x11,
+    /// A reference type definition.
+    Class = 0x12,
+    /// A type's generic parameter.
+    Var = 0x13,
+    /// A multi-dimensional array.
+    Array = 0x14,
+    /// A generic instance.
+    GenericInst = 0x15,
+    /// `TypedReference`.
+    TypedByRef = 0x16,
+    /// `nint`.
+    I = 0x18,
+    /// `nuint`.
+    U = 0x19,
+    /// A function pointer.
+    FnPtr = 0x1B,
+    /// `object`.
+    Object = 0x1C,
+    /// A single-dimensional, zero-based array.
+    SzArray = 0x1D,
+    /// A method's generic parameter.
+    MVar = 0x1E,
+}
+
+impl ElementType {
+    /// The element type with this tag, if any.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        use ElementType::*;
+        Some(match value {
+            0x00 => End,
+            0x01 => Void,
+            0x02 => Boolean,
+            0x03 => Char,
+            0x04 => I1,
+            0x05 => U1,
+            0x06 => I2,
+            0x07 => U2,
+            0x08 => I4,
+            0x09 => U4,
+            0x0A => I8,
+            0x0B => U8,
+            0x0C => R4,
+            0x0D => R8,
+            0x0E => String,
+            0x0F => Ptr,
+            0x10 => ByRef,
+            0x11 => ValueType,
+            0x12 => Class,
+            0x13 => Var,
+            0x14 => Array,
+            0x15 => GenericInst,
+            0x16 => TypedByRef,
+            0x18 => I,
+            0x19 => U,
+            0x1B => FnPtr,
+            0x1C => Object,
+            0x1D => SzArray,
+            0x1E => MVar,
+            _ => return None,
+        })
+    }
+}
+
+/// A decoded field or parameter default. Enum constants are stored as their
+/// underlying integer type and decode to that.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ConstantValue {
+    /// A `bool`.
+    Boolean(bool),
+    /// A `char`.
+    Char(char),
+    /// An `sbyte`.
+    I1(i8),
+    /// A `byte`.
+    U1(u8),
+    /// A `short`.
+    I2(i16),
+    /// A `ushort`.
+    U2(u16),
+    /// An `int`.
+    I4(i32),
+    /// A `uint`.
+    U4(u32),
+    /// A `long`.
+    I8(i64),
+    /// A `ulong`.
+    U8(u64),
+    /// A `float`.
+    R4(f32),
+    /// A `double`.
+    R8(f64),
+    /// A `string`.
+    String(String),
+    /// `null`, the only constant of other reference types.
+    Null,
+}
+
+// Renders the value as a C# literal.
+impl fmt::Display for ConstantValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstantValue::Boolean(v) => write!(f, "{}", v),
+            ConstantValue::Char(c) => write!(f, "'{}'", escape_char(*c, '\'')),
+            ConstantValue::I1(v) => write!(f, "{}", v),
+            ConstantValue::U1(v) => write!(f, "{}", v),
+            ConstantValue::I2(v) => write!(f, "{}", v),
+            ConstantValue::U2(v) => write!(f, "{}", v),
+            ConstantValue::I4(v) => write!(f, "{}", v),
+            ConstantValue::U4(v) => write!(f, "{}", v),
+            ConstantValue::I8(v) => write!(f, "{}", v),
+            ConstantValue::U8(v) => write!(f, "{}", v),
+            ConstantValue::R4(v) => write_float(f, *v as f64, &v.to_string(), "float", "f"),
+            ConstantValue::R8(v) => write_float(f, *v, &v.to_string(), "double", "d"),
+            ConstantValue::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    write!(f, "{}", escape_char(c, '"'))?;
+                }
+                write!(f, "\"")
+            }
+            ConstantValue::Null => write!(f, "null"),
+        }
+    }
+}
+
+fn write_float(
+    f: &mut fmt::Formatter,
+    v: f64,
+    literal: &str,
+    type_name: &str,
+    suffix: &str,
+) -> fmt::Result {
+    if v.is_nan() {
+        write!(f, "{}.NaN", type_name)
+    } else if v.is_infinite() {
+        let sign = if v > 0.0 { "Positive" } else { "Negative" };
+        write!(f, "{}.{}Infinity", type_name, sign)
+    } else {
+        write!(f, "{}{}", literal, suffix)
+    }
+}
+
+fn escape_char(c: char, quote: char) -> String {
+    match c {
+        '\\' => "\\\\".to_string(),
+        '\0' => "\\0".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        c if c == quote => format!("\\{}", c),
+        c if c.is_control() => format!("\\u{:04X}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+/// Usage lists and pairs exist up to 24.5; 27 moved metadata usages into the
+/// binary.
 pub struct MetadataUsageList {
+    /// The first of its pairs in `metadata_usage_pairs`.
     pub start: u32,
+    /// How many pairs it has.
     pub count: u32,
 }
 
-impl MetadataUsageList {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for MetadataUsageList {
+    fn size(_: MetadataVersion) -> usize {
+        8
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
         Ok(Self {
             start: r.read_u32()?,
             count: r.read_u32()?,
@@ -410,13 +1656,20 @@ impl MetadataUsageList {
     }
 }
 
+/// A metadata usage and the slot it fills.
 pub struct MetadataUsagePair {
+    /// The slot, an index into the binary's usage list.
     pub destination_index: u32,
+    /// The usage, as `GlobalMetadata::decode_index` reads it.
     pub encoded_source_index: u32,
 }
 
-impl MetadataUsagePair {
-    fn read(r: &mut Reader) -> Result<Self, String> {
+impl Record for MetadataUsagePair {
+    fn size(_: MetadataVersion) -> usize {
+        8
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
         Ok(Self {
             destination_index: r.read_u32()?,
             encoded_source_index: r.read_u32()?,
@@ -424,109 +1677,901 @@ impl MetadataUsagePair {
     }
 }
 
+/// A field of a type referenced by type index, the target of FieldInfo
+/// metadata usages.
+pub struct FieldRef {
+    /// The type's type index.
+    pub type_index: i32,
+    /// The field, relative to the type's `field_start`.
+    pub field_index: i32,
+}
+
+impl Record for FieldRef {
+    fn size(_: MetadataVersion) -> usize {
+        8
+    }
+
+    fn read(r: &mut Reader, _: MetadataVersion) -> Result<Self, String> {
+        Ok(Self {
+            type_index: r.read_i32()?,
+            field_index: r.read_i32()?,
+        })
+    }
+}
+
+/// How string literals are read: as stored, decrypted with a given scheme,
+/// or with whichever scheme the detector picks.
+#[derive(Clone, Copy, Default)]
+pub enum LiteralDecryption {
+    /// Leave the literals as stored.
+    #[default]
+    Keep,
+    /// Decrypt them with this scheme.
+    Scheme(LiteralScheme),
+    /// Decrypt them with the scheme detection finds, if any.
+    Detect,
+}
+
+/// What `GlobalMetadata::parse_with` does beyond reading the tables.
+#[derive(Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// How string literals are read.
+    pub literals: LiteralDecryption,
+}
+
+/// A parsed `global-metadata.dat`. The tables are read as stored; names are
+/// looked up in the string table through the `*_name` methods.
 pub struct GlobalMetadata {
+    /// The header, as read.
     pub header: GlobalMetadataHeader,
+    /// The version, with the 24.x sub-version worked out.
+    pub version: MetadataVersion,
+    /// Every string literal, decoded as UTF-8 with invalid bytes replaced.
     pub string_literals: Vec<String>,
+    /// The scheme `string_literals` were decrypted with, if any.
+    pub literal_scheme: Option<LiteralScheme>,
+    /// How sure detection was of it, when it was detected.
+    pub literal_detection: Option<Detection>,
+    /// The images, one per assembly.
     pub images: Vec<ImageDefinition>,
+    /// The assemblies.
     pub assemblies: Vec<AssemblyDefinition>,
+    /// Every type definition.
     pub type_definitions: Vec<TypeDefinition>,
+    /// Every method definition.
+    pub methods: Vec<MethodDefinition>,
+    /// Every field definition.
+    pub fields: Vec<FieldDefinition>,
+    /// Field constants; see `field_default_value`.
+    pub field_default_values: Vec<FieldDefaultValue>,
+    /// Marshaled field sizes; see `field_marshaled_size`.
+    pub field_marshaled_sizes: Vec<FieldMarshaledSize>,
+    /// Every method parameter.
+    pub parameters: Vec<ParameterDefinition>,
+    /// Parameter defaults; see `parameter_default_value`.
+    pub parameter_default_values: Vec<ParameterDefaultValue>,
+    /// Every property.
+    pub properties: Vec<PropertyDefinition>,
+    /// Every event.
+    pub events: Vec<EventDefinition>,
+    /// Every generic container.
+    pub generic_containers: Vec<GenericContainer>,
+    /// Every generic parameter.
+    pub generic_parameters: Vec<GenericParameter>,
+    /// Constraint type indices, ranged by the generic parameters.
+    pub generic_parameter_constraints: Vec<i32>,
+    /// Indices into `type_definitions`, ranged by the enclosing types.
+    pub nested_types: Vec<i32>,
+    /// Interface type indices, ranged by the implementing types.
+    pub interfaces: Vec<i32>,
+    /// Encoded vtable entries; see `type_vtable`.
+    pub vtable_methods: Vec<u32>,
+    /// Interface offsets, ranged by the types.
+    pub interface_offsets: Vec<InterfaceOffsetPair>,
+    /// Pre-29 attribute ranges.
+    pub attribute_type_ranges: Vec<CustomAttributeTypeRange>,
+    /// Pre-29 attribute type indices, ranged by `attribute_type_ranges`.
+    pub attribute_types: Vec<i32>,
+    /// 29+ attribute blob ranges.
+    pub attribute_data_ranges: Vec<CustomAttributeDataRange>,
+    /// Usage lists, up to 24.5.
     pub metadata_usage_lists: Vec<MetadataUsageList>,
+    /// Usage pairs, up to 24.5.
     pub metadata_usage_pairs: Vec<MetadataUsagePair>,
+    /// Fields referenced by FieldInfo usages.
+    pub field_refs: Vec<FieldRef>,
+    /// Where each string literal's bytes are.
     pub string_literal_infos: Vec<StringLiteralInfo>,
     string_data: Vec<u8>,
+    default_value_data: Vec<u8>,
+    field_default_value_index: HashMap<i32, usize>,
+    field_marshaled_size_index: HashMap<i32, usize>,
+    parameter_default_value_index: HashMap<i32, usize>,
+    declaring_types: HashMap<usize, usize>,
+    attribute_data: Vec<u8>,
+    attribute_ranges: HashMap<(usize, u32), usize>,
 }
 
 impl GlobalMetadata {
+    /// Parses decrypted metadata, leaving the string literals as stored.
     pub fn parse(data: &[u8]) -> Result<Self, String> {
-        let mut r = Reader::new(data, 0);
-        let header = GlobalMetadataHeader::read(&mut r)?;
-
-        let str_start = header.string_offset as usize;
-        let str_end = str_start + header.string_count as usize;
-        let string_data = data
-            .get(str_start..str_end)
-            .ok_or("String data section out of bounds")?
-            .to_vec();
-
-        let info_count = header.string_literal_count as usize / STRING_LITERAL_INFO_SIZE;
-        let mut r = Reader::new(data, header.string_literal_offset as usize);
-        let mut literal_infos = Vec::with_capacity(info_count);
-        for _ in 0..info_count {
-            literal_infos.push(StringLiteralInfo::read(&mut r)?);
-        }
-
-        let lit_data_start = header.string_literal_data_offset as usize;
-        let lit_data_end = lit_data_start + header.string_literal_data_count as usize;
-        let lit_data = data
-            .get(lit_data_start..lit_data_end)
-            .ok_or("String literal data section out of bounds")?;
-        let mut string_literals = Vec::with_capacity(info_count);
-        for info in &literal_infos {
-            let start = info.offset as usize;
-            let end = start + info.length as usize;
-            if end <= lit_data.len() {
-                string_literals.push(String::from_utf8_lossy(&lit_data[start..end]).into_owned());
-            } else {
-                string_literals.push(String::new());
-            }
-        }
+        Self::parse_with(data, ParseOptions::default())
+    }
 
-        let img_count = header.images_count as usize / IMAGE_DEF_SIZE;
-        let mut r = Reader::new(data, header.images_offset as usize);
-        let mut images = Vec::with_capacity(img_count);
-        for _ in 0..img_count {
-            images.push(ImageDefinition::read(&mut r)?);
-        }
+    /// Parses decrypted metadata. Fails on unsupported versions and on
+    /// truncated or out-of-bounds sections, but not on a wrong magic, which
+    /// `is_valid` reports.
+    pub fn parse_with(data: &[u8], options: ParseOptions) -> Result<Self, String> {
+        let header = GlobalMetadataHeader::parse(data)?;
+        let version = MetadataVersion::detect(data, &header)?;
 
-        let asm_count = header.assemblies_count as usize / ASSEMBLY_DEF_SIZE;
-        let mut r = Reader::new(data, header.assemblies_offset as usize);
-        let mut assemblies = Vec::with_capacity(asm_count);
-        for _ in 0..asm_count {
-            assemblies.push(AssemblyDefinition::read(&mut r)?);
-        }
+        let string_data = section(
+            data,
+            header.string_offset,
+            header.string_count,
+            "String data",
+        )?
+        .to_vec();
 
-        let type_count = header.type_definitions_count as usize / TYPE_DEF_SIZE;
-        let mut r = Reader::new(data, header.type_definitions_offset as usize);
-        let mut type_definitions = Vec::with_capacity(type_count);
-        for _ in 0..type_count {
-            type_definitions.push(TypeDefinition::read(&mut r)?);
-        }
+        let literal_infos: Vec<StringLiteralInfo> = read_table(
+            data,
+            header.string_literal_offset,
+            header.string_literal_count,
+            version,
+        )?;
 
-        let list_count = header.metadata_usage_lists_count as usize / METADATA_USAGE_LIST_SIZE;
-        let mut r = Reader::new(data, header.metadata_usage_lists_offset as usize);
-        let mut metadata_usage_lists = Vec::with_capacity(list_count);
-        for _ in 0..list_count {
-            metadata_usage_lists.push(MetadataUsageList::read(&mut r)?);
-        }
+        let lit_data = section(
+            data,
+            header.string_literal_data_offset,
+            header.string_literal_data_count,
+            "String literal data",
+        )?;
+        let literal_bytes: Vec<&[u8]> = literal_infos
+            .iter()
+            .map(|info| {
+                let start = info.offset as usize;
+                lit_data
+                    .get(start..start + info.length as usize)
+                    .unwrap_or_default()
+            })
+            .collect();
+        let literal_detection = match options.literals {
+            LiteralDecryption::Detect => {
+                let indexed: Vec<_> = literal_bytes.iter().copied().enumerate().collect();
+                literals::detect(&indexed)
+            }
+            _ => None,
+        };
+        let literal_scheme = match options.literals {
+            LiteralDecryption::Keep => None,
+            LiteralDecryption::Scheme(scheme) => Some(scheme),
+            LiteralDecryption::Detect => literal_detection.map(|detection| detection.scheme),
+        };
+        let string_literals = literal_bytes
+            .iter()
+            .zip(&literal_infos)
+            .enumerate()
+            .map(|(index, (bytes, info))| {
+                let mut bytes = bytes.to_vec();
+                if let Some(scheme) = literal_scheme {
+                    scheme.apply(&mut bytes, index, info.length);
+                }
+                String::from_utf8_lossy(&bytes).into_owned()
+            })
+            .collect();
 
-        let pair_count = header.metadata_usage_pairs_count as usize / METADATA_USAGE_PAIR_SIZE;
-        let mut r = Reader::new(data, header.metadata_usage_pairs_offset as usize);
-        let mut metadata_usage_pairs = Vec::with_capacity(pair_count);
-        for _ in 0..pair_count {
-            metadata_usage_pairs.push(MetadataUsagePair::read(&mut r)?);
+        let images: Vec<ImageDefinition> =
+            read_table(data, header.images_offset, header.images_count, version)?;
+        let assemblies = read_table(
+            data,
+            header.assemblies_offset,
+            header.assemblies_count,
+            version,
+        )?;
+        let type_definitions: Vec<TypeDefinition> = read_table(
+            data,
+            header.type_definitions_offset,
+            header.type_definitions_count,
+            version,
+        )?;
+        let methods = read_table(data, header.methods_offset, header.methods_count, version)?;
+        let fields = read_table(data, header.fields_offset, header.fields_count, version)?;
+        let field_default_values: Vec<FieldDefaultValue> = read_table(
+            data,
+            header.field_default_values_offset,
+            header.field_default_values_count,
+            version,
+        )?;
+        let field_marshaled_sizes: Vec<FieldMarshaledSize> = read_table(
+            data,
+            header.field_marshaled_sizes_offset,
+            header.field_marshaled_sizes_count,
+            version,
+        )?;
+        let default_value_data = section(
+            data,
+            header.field_and_parameter_default_value_data_offset,
+            header.field_and_parameter_default_value_data_count,
+            "Default value data",
+        )?
+        .to_vec();
+        let field_default_value_index = index_by(&field_default_values, |v| v.field_index);
+        let field_marshaled_size_index = index_by(&field_marshaled_sizes, |v| v.field_index);
+        let parameters = read_table(
+            data,
+            header.parameters_offset,
+            header.parameters_count,
+            version,
+        )?;
+        let parameter_default_values: Vec<ParameterDefaultValue> = read_table(
+            data,
+            header.parameter_default_values_offset,
+            header.parameter_default_values_count,
+            version,
+        )?;
+        let parameter_default_value_index =
+            index_by(&parameter_default_values, |v| v.parameter_index);
+        let properties = read_table(
+            data,
+            header.properties_offset,
+            header.properties_count,
+            version,
+        )?;
+        let events = read_table(data, header.events_offset, header.events_count, version)?;
+        let generic_containers = read_table(
+            data,
+            header.generic_containers_offset,
+            header.generic_containers_count,
+            version,
+        )?;
+        let generic_parameters = read_table(
+            data,
+            header.generic_parameters_offset,
+            header.generic_parameters_count,
+            version,
+        )?;
+        let generic_parameter_constraints = read_table(
+            data,
+            header.generic_parameter_constraints_offset,
+            header.generic_parameter_constraints_count,
+            version,
+        )?;
+        let nested_types = read_table(
+            data,
+            header.nested_types_offset,
+            header.nested_types_count,
+            version,
+        )?;
+        let interfaces = read_table(
+            data,
+            header.interfaces_offset,
+            header.interfaces_count,
+            version,
+        )?;
+        let vtable_methods = read_table(
+            data,
+            header.vtable_methods_offset,
+            header.vtable_methods_count,
+            version,
+        )?;
+        let interface_offsets = read_table(
+            data,
+            header.interface_offsets_offset,
+            header.interface_offsets_count,
+            version,
+        )?;
+        // declaring_type_index is a type index into the binary, so nesting
+        // is worked out from the nested type lists instead.
+        let mut declaring_types = HashMap::new();
+        for (parent, type_def) in type_definitions.iter().enumerate() {
+            let children: &[i32] = range(
+                &nested_types,
+                type_def.nested_types_start,
+                type_def.nested_type_count as usize,
+            );
+            for &child in children {
+                if let Ok(child) = usize::try_from(child) {
+                    declaring_types.insert(child, parent);
+                }
+            }
+        }
+        let attribute_type_ranges: Vec<CustomAttributeTypeRange> = read_table(
+            data,
+            header.attributes_info_offset,
+            header.attributes_info_count,
+            version,
+        )?;
+        let attribute_types = read_table(
+            data,
+            header.attribute_types_offset,
+            header.attribute_types_count,
+            version,
+        )?;
+        let attribute_data_ranges: Vec<CustomAttributeDataRange> = read_table(
+            data,
+            header.attribute_data_range_offset,
+            header.attribute_data_range_count,
+            version,
+        )?;
+        let attribute_data = section(
+            data,
+            header.attribute_data_offset,
+            header.attribute_data_size,
+            "Attribute data",
+        )?
+        .to_vec();
+        // Tokens are only unique within an image, whose custom attribute
+        // range selects the entries that belong to it.
+        let range_tokens: Vec<u32> = if version >= MetadataVersion::V29_0 {
+            attribute_data_ranges.iter().map(|r| r.token).collect()
+        } else {
+            attribute_type_ranges.iter().map(|r| r.token).collect()
+        };
+        let mut attribute_ranges = HashMap::new();
+        if version >= MetadataVersion::V24_1 {
+            for (image_index, image) in images.iter().enumerate() {
+                let start = image.custom_attribute_start;
+                let count = image.custom_attribute_count as usize;
+                for (i, &token) in range(&range_tokens, start, count).iter().enumerate() {
+                    attribute_ranges.insert((image_index, token), start as usize + i);
+                }
+            }
         }
+        let metadata_usage_lists = read_table(
+            data,
+            header.metadata_usage_lists_offset,
+            header.metadata_usage_lists_count,
+            version,
+        )?;
+        let metadata_usage_pairs = read_table(
+            data,
+            header.metadata_usage_pairs_offset,
+            header.metadata_usage_pairs_count,
+            version,
+        )?;
+        let field_refs = read_table(
+            data,
+            header.field_refs_offset,
+            header.field_refs_count,
+            version,
+        )?;
 
         Ok(Self {
             header,
+            version,
             string_literals,
+            literal_scheme,
+            literal_detection,
             images,
             assemblies,
             type_definitions,
+            methods,
+            fields,
+            field_default_values,
+            field_marshaled_sizes,
+            parameters,
+            parameter_default_values,
+            properties,
+            events,
+            generic_containers,
+            generic_parameters,
+            generic_parameter_constraints,
+            nested_types,
+            interfaces,
+            vtable_methods,
+            interface_offsets,
+            attribute_type_ranges,
+            attribute_types,
+            attribute_data_ranges,
             metadata_usage_lists,
             metadata_usage_pairs,
+            field_refs,
             string_literal_infos: literal_infos,
             string_data,
+            default_value_data,
+            field_default_value_index,
+            field_marshaled_size_index,
+            parameter_default_value_index,
+            declaring_types,
+            attribute_data,
+            attribute_ranges,
         })
     }
 
+    /// The file the metadata was parsed from, with its string literal bytes
+    /// decrypted with `scheme` or left as they are, independently of how it
+    /// was parsed.
+    pub fn write(&self, data: &[u8], scheme: Option<LiteralScheme>) -> Vec<u8> {
+        let mut out = data.to_vec();
+        let Some(scheme) = scheme else {
+            return out;
+        };
+        let lit_data_start = self.header.string_literal_data_offset as usize;
+        for (index, info) in self.string_literal_infos.iter().enumerate() {
+            let start = lit_data_start + info.offset as usize;
+            let end = start + info.length as usize;
+            if let Some(slice) = out.get_mut(start..end) {
+                scheme.apply(slice, index, info.length);
+            }
+        }
+        out
+    }
+
+    /// Whether the header starts with the metadata magic.
     pub fn is_valid(&self) -> bool {
         self.header.sanity == EXPECTED_MAGIC
     }
 
+    /// The header magic as stored.
     pub fn magic_bytes(&self) -> [u8; 4] {
         self.header.sanity.to_le_bytes()
     }
 
+    /// A type's methods.
+    pub fn type_methods(&self, type_def: &TypeDefinition) -> &[MethodDefinition] {
+        range(
+            &self.methods,
+            type_def.method_start,
+            type_def.method_count as usize,
+        )
+    }
+
+    /// A method's name.
+    pub fn method_name(&self, method: &MethodDefinition) -> Option<&str> {
+        self.get_string(method.name_index)
+    }
+
+    /// Resolves a method index relative to the type, as property and event
+    /// accessors store them, to an index into `methods`.
+    pub fn type_method_index(&self, type_def: &TypeDefinition, relative: i32) -> Option<usize> {
+        if relative < 0 || relative >= type_def.method_count as i32 {
+            return None;
+        }
+        let index = usize::try_from(type_def.method_start.checked_add(relative)?).ok()?;
+        (index < self.methods.len()).then_some(index)
+    }
+
+    /// A type's properties.
+    pub fn type_properties(&self, type_def: &TypeDefinition) -> &[PropertyDefinition] {
+        range(
+            &self.properties,
+            type_def.property_start,
+            type_def.property_count as usize,
+        )
+    }
+
+    /// A property's name.
+    pub fn property_name(&self, property: &PropertyDefinition) -> Option<&str> {
+        self.get_string(property.name_index)
+    }
+
+    /// A type's events.
+    pub fn type_events(&self, type_def: &TypeDefinition) -> &[EventDefinition] {
+        range(
+            &self.events,
+            type_def.event_start,
+            type_def.event_count as usize,
+        )
+    }
+
+    /// An event's name.
+    pub fn event_name(&self, event: &EventDefinition) -> Option<&str> {
+        self.get_string(event.name_index)
+    }
+
+    /// Indices into `methods` of the type's property and event accessors,
+    /// which dumps fold into the property or event declaration.
+    pub fn accessor_methods(&self, type_def: &TypeDefinition) -> HashSet<usize> {
+        let properties = self
+            .type_properties(type_def)
+            .iter()
+            .flat_map(|p| [p.get, p.set]);
+        let events = self
+            .type_events(type_def)
+            .iter()
+            .flat_map(|e| [e.add, e.remove, e.raise]);
+        properties
+            .chain(events)
+            .filter_map(|relative| self.type_method_index(type_def, relative))
+            .collect()
+    }
+
+    /// A method's parameters.
+    pub fn method_parameters(&self, method: &MethodDefinition) -> &[ParameterDefinition] {
+        range(
+            &self.parameters,
+            method.parameter_start,
+            method.parameter_count as usize,
+        )
+    }
+
+    /// A parameter's name.
+    pub fn parameter_name(&self, parameter: &ParameterDefinition) -> Option<&str> {
+        self.get_string(parameter.name_index)
+    }
+
+    /// The default value of the parameter at `parameter_index`, if it has one.
+    pub fn parameter_default_value(&self, parameter_index: i32) -> Option<&ParameterDefaultValue> {
+        self.parameter_default_value_index
+            .get(&parameter_index)
+            .map(|&i| &self.parameter_default_values[i])
+    }
+
+    /// A type's fields.
+    pub fn type_fields(&self, type_def: &TypeDefinition) -> &[FieldDefinition] {
+        range(
+            &self.fields,
+            type_def.field_start,
+            type_def.field_count as usize,
+        )
+    }
+
+    /// A field's name.
+    pub fn field_name(&self, field: &FieldDefinition) -> Option<&str> {
+        self.get_string(field.name_index)
+    }
+
+    /// The constant of the field at `field_index`, if it has one.
+    pub fn field_default_value(&self, field_index: i32) -> Option<&FieldDefaultValue> {
+        self.field_default_value_index
+            .get(&field_index)
+            .map(|&i| &self.field_default_values[i])
+    }
+
+    /// The marshaled size of the field at `field_index`, if it has one.
+    pub fn field_marshaled_size(&self, field_index: i32) -> Option<&FieldMarshaledSize> {
+        self.field_marshaled_size_index
+            .get(&field_index)
+            .map(|&i| &self.field_marshaled_sizes[i])
+    }
+
+    /// Decodes a constant from the shared field and parameter default value
+    /// data. The element type is not stored alongside the value: it comes
+    /// from the binary's type table (the underlying type, for enums).
+    pub fn default_value(
+        &self,
+        data_index: i32,
+        element: ElementType,
+    ) -> Result<ConstantValue, String> {
+        let Ok(start) = usize::try_from(data_index) else {
+            return Ok(ConstantValue::Null);
+        };
+        self.read_constant(&mut Reader::new(&self.default_value_data, start), element)
+    }
+
+    // The constant encoding shared by default values and attribute data.
+    fn read_constant(&self, r: &mut Reader, element: ElementType) -> Result<ConstantValue, String> {
+        let compressed = self.version >= MetadataVersion::V29_0;
+        Ok(match element {
+            ElementType::Boolean => ConstantValue::Boolean(r.read_u8()? != 0),
+            ElementType::Char => {
+                let unit = r.read_u16()?;
+                ConstantValue::Char(char::from_u32(unit as u32).unwrap_or('\u{FFFD}'))
+            }
+            ElementType::I1 => ConstantValue::I1(r.read_u8()? as i8),
+            ElementType::U1 => ConstantValue::U1(r.read_u8()?),
+            ElementType::I2 => ConstantValue::I2(r.read_u16()? as i16),
+            ElementType::U2 => ConstantValue::U2(r.read_u16()?),
+            ElementType::I4 if compressed => ConstantValue::I4(r.read_compressed_i32()?),
+            ElementType::I4 => ConstantValue::I4(r.read_i32()?),
+            ElementType::U4 if compressed => ConstantValue::U4(r.read_compressed_u32()?),
+            ElementType::U4 => ConstantValue::U4(r.read_u32()?),
+            ElementType::I8 => ConstantValue::I8(r.read_u64()? as i64),
+            ElementType::U8 => ConstantValue::U8(r.read_u64()?),
+            ElementType::R4 => ConstantValue::R4(f32::from_bits(r.read_u32()?)),
+            ElementType::R8 => ConstantValue::R8(f64::from_bits(r.read_u64()?)),
+            ElementType::String => {
+                let length = if compressed {
+                    r.read_compressed_i32()?
+                } else {
+                    r.read_i32()?
+                };
+                match usize::try_from(length) {
+                    Ok(length) => ConstantValue::String(
+                        String::from_utf8_lossy(r.read_bytes(length)?).into_owned(),
+                    ),
+                    Err(_) => ConstantValue::Null,
+                }
+            }
+            // Reference types, generic instances included, can only default
+            // to null.
+            ElementType::Class
+            | ElementType::Object
+            | ElementType::SzArray
+            | ElementType::GenericInst => ConstantValue::Null,
+            other => return Err(format!("No constant encoding for {:?}", other)),
+        })
+    }
+
+    /// Indices into `type_definitions`.
+    pub fn type_nested_types(&self, type_def: &TypeDefinition) -> &[i32] {
+        range(
+            &self.nested_types,
+            type_def.nested_types_start,
+            type_def.nested_type_count as usize,
+        )
+    }
+
+    /// The enclosing type definition of a nested type.
+    pub fn declaring_type(&self, type_index: usize) -> Option<usize> {
+        self.declaring_types.get(&type_index).copied()
+    }
+
+    /// Type indices into the binary's type table.
+    pub fn type_interfaces(&self, type_def: &TypeDefinition) -> &[i32] {
+        range(
+            &self.interfaces,
+            type_def.interfaces_start,
+            type_def.interfaces_count as usize,
+        )
+    }
+
+    /// A type's interface offsets.
+    pub fn type_interface_offsets(&self, type_def: &TypeDefinition) -> &[InterfaceOffsetPair] {
+        range(
+            &self.interface_offsets,
+            type_def.interface_offsets_start,
+            type_def.interface_offsets_count as usize,
+        )
+    }
+
+    /// A type's vtable, decoded.
+    pub fn type_vtable(&self, type_def: &TypeDefinition) -> Vec<VtableSlot> {
+        range(
+            &self.vtable_methods,
+            type_def.vtable_start,
+            type_def.vtable_count as usize,
+        )
+        .iter()
+        .map(|&encoded| match self.decode_index(encoded) {
+            (Some(UsageKind::MethodDef), index) if (index as usize) < self.methods.len() => {
+                VtableSlot::Method(index as usize)
+            }
+            (Some(UsageKind::MethodRef), index) => VtableSlot::MethodSpec(index),
+            _ => VtableSlot::Empty,
+        })
+        .collect()
+    }
+
+    /// Splits an encoded index into its kind and index. From 27 the low bit
+    /// is a flag and the index sits above it.
+    pub fn decode_index(&self, encoded: u32) -> (Option<UsageKind>, u32) {
+        let kind = UsageKind::from_u32((encoded & 0xE000_0000) >> 29);
+        let index = if self.version.major >= 27 {
+            (encoded & 0x1FFF_FFFE) >> 1
+        } else {
+            encoded & 0x1FFF_FFFF
+        };
+        (kind, index)
+    }
+
+    /// The image whose type range holds the type definition.
+    pub fn type_image(&self, type_index: usize) -> Option<usize> {
+        self.images.iter().position(|image| {
+            usize::try_from(image.type_start).is_ok_and(|start| {
+                type_index >= start && type_index < start + image.type_count as usize
+            })
+        })
+    }
+
+    /// The attribute range of a token in an image, indexing
+    /// `attribute_type_ranges` before 29 and `attribute_data_ranges` after.
+    /// 24.0 records carry the index themselves as `legacy_index`.
+    pub fn attribute_range(
+        &self,
+        image_index: usize,
+        token: u32,
+        legacy_index: i32,
+    ) -> Option<usize> {
+        if self.version < MetadataVersion::V24_1 {
+            return usize::try_from(legacy_index)
+                .ok()
+                .filter(|&i| i < self.attribute_type_ranges.len());
+        }
+        self.attribute_ranges.get(&(image_index, token)).copied()
+    }
+
+    /// Pre-29: the type indices of the attributes in a range.
+    pub fn attribute_type_indices(&self, range_index: usize) -> &[i32] {
+        self.attribute_type_ranges
+            .get(range_index)
+            .map(|r| {
+                range(
+                    &self.attribute_types,
+                    r.start,
+                    usize::try_from(r.count).unwrap_or(0),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// 29+: decodes the attributes in a range. Enum arguments are stored as
+    /// their underlying type, which `enum_type` resolves from the enum's type
+    /// index.
+    pub fn attributes(
+        &self,
+        range_index: usize,
+        enum_type: &dyn Fn(i32) -> Option<ElementType>,
+    ) -> Result<Vec<CustomAttribute>, String> {
+        let Some(entry) = self.attribute_data_ranges.get(range_index) else {
+            return Ok(Vec::new());
+        };
+        let start = entry.start_offset as usize;
+        let end = self
+            .attribute_data_ranges
+            .get(range_index + 1)
+            .map_or(self.attribute_data.len(), |next| next.start_offset as usize);
+        let blob = self
+            .attribute_data
+            .get(start..end)
+            .ok_or("Attribute data range out of bounds")?;
+
+        let mut r = Reader::new(blob, 0);
+        let count = r.read_compressed_u32()? as usize;
+        let mut constructors = Vec::with_capacity(count.min(blob.len() / 4));
+        for _ in 0..count {
+            constructors.push(r.read_u32()? as usize);
+        }
+        let mut attributes = Vec::with_capacity(constructors.len());
+        for constructor in constructors {
+            let declaring = self
+                .methods
+                .get(constructor)
+                .and_then(|m| usize::try_from(m.declaring_type).ok())
+                .and_then(|t| self.type_definitions.get(t))
+                .ok_or_else(|| format!("Invalid attribute constructor {}", constructor))?;
+            let argument_count = r.read_compressed_u32()?;
+            let field_count = r.read_compressed_u32()?;
+            let property_count = r.read_compressed_u32()?;
+
+            let mut arguments = Vec::new();
+            for _ in 0..argument_count {
+                arguments.push(self.read_attribute_value(&mut r, enum_type)?);
+            }
+            let mut named_arguments = Vec::new();
+            for i in 0..field_count + property_count {
+                let value = self.read_attribute_value(&mut r, enum_type)?;
+                let (owner, member) = self.read_named_member(&mut r, declaring)?;
+                let name = if i < field_count {
+                    self.fields
+                        .get(owner.field_start as usize + member)
+                        .and_then(|f| self.field_name(f))
+                } else {
+                    self.properties
+                        .get(owner.property_start as usize + member)
+                        .and_then(|p| self.property_name(p))
+                };
+                named_arguments.push(NamedArgument {
+                    name: name.unwrap_or("").to_string(),
+                    value,
+                });
+            }
+            attributes.push(CustomAttribute {
+                constructor,
+                arguments,
+                named_arguments,
+            });
+        }
+        Ok(attributes)
+    }
+
+    fn read_attribute_value(
+        &self,
+        r: &mut Reader,
+        enum_type: &dyn Fn(i32) -> Option<ElementType>,
+    ) -> Result<AttributeValue, String> {
+        let (element, enum_index) = self.read_encoded_type(r, enum_type)?;
+        self.read_attribute_payload(r, element, enum_index, enum_type)
+    }
+
+    // A type tag, resolving enums to their underlying type.
+    fn read_encoded_type(
+        &self,
+        r: &mut Reader,
+        enum_type: &dyn Fn(i32) -> Option<ElementType>,
+    ) -> Result<(u8, Option<i32>), String> {
+        let tag = r.read_u8()?;
+        if tag != ENCODED_TYPE_ENUM {
+            return Ok((tag, None));
+        }
+        let type_index = r.read_compressed_i32()?;
+        let underlying = enum_type(type_index)
+            .ok_or_else(|| format!("Unknown underlying type for enum type {}", type_index))?;
+        Ok((underlying as u8, Some(type_index)))
+    }
+
+    fn read_attribute_payload(
+        &self,
+        r: &mut Reader,
+        tag: u8,
+        enum_index: Option<i32>,
+        enum_type: &dyn Fn(i32) -> Option<ElementType>,
+    ) -> Result<AttributeValue, String> {
+        if tag == ENCODED_TYPE_INDEX {
+            return Ok(AttributeValue::Type(r.read_compressed_i32()?));
+        }
+        let element = ElementType::from_u8(tag)
+            .ok_or_else(|| format!("Unknown attribute argument type 0x{:02X}", tag))?;
+        if element == ElementType::SzArray {
+            let Ok(length) = usize::try_from(r.read_compressed_i32()?) else {
+                return Ok(AttributeValue::Constant(ConstantValue::Null));
+            };
+            let (element_tag, element_enum) = self.read_encoded_type(r, enum_type)?;
+            let mixed = r.read_u8()? == 1;
+            let mut items = Vec::with_capacity(length.min(r.data.len()));
+            for _ in 0..length {
+                let (tag, enum_index) = if mixed {
+                    self.read_encoded_type(r, enum_type)?
+                } else {
+                    (element_tag, element_enum)
+                };
+                items.push(self.read_attribute_payload(r, tag, enum_index, enum_type)?);
+            }
+            return Ok(AttributeValue::Array(items));
+        }
+        let value = self.read_constant(r, element)?;
+        Ok(match enum_index {
+            Some(type_index) => AttributeValue::Enum { type_index, value },
+            None => AttributeValue::Constant(value),
+        })
+    }
+
+    // A named argument's field or property index, relative to the attribute
+    // type or, when negative, to a base type that follows it.
+    fn read_named_member<'a>(
+        &'a self,
+        r: &mut Reader,
+        attribute_type: &'a TypeDefinition,
+    ) -> Result<(&'a TypeDefinition, usize), String> {
+        let member = r.read_compressed_i32()?;
+        if member >= 0 {
+            return Ok((attribute_type, member as usize));
+        }
+        let type_index = r.read_compressed_u32()? as usize;
+        let owner = self
+            .type_definitions
+            .get(type_index)
+            .ok_or_else(|| format!("Invalid named argument type {}", type_index))?;
+        Ok((owner, -(member + 1) as usize))
+    }
+
+    /// Containers are referenced by index from type and method definitions,
+    /// with -1 for non-generic ones.
+    pub fn generic_container(&self, index: i32) -> Option<&GenericContainer> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.generic_containers.get(index))
+    }
+
+    /// A generic container's parameters.
+    pub fn container_parameters(&self, container: &GenericContainer) -> &[GenericParameter] {
+        range(
+            &self.generic_parameters,
+            container.generic_parameter_start,
+            usize::try_from(container.type_argc).unwrap_or(0),
+        )
+    }
+
+    /// A generic parameter's name.
+    pub fn generic_parameter_name(&self, parameter: &GenericParameter) -> Option<&str> {
+        self.get_string(parameter.name_index)
+    }
+
+    /// A generic parameter's constraints, as type indices.
+    pub fn parameter_constraints(&self, parameter: &GenericParameter) -> &[i32] {
+        range(
+            &self.generic_parameter_constraints,
+            parameter.constraints_start as i32,
+            usize::try_from(parameter.constraints_count).unwrap_or(0),
+        )
+    }
+
+    /// A type's name, without its namespace.
+    pub fn type_name(&self, type_def: &TypeDefinition) -> Option<&str> {
+        self.get_string(type_def.name_index)
+    }
+
+    /// The identifier at `index` in the string table.
     pub fn get_string(&self, index: i32) -> Option<&str> {
         if index < 0 || index as usize >= self.string_data.len() {
             return None;
@@ -541,20 +2586,468 @@ impl GlobalMetadata {
     }
 }
 
-pub fn decrypt_string_literals(metadata: &GlobalMetadata, data: &mut [u8]) -> Result<(), String> {
-    let lit_data_start = metadata.header.string_literal_data_offset as usize;
-    
-    for info in &metadata.string_literal_infos {
-        let start = lit_data_start + info.offset as usize;
-        let end = start + info.length as usize;
-        
-        if let Some(slice) = data.get_mut(start..end) {
-            let xor_key = (info.length as u8) ^ 0x2E;
-            for byte in slice {
-                *byte ^= xor_key;
-            }
+// The `count` records from `start`, or none when the range is unset (-1) or
+// runs past the table.
+fn range<T>(table: &[T], start: i32, count: usize) -> &[T] {
+    usize::try_from(start)
+        .ok()
+        .and_then(|start| table.get(start..start.checked_add(count)?))
+        .unwrap_or_default()
+}
+
+fn index_by<T>(table: &[T], key: impl Fn(&T) -> i32) -> HashMap<i32, usize> {
+    table
+        .iter()
+        .enumerate()
+        .map(|(i, record)| (key(record), i))
+        .collect()
+}
+
+/// Whether `data` starts with the metadata magic, without parsing it.
+pub fn has_valid_magic(data: &[u8]) -> bool {
+    data.get(..4)
+        .is_some_and(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) == EXPECTED_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::literals::DEFAULT_SCHEME;
+    use crate::testing::metadata_file;
+
+    const HEADER_SIZE: usize = 256;
+
+    // A version 29 file holding only `literals`, XORed the way hg builds
+    // store them. Every other section is empty.
+    fn with_literals(literals: &[&str]) -> Vec<u8> {
+        encrypted_with(literals, DEFAULT_SCHEME)
+    }
+
+    fn encrypted_with(literals: &[&str], scheme: LiteralScheme) -> Vec<u8> {
+        let mut infos = Vec::new();
+        let mut bytes = Vec::new();
+        for (index, literal) in literals.iter().enumerate() {
+            let mut encrypted = literal.as_bytes().to_vec();
+            scheme.apply(&mut encrypted, index, literal.len() as u32);
+            infos.extend((literal.len() as u32).to_le_bytes());
+            infos.extend((bytes.len() as u32).to_le_bytes());
+            bytes.extend(encrypted);
+        }
+        let mut data = vec![0; HEADER_SIZE];
+        data[..4].copy_from_slice(&EXPECTED_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&29i32.to_le_bytes());
+        let sections = [
+            (HEADER_SIZE, infos.len()),
+            (HEADER_SIZE + infos.len(), bytes.len()),
+        ];
+        for (i, (offset, size)) in sections.into_iter().enumerate() {
+            data[8 + i * 8..12 + i * 8].copy_from_slice(&(offset as i32).to_le_bytes());
+            data[12 + i * 8..16 + i * 8].copy_from_slice(&(size as i32).to_le_bytes());
+        }
+        data.extend(infos);
+        data.extend(bytes);
+        data
+    }
+
+    // Header section pairs: 24.0 and 24.1 carry rgctx entries, usages and
+    // attribute type ranges; 24.2 drops rgctx; 27 drops the usages and adds
+    // Windows Runtime strings; 29 swaps the type ranges for attribute data.
+    const V24_0_PAIRS: usize = 33;
+    const V24_2_PAIRS: usize = 32;
+    const PAIRS: usize = 31;
+
+    const STRINGS: usize = 2;
+    const PROPERTIES: usize = 4;
+    const METHODS: usize = 5;
+    const DEFAULT_VALUE_DATA: usize = 8;
+    const FIELDS: usize = 11;
+    const TYPE_DEFINITIONS: usize = 19;
+    const V24_0_IMAGES: usize = 21;
+    const IMAGES: usize = 20;
+    const ATTRIBUTE_DATA: usize = 24;
+    const ATTRIBUTE_DATA_RANGES: usize = 25;
+
+    // `count` images of `size` bytes, each with the token 1 every image has.
+    fn images(count: usize, size: usize) -> Vec<u8> {
+        let mut data = vec![0; count * size];
+        for image in data.chunks_exact_mut(size) {
+            image[28..32].copy_from_slice(&1u32.to_le_bytes());
+        }
+        data
+    }
+
+    fn detect(data: &[u8]) -> MetadataVersion {
+        GlobalMetadata::parse(data).unwrap().version
+    }
+
+    fn decrypting() -> ParseOptions {
+        ParseOptions {
+            literals: LiteralDecryption::Scheme(DEFAULT_SCHEME),
+        }
+    }
+
+    fn detecting() -> ParseOptions {
+        ParseOptions {
+            literals: LiteralDecryption::Detect,
+        }
+    }
+
+    const SAMPLE: [&str; 6] = [
+        "UnityEngine.Debug",
+        "Player died",
+        "config_id",
+        "Assets/Prefabs/Enemy.prefab",
+        "Loading scene {0}",
+        "",
+    ];
+
+    #[test]
+    fn parse_keeps_literal_bytes_by_default() {
+        let data = with_literals(&["Hello"]);
+        let metadata = GlobalMetadata::parse(&data).unwrap();
+        // 5 ^ 0x2E = 0x2B
+        let expected: String = "Hello".chars().map(|c| (c as u8 ^ 0x2B) as char).collect();
+        assert_eq!(metadata.string_literals, [expected]);
+    }
+
+    #[test]
+    fn parse_decrypts_literals_when_asked() {
+        let data = with_literals(&["Hello", "UnityEngine.Debug", ""]);
+        let metadata = GlobalMetadata::parse_with(&data, decrypting()).unwrap();
+        assert_eq!(metadata.string_literals, ["Hello", "UnityEngine.Debug", ""]);
+    }
+
+    #[test]
+    fn parse_does_not_touch_the_input() {
+        let data = with_literals(&["config_id"]);
+        let copy = data.clone();
+        GlobalMetadata::parse_with(&data, decrypting()).unwrap();
+        assert_eq!(data, copy);
+    }
+
+    #[test]
+    fn write_keeps_original_literal_bytes() {
+        let data = with_literals(&["Hello", "config_id"]);
+        let metadata = GlobalMetadata::parse_with(&data, decrypting()).unwrap();
+        assert_eq!(metadata.write(&data, None), data);
+    }
+
+    #[test]
+    fn write_decrypts_literal_bytes() {
+        let data = with_literals(&["Hello", "config_id"]);
+        let metadata = GlobalMetadata::parse(&data).unwrap();
+        let written = metadata.write(&data, Some(DEFAULT_SCHEME));
+        let start = metadata.header.string_literal_data_offset as usize;
+        assert_eq!(&written[start..], b"Helloconfig_id");
+        assert_eq!(written[..start], data[..start]);
+        let reparsed = GlobalMetadata::parse(&written).unwrap();
+        assert_eq!(reparsed.string_literals, ["Hello", "config_id"]);
+    }
+
+    #[test]
+    fn key_wraps_at_256_bytes() {
+        let literal = "x".repeat(300);
+        let data = with_literals(&[&literal]);
+        // 300 as u8 = 44, 44 ^ 0x2E = 0x02
+        let start = data.len() - literal.len();
+        assert!(data[start..].iter().all(|&b| b == b'x' ^ 0x02));
+        let metadata = GlobalMetadata::parse_with(&data, decrypting()).unwrap();
+        assert_eq!(metadata.string_literals, [literal]);
+    }
+
+    #[test]
+    fn detect_finds_the_hg_scheme() {
+        let data = with_literals(&SAMPLE);
+        let metadata = GlobalMetadata::parse_with(&data, detecting()).unwrap();
+        assert_eq!(metadata.literal_scheme, Some(DEFAULT_SCHEME));
+        assert_eq!(metadata.string_literals, SAMPLE);
+    }
+
+    #[test]
+    fn detect_finds_other_schemes() {
+        for scheme in [
+            LiteralScheme::Xor(0x5A),
+            LiteralScheme::LengthXor(0x13),
+            LiteralScheme::IndexXor(0x77),
+            LiteralScheme::Rolling(0x40),
+        ] {
+            let data = encrypted_with(&SAMPLE, scheme);
+            let metadata = GlobalMetadata::parse_with(&data, detecting()).unwrap();
+            assert_eq!(metadata.string_literals, SAMPLE, "{}", scheme);
+        }
+    }
+
+    #[test]
+    fn detect_leaves_plaintext_alone() {
+        let data = encrypted_with(&SAMPLE, LiteralScheme::Xor(0));
+        let metadata = GlobalMetadata::parse_with(&data, detecting()).unwrap();
+        assert_eq!(metadata.literal_scheme, Some(LiteralScheme::Xor(0)));
+        assert_eq!(metadata.string_literals, SAMPLE);
+    }
+
+    #[test]
+    fn detect_reads_the_major_version() {
+        for (major, version) in [
+            (27, MetadataVersion::V27_0),
+            (29, MetadataVersion::V29_0),
+            (31, MetadataVersion::V31_0),
+        ] {
+            assert_eq!(detect(&metadata_file(major, PAIRS, &[])), version);
+        }
+        assert!(GlobalMetadata::parse(&metadata_file(23, V24_0_PAIRS, &[])).is_err());
+        assert!(GlobalMetadata::parse(&metadata_file(30, PAIRS, &[])).is_err());
+    }
+
+    #[test]
+    fn detect_tells_24_0_from_24_1_by_record_sizes() {
+        let types = [0; 104];
+        let data = metadata_file(
+            24,
+            V24_0_PAIRS,
+            &[(TYPE_DEFINITIONS, &types), (V24_0_IMAGES, &images(1, 32))],
+        );
+        assert_eq!(detect(&data), MetadataVersion::V24_0);
+
+        let types = [0; 100];
+        let data = metadata_file(
+            24,
+            V24_0_PAIRS,
+            &[(TYPE_DEFINITIONS, &types), (V24_0_IMAGES, &images(1, 40))],
+        );
+        assert_eq!(detect(&data), MetadataVersion::V24_1);
+    }
+
+    #[test]
+    fn detect_tells_24_0_from_24_1_by_image_tokens() {
+        // 160 bytes of images fit both sizes; only 24.0 keeps the tokens in
+        // step with a 32-byte stride.
+        let data = metadata_file(24, V24_0_PAIRS, &[(V24_0_IMAGES, &images(5, 32))]);
+        assert_eq!(detect(&data), MetadataVersion::V24_0);
+        let data = metadata_file(24, V24_0_PAIRS, &[(V24_0_IMAGES, &images(4, 40))]);
+        assert_eq!(detect(&data), MetadataVersion::V24_1);
+    }
+
+    #[test]
+    fn detect_tells_24_2_from_24_4_by_assembly_size() {
+        // 24.3 and 24.5 read as 24.2 and 24.4: their changes are all in the
+        // binary.
+        let assemblies = [0; 68];
+        let data = metadata_file(
+            24,
+            V24_2_PAIRS,
+            &[(IMAGES, &images(1, 40)), (IMAGES + 1, &assemblies)],
+        );
+        assert_eq!(detect(&data), MetadataVersion::V24_2);
+
+        let assemblies = [0; 64];
+        let data = metadata_file(
+            24,
+            V24_2_PAIRS,
+            &[(IMAGES, &images(1, 40)), (IMAGES + 1, &assemblies)],
+        );
+        assert_eq!(detect(&data), MetadataVersion::V24_4);
+    }
+
+    #[test]
+    fn header_layout_places_sections_per_version() {
+        type Field = fn(&GlobalMetadataHeader) -> (i32, i32);
+        let rows: [(i32, usize, usize, Field); 8] = [
+            (24, V24_0_PAIRS, 20, |h| {
+                (h.rgctx_entries_offset, h.rgctx_entries_count)
+            }),
+            (24, V24_0_PAIRS, 24, |h| {
+                (h.metadata_usage_pairs_offset, h.metadata_usage_pairs_count)
+            }),
+            (24, V24_2_PAIRS, 20, |h| (h.images_offset, h.images_count)),
+            (24, V24_2_PAIRS, 27, |h| {
+                (h.attribute_types_offset, h.attribute_types_count)
+            }),
+            (27, PAIRS, 24, |h| {
+                (h.attributes_info_offset, h.attributes_info_count)
+            }),
+            (27, PAIRS, 29, |h| {
+                (
+                    h.windows_runtime_strings_offset,
+                    h.windows_runtime_strings_size,
+                )
+            }),
+            (29, PAIRS, 24, |h| {
+                (h.attribute_data_offset, h.attribute_data_size)
+            }),
+            (31, PAIRS, 25, |h| {
+                (h.attribute_data_range_offset, h.attribute_data_range_count)
+            }),
+        ];
+        for (version, pairs, slot, field) in rows {
+            let data = metadata_file(version, pairs, &[(slot, &[0; 8])]);
+            let header = GlobalMetadataHeader::parse(&data).unwrap();
+            let expected = ((8 + pairs * 8) as i32, 8);
+            assert_eq!(field(&header), expected, "{} slot {}", version, slot);
+            let sections = header.sections();
+            assert_eq!(sections.len(), pairs);
+            assert_eq!(sections[slot], expected);
+        }
+    }
+
+    #[test]
+    fn compressed_integers() {
+        let unsigned: [(&[u8], u32); 10] = [
+            (&[0x00], 0),
+            (&[0x7F], 0x7F),
+            (&[0x80, 0x80], 0x80),
+            (&[0xBF, 0xFF], 0x3FFF),
+            (&[0xC0, 0x00, 0x40, 0x00], 0x4000),
+            (&[0xDF, 0xFF, 0xFF, 0xFF], 0x1FFF_FFFF),
+            (&[0xF0, 0x00, 0x00, 0x00, 0x20], 0x2000_0000),
+            (&[0xF0, 0xFF, 0xFF, 0xFF, 0xFF], u32::MAX),
+            (&[0xFE], u32::MAX - 1),
+            (&[0xFF], u32::MAX),
+        ];
+        for (bytes, value) in unsigned {
+            let mut r = Reader::new(bytes, 0);
+            assert_eq!(r.read_compressed_u32(), Ok(value), "{:02X?}", bytes);
+            assert_eq!(r.pos, bytes.len());
+        }
+
+        let signed: [(&[u8], i32); 8] = [
+            (&[0x00], 0),
+            (&[0x01], -1),
+            (&[0x02], 1),
+            (&[0x7F], -64),
+            (&[0x80, 0x80], 64),
+            (&[0xF0, 0xFE, 0xFF, 0xFF, 0xFF], i32::MAX),
+            (&[0xF0, 0xFD, 0xFF, 0xFF, 0xFF], i32::MIN + 1),
+            (&[0xFF], i32::MIN),
+        ];
+        for (bytes, value) in signed {
+            let mut r = Reader::new(bytes, 0);
+            assert_eq!(r.read_compressed_i32(), Ok(value), "{:02X?}", bytes);
+            assert_eq!(r.pos, bytes.len());
+        }
+
+        for bytes in [&[0xE0][..], &[0x80], &[0xC0, 0x00], &[0xF0, 0x00, 0x00]] {
+            assert!(Reader::new(bytes, 0).read_compressed_u32().is_err());
         }
     }
 
-    Ok(())
+    #[test]
+    fn decode_index_shifts_out_the_flag_from_27() {
+        let v24 = GlobalMetadata::parse(&metadata_file(24, V24_0_PAIRS, &[])).unwrap();
+        let v27 = GlobalMetadata::parse(&metadata_file(27, PAIRS, &[])).unwrap();
+        let method = 0x6000_000F;
+        assert_eq!(v24.decode_index(method), (Some(UsageKind::MethodDef), 15));
+        assert_eq!(v27.decode_index(method), (Some(UsageKind::MethodDef), 7));
+        let literal = 0xA000_0009;
+        assert_eq!(
+            v24.decode_index(literal),
+            (Some(UsageKind::StringLiteral), 9)
+        );
+        assert_eq!(
+            v27.decode_index(literal),
+            (Some(UsageKind::StringLiteral), 4)
+        );
+        assert_eq!(v27.decode_index(0x0000_0004), (None, 2));
+    }
+
+    #[test]
+    fn read_constant_before_29() {
+        let data = [
+            0x2A, 0x00, 0x00, 0x00, // 42
+            0x41, 0x00, // 'A'
+            0x02, 0x00, 0x00, 0x00, b'h', b'i', // "hi"
+            0xFF, 0xFF, 0xFF, 0xFF, // null string
+        ];
+        let metadata =
+            GlobalMetadata::parse(&metadata_file(27, PAIRS, &[(DEFAULT_VALUE_DATA, &data)]))
+                .unwrap();
+        let value = |index, element| metadata.default_value(index, element);
+        assert_eq!(value(0, ElementType::I4), Ok(ConstantValue::I4(42)));
+        assert_eq!(value(0, ElementType::U1), Ok(ConstantValue::U1(42)));
+        assert_eq!(value(4, ElementType::Char), Ok(ConstantValue::Char('A')));
+        assert_eq!(
+            value(6, ElementType::String),
+            Ok(ConstantValue::String("hi".to_string()))
+        );
+        assert_eq!(value(12, ElementType::String), Ok(ConstantValue::Null));
+        assert_eq!(value(-1, ElementType::I4), Ok(ConstantValue::Null));
+        assert_eq!(value(0, ElementType::Class), Ok(ConstantValue::Null));
+        assert_eq!(value(0, ElementType::GenericInst), Ok(ConstantValue::Null));
+        assert!(value(14, ElementType::I4).is_err());
+        assert!(value(0, ElementType::Void).is_err());
+    }
+
+    #[test]
+    fn read_constant_from_29() {
+        let data = [
+            0x54, // 42
+            0x03, // -2
+            0x80, 0x80, // 128
+            0x04, b'h', b'i', // "hi"
+            0x01, // null string
+        ];
+        let metadata =
+            GlobalMetadata::parse(&metadata_file(29, PAIRS, &[(DEFAULT_VALUE_DATA, &data)]))
+                .unwrap();
+        let value = |index, element| metadata.default_value(index, element);
+        assert_eq!(value(0, ElementType::I4), Ok(ConstantValue::I4(42)));
+        assert_eq!(value(1, ElementType::I4), Ok(ConstantValue::I4(-2)));
+        assert_eq!(value(2, ElementType::U4), Ok(ConstantValue::U4(128)));
+        assert_eq!(
+            value(4, ElementType::String),
+            Ok(ConstantValue::String("hi".to_string()))
+        );
+        assert_eq!(value(7, ElementType::String), Ok(ConstantValue::Null));
+    }
+
+    #[test]
+    fn attributes_decode_arguments_fields_and_properties() {
+        let mut property = [0; 20];
+        property[..4].copy_from_slice(&3i32.to_le_bytes());
+        let range = [0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00];
+        let blob = [
+            0x01, // one attribute
+            0x00, 0x00, 0x00, 0x00, // constructor: method 0
+            0x01, 0x01, 0x01, // one argument, field and property
+            0x08, 0x54, // (int)42
+            0x0E, 0x04, b'h', b'i', 0x00, // field 0 = "hi"
+            0x02, 0x01, 0x00, // property 0 = true
+        ];
+        let data = metadata_file(
+            29,
+            PAIRS,
+            &[
+                (STRINGS, b"Id\0Tag\0"),
+                (PROPERTIES, &property),
+                (METHODS, &[0; 32]),
+                (FIELDS, &[0; 12]),
+                (TYPE_DEFINITIONS, &[0; 88]),
+                (ATTRIBUTE_DATA, &blob),
+                (ATTRIBUTE_DATA_RANGES, &range),
+            ],
+        );
+        let metadata = GlobalMetadata::parse(&data).unwrap();
+        let attributes = metadata.attributes(0, &|_| None).unwrap();
+        assert_eq!(attributes.len(), 1);
+        let attribute = &attributes[0];
+        assert_eq!(attribute.constructor, 0);
+        assert!(matches!(
+            attribute.arguments[..],
+            [AttributeValue::Constant(ConstantValue::I4(42))]
+        ));
+        let named: Vec<_> = attribute
+            .named_arguments
+            .iter()
+            .map(|argument| match &argument.value {
+                AttributeValue::Constant(value) => (argument.name.as_str(), value.clone()),
+                _ => panic!("{} is not a constant", argument.name),
+            })
+            .collect();
+        assert_eq!(
+            named,
+            [
+                ("Id", ConstantValue::String("hi".to_string())),
+                ("Tag", ConstantValue::Boolean(true)),
+            ]
+        );
+    }
 }
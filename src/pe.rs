@@ -0,0 +1,178 @@
+use crate::utils::{read_u16, read_u32, read_u64};
+
+const DOS_MAGIC: u16 = 0x5A4D;
+const NT_SIGNATURE: u32 = 0x0000_4550;
+const OPTIONAL_HDR32_MAGIC: u16 = 0x10B;
+const OPTIONAL_HDR64_MAGIC: u16 = 0x20B;
+const SECTION_HEADER_SIZE: usize = 40;
+
+// Field offsets shared by PE32 and PE32+ optional headers.
+pub const OPT_SIZE_OF_INITIALIZED_DATA: usize = 8;
+pub const OPT_SIZE_OF_IMAGE: usize = 56;
+// Field offsets inside a section header.
+pub const SECTION_VIRTUAL_SIZE: usize = 8;
+pub const SECTION_SIZE_OF_RAW_DATA: usize = 16;
+
+const SCN_CNT_CODE: u32 = 0x0000_0020;
+const SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+const SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+pub const MACHINE_I386: u16 = 0x014C;
+pub const MACHINE_ARMNT: u16 = 0x01C4;
+pub const MACHINE_AMD64: u16 = 0x8664;
+pub const MACHINE_ARM64: u16 = 0xAA64;
+
+pub const DIRECTORY_RESOURCE: usize = 2;
+pub const DIRECTORY_EXCEPTION: usize = 3;
+pub const DIRECTORY_BASERELOC: usize = 5;
+
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+pub struct SectionHeader {
+    pub name: String,
+    pub virtual_size: u32,
+    pub virtual_address: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    pub characteristics: u32,
+}
+
+impl SectionHeader {
+    fn read(data: &[u8], offset: usize) -> Result<Self, String> {
+        let raw_name = data
+            .get(offset..offset + 8)
+            .ok_or("Section table out of bounds")?;
+        let name_len = raw_name.iter().position(|&b| b == 0).unwrap_or(8);
+        Ok(Self {
+            name: String::from_utf8_lossy(&raw_name[..name_len]).into_owned(),
+            virtual_size: read_u32(data, offset + 8)?,
+            virtual_address: read_u32(data, offset + 12)?,
+            size_of_raw_data: read_u32(data, offset + 16)?,
+            pointer_to_raw_data: read_u32(data, offset + 20)?,
+            characteristics: read_u32(data, offset + 36)?,
+        })
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.characteristics & (SCN_CNT_CODE | SCN_MEM_EXECUTE) != 0
+    }
+
+    pub fn has_initialized_data(&self) -> bool {
+        self.characteristics & SCN_CNT_INITIALIZED_DATA != 0
+    }
+
+    pub fn raw_range(&self, file_len: usize) -> (usize, usize) {
+        let start = (self.pointer_to_raw_data as usize).min(file_len);
+        let end = (start + self.size_of_raw_data as usize).min(file_len);
+        (start, end)
+    }
+}
+
+pub struct PeFile {
+    pub machine: u16,
+    pub is_64: bool,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub optional_header_offset: usize,
+    pub section_table_offset: usize,
+    pub data_directories: Vec<DataDirectory>,
+    pub sections: Vec<SectionHeader>,
+}
+
+impl PeFile {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if read_u16(data, 0)? != DOS_MAGIC {
+            return Err("Not a PE file: missing MZ signature".to_string());
+        }
+        let nt_offset = read_u32(data, 0x3C)? as usize;
+        if read_u32(data, nt_offset)? != NT_SIGNATURE {
+            return Err("Not a PE file: missing PE signature".to_string());
+        }
+
+        let file_header = nt_offset + 4;
+        let machine = read_u16(data, file_header)?;
+        let number_of_sections = read_u16(data, file_header + 2)? as usize;
+        let size_of_optional_header = read_u16(data, file_header + 16)? as usize;
+
+        let optional_header = file_header + 20;
+        let (is_64, image_base, dir_count_offset) = match read_u16(data, optional_header)? {
+            OPTIONAL_HDR32_MAGIC => (false, read_u32(data, optional_header + 28)? as u64, 92),
+            OPTIONAL_HDR64_MAGIC => (true, read_u64(data, optional_header + 24)?, 108),
+            magic => return Err(format!("Unknown optional header magic: 0x{:X}", magic)),
+        };
+
+        let section_alignment = read_u32(data, optional_header + 32)?;
+        let file_alignment = read_u32(data, optional_header + 36)?;
+
+        let dir_count = read_u32(data, optional_header + dir_count_offset)? as usize;
+        let dir_start = optional_header + dir_count_offset + 4;
+        let mut data_directories = Vec::with_capacity(dir_count.min(16));
+        for i in 0..dir_count.min(16) {
+            let offset = dir_start + i * 8;
+            if offset + 8 > optional_header + size_of_optional_header {
+                break;
+            }
+            data_directories.push(DataDirectory {
+                virtual_address: read_u32(data, offset)?,
+                size: read_u32(data, offset + 4)?,
+            });
+        }
+
+        let section_table = optional_header + size_of_optional_header;
+        let mut sections = Vec::with_capacity(number_of_sections);
+        for i in 0..number_of_sections {
+            sections.push(SectionHeader::read(
+                data,
+                section_table + i * SECTION_HEADER_SIZE,
+            )?);
+        }
+
+        Ok(Self {
+            machine,
+            is_64,
+            image_base,
+            section_alignment,
+            file_alignment,
+            optional_header_offset: optional_header,
+            section_table_offset: section_table,
+            data_directories,
+            sections,
+        })
+    }
+
+    pub fn machine_name(&self) -> &'static str {
+        match self.machine {
+            MACHINE_I386 => "x86",
+            MACHINE_ARMNT => "arm",
+            MACHINE_AMD64 => "x86_64",
+            MACHINE_ARM64 => "arm64",
+            _ => "unknown",
+        }
+    }
+
+    pub fn directory(&self, index: usize) -> Option<&DataDirectory> {
+        self.data_directories
+            .get(index)
+            .filter(|dir| dir.virtual_address != 0 && dir.size != 0)
+    }
+
+    // Resources, relocations and unwind info live in sections of their own
+    // and never hold the metadata blob, so those are skipped along with code.
+    pub fn is_searchable(&self, section: &SectionHeader) -> bool {
+        if section.is_code() || !section.has_initialized_data() {
+            return false;
+        }
+        ![DIRECTORY_RESOURCE, DIRECTORY_BASERELOC, DIRECTORY_EXCEPTION]
+            .iter()
+            .filter_map(|&index| self.directory(index))
+            .any(|dir| dir.virtual_address == section.virtual_address)
+    }
+
+    pub fn section_header_offset(&self, index: usize) -> usize {
+        self.section_table_offset + index * SECTION_HEADER_SIZE
+    }
+}
@@ -0,0 +1,140 @@
+use crate::cipher::DecryptConfig;
+use crate::extractor::Extractor;
+use crate::metadata::{self, GlobalMetadata, ParseOptions};
+use crate::repack::{self, Patch};
+use crate::strings::{self, StringsFormat};
+use crate::types::TypeTable;
+use crate::{dump, script};
+
+/// Collects the candidate blobs and selects `candidate`, or the best scoring
+/// one when not given.
+pub fn extract(
+    extractor: &mut Extractor,
+    config: &DecryptConfig,
+    candidate: Option<usize>,
+) -> Result<(), String> {
+    extractor
+        .process(config)
+        .map_err(|e| format!("Extraction failed: {}", e))?;
+    if let Some(index) = candidate {
+        extractor.select(index)?;
+    }
+    Ok(())
+}
+
+/// What `dump` makes of the decrypted blob beyond parsing it.
+#[derive(Clone, Copy, Default)]
+pub struct DumpOptions {
+    /// How the metadata is parsed.
+    pub parse: ParseOptions,
+    /// Resolve types against the binary even when no output needs them.
+    pub types: bool,
+    /// Write dump.cs and, when the types resolve, the script files.
+    pub files: bool,
+    /// Export the string literals in this format.
+    pub strings: Option<StringsFormat>,
+}
+
+/// The decrypted metadata and everything made from it.
+pub struct Dump {
+    /// The file to save: the decrypted blob, with its string literals
+    /// decrypted when the metadata was parsed with a scheme.
+    pub data: Vec<u8>,
+    /// The parsed metadata, or why it did not parse. Nothing below is made
+    /// when it failed or its magic is wrong.
+    pub metadata: Result<GlobalMetadata, String>,
+    /// The binary's types, when asked for or needed.
+    pub types: Option<Result<TypeTable, String>>,
+    /// The exported string literals.
+    pub strings: Option<String>,
+    /// File names and contents: dump.cs alone when the binary's types did
+    /// not resolve, as `types` reports.
+    pub files: Vec<(&'static str, String)>,
+}
+
+/// Parses `decrypted`, the blob `extractor` selected, and builds the outputs
+/// `options` ask for, resolving types against the extractor's binary.
+pub fn dump(extractor: &Extractor, decrypted: Vec<u8>, options: &DumpOptions) -> Dump {
+    let mut dump = Dump {
+        metadata: GlobalMetadata::parse_with(&decrypted, options.parse),
+        data: decrypted,
+        types: None,
+        strings: None,
+        files: Vec::new(),
+    };
+    let Ok(metadata) = &dump.metadata else {
+        return dump;
+    };
+    if !metadata.is_valid() {
+        return dump;
+    }
+    dump.data = metadata.write(&dump.data, metadata.literal_scheme);
+
+    let types = (options.types || options.files || options.strings.is_some())
+        .then(|| TypeTable::from_binary(metadata, extractor.get_file_data()));
+    let resolved = types.as_ref().and_then(|types| types.as_ref().ok());
+    if let Some(format) = options.strings {
+        let registration = resolved.and_then(TypeTable::registration);
+        dump.strings = Some(strings::export_strings(metadata, registration, format));
+    }
+    if options.files {
+        dump.files = match resolved {
+            Some(types) => {
+                let mut files = vec![("dump.cs", dump::dump_cs(metadata, types))];
+                files.extend(script::script_files(metadata, types));
+                files
+            }
+            None => {
+                let types = TypeTable::from_metadata(metadata);
+                vec![("dump.cs", dump::dump_cs(metadata, &types))]
+            }
+        };
+    }
+    dump.types = types;
+    dump
+}
+
+/// A binary with its blob replaced.
+pub struct Repack {
+    /// The patched binary.
+    pub binary: Vec<u8>,
+    /// What was changed in it.
+    pub patch: Patch,
+}
+
+/// Encrypts the decrypted `metadata` with `config` and writes it over the
+/// blob `extractor` selected, then checks that the patched binary extracts
+/// a blob at the same offset that decrypts back to `metadata`.
+pub fn repack(
+    extractor: &Extractor,
+    config: &DecryptConfig,
+    metadata: &[u8],
+) -> Result<Repack, String> {
+    if !metadata::has_valid_magic(metadata) {
+        return Err("The metadata is not a decrypted global-metadata.dat".to_string());
+    }
+    // XXTEA works on whole words; decryption hands the padding back as zeros.
+    let mut plain = metadata.to_vec();
+    plain.resize(plain.len().next_multiple_of(4), 0);
+
+    let location = extractor.get_location().ok_or("No blob to replace")?;
+    let old_size = extractor.get_valid_data().len();
+    let encrypted = config
+        .encrypt(&plain)
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    let mut binary = extractor.get_file_data().to_vec();
+    let patch = repack::patch(&mut binary, location, old_size, &encrypted)?;
+
+    let mut check = Extractor::from_bytes(extractor.get_source().to_string(), binary.clone());
+    check
+        .process(config)
+        .map_err(|e| format!("Patched binary did not re-extract: {}", e))?;
+    let round_trip = check.get_candidates().iter().any(|c| {
+        c.location.file_offset == patch.file_offset
+            && config.decrypt(&c.data).is_ok_and(|d| d == plain)
+    });
+    if !round_trip {
+        return Err("Patched blob does not decrypt back to the input metadata".to_string());
+    }
+    Ok(Repack { binary, patch })
+}
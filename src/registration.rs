@@ -0,0 +1,722 @@
+use crate::binary::Binary;
+use crate::csharp;
+use crate::metadata::{ElementType, GlobalMetadata, MetadataVersion};
+use crate::types::{Il2CppType, TypeKind};
+use crate::utils::read_u32;
+use std::collections::{HashMap, HashSet};
+
+// Il2CppMetadataRegistration is a run of count/pointer pairs. The field
+// offset and type size tables both have one entry per type definition, which
+// is what the search keys on.
+const REGISTRATION_GENERIC_INSTS_COUNT: usize = 2;
+const REGISTRATION_TYPES_COUNT: usize = 6;
+const REGISTRATION_METHOD_SPECS_COUNT: usize = 8;
+const REGISTRATION_FIELD_OFFSETS_COUNT: usize = 10;
+const REGISTRATION_TYPE_SIZES_COUNT: usize = 12;
+const REGISTRATION_USAGES_COUNT: usize = 14;
+const MAX_ENTRIES: u64 = 0x100_0000;
+
+// Generic arguments nest; anything deeper or wider than this is a bad
+// pointer.
+const MAX_DEPTH: usize = 16;
+const MAX_GENERIC_ARGUMENTS: usize = 64;
+
+const METHOD_SPEC_SIZE: usize = 12;
+
+const TYPE_VALUETYPE_BIT: u32 = 31;
+
+// Where the Il2CppType bitfield keeps the byref flag. After attrs:16 and
+// type:8 come num_mods and the flags: num_mods:6, byref, pinned up to 27.1;
+// num_mods:5, byref, pinned, valuetype from 27.2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TypeLayout {
+    Wide,
+    Narrow,
+}
+
+impl TypeLayout {
+    // Metadata 27 is the same for 27.0 through 27.2, so the binary's types
+    // decide: pinned is never set in the table, while 27.2's valuetype flag
+    // is on every value type.
+    fn detect(version: MetadataVersion, bitfields: &[u32]) -> Self {
+        match version.major {
+            ..27 => Self::Wide,
+            27 if !bitfields.iter().any(|b| b >> TYPE_VALUETYPE_BIT != 0) => Self::Wide,
+            _ => Self::Narrow,
+        }
+    }
+
+    // attrs, the element type and byref.
+    fn decode(self, bits: u32) -> (u16, u8, bool) {
+        let byref_bit = match self {
+            Self::Wide => 30,
+            Self::Narrow => 29,
+        };
+        (
+            bits as u16,
+            (bits >> 16) as u8,
+            (bits >> byref_bit) & 1 != 0,
+        )
+    }
+}
+
+/// Il2CppMethodSpec: a method definition with indices into the generic
+/// instantiations for its class and its own type arguments, -1 when absent.
+pub struct MethodSpec {
+    /// Index into the metadata's `methods`.
+    pub method_definition_index: i32,
+    /// The declaring class's type arguments, in `generic_insts`.
+    pub class_inst_index: i32,
+    /// The method's own type arguments, in `generic_insts`.
+    pub method_inst_index: i32,
+}
+
+/// Where a method's code is, in the three forms Il2CppDumper prints.
+#[derive(Clone, Copy)]
+pub struct CodeAddress {
+    /// Relative to the image base; the VA for ELF and Mach-O.
+    pub rva: u64,
+    /// The offset in the file.
+    pub offset: usize,
+    /// The address once loaded at the preferred base.
+    pub va: u64,
+}
+
+/// What the binary registers with the runtime, as far as the dumps need it:
+/// the Il2CppType table and generic instantiations from the
+/// MetadataRegistration, the metadata usage slots, and the code address of
+/// every method definition from the CodeRegistration.
+pub struct Registration {
+    /// 4 or 8, for the slice the tables are in.
+    pub pointer_size: usize,
+    /// The Il2CppType table, by type index.
+    pub types: Vec<Il2CppType>,
+    /// The type arguments of every generic instantiation.
+    pub generic_insts: Vec<Vec<Il2CppType>>,
+    /// Every generic method instance.
+    pub method_specs: Vec<MethodSpec>,
+    /// (slot address, encoded usage) for every metadata usage slot.
+    pub usages: Vec<(u64, u32)>,
+    /// Code address by method definition index.
+    pub method_pointers: HashMap<usize, CodeAddress>,
+}
+
+impl Registration {
+    /// Locates the registration of `metadata` in the binary `data`.
+    pub fn find(metadata: &GlobalMetadata, data: &[u8]) -> Result<Self, String> {
+        let binary = Binary::parse(data)?;
+        if let Some(error) = &binary.pointer_error {
+            return Err(error.clone());
+        }
+        let mut image = find_metadata_registration(&binary, data, metadata.type_definitions.len())
+            .ok_or("MetadataRegistration not found in binary")?;
+        image.layout = image.type_layout(metadata.version);
+        let types = image.read_types(metadata)?;
+        let method_pointers = if metadata.version >= MetadataVersion::V24_2 {
+            image.code_gen_module_pointers(metadata)
+        } else {
+            image.code_registration_pointers(metadata)
+        };
+        Ok(Self {
+            pointer_size: image.pointer_size,
+            types,
+            generic_insts: image.read_generic_insts(metadata),
+            method_specs: image.read_method_specs(),
+            usages: image.read_usages(metadata),
+            method_pointers: method_pointers.unwrap_or_default(),
+        })
+    }
+}
+
+// A view of the binary from the MetadataRegistration's slice.
+struct Image<'a> {
+    binary: &'a Binary,
+    data: &'a [u8],
+    slice: Option<&'a str>,
+    pointer_size: usize,
+    registration: usize,
+    layout: TypeLayout,
+}
+
+fn find_metadata_registration<'a>(
+    binary: &'a Binary,
+    data: &'a [u8],
+    type_definitions: usize,
+) -> Option<Image<'a>> {
+    let count = type_definitions as u64;
+    if count == 0 {
+        return None;
+    }
+    for section in binary.sections.iter().filter(|s| !s.executable) {
+        let mut image = Image {
+            binary,
+            data,
+            slice: section.slice.as_deref(),
+            pointer_size: section.pointer_size,
+            registration: 0,
+            layout: TypeLayout::Wide,
+        };
+        let ps = section.pointer_size;
+        let end = section.file_offset + section.raw(data).len();
+        let first = section.file_offset + REGISTRATION_FIELD_OFFSETS_COUNT * ps;
+        let mut offset = first.next_multiple_of(ps);
+        while offset + 4 * ps <= end {
+            if image.word(offset) == Some(count) && image.word(offset + 2 * ps) == Some(count) {
+                image.registration = offset - REGISTRATION_FIELD_OFFSETS_COUNT * ps;
+                if image.is_registration() {
+                    return Some(image);
+                }
+            }
+            offset += ps;
+        }
+    }
+    None
+}
+
+impl Image<'_> {
+    fn word(&self, offset: usize) -> Option<u64> {
+        self.binary
+            .read_pointer(self.data, offset, self.pointer_size)
+    }
+
+    fn word_u32(&self, offset: usize) -> Option<u32> {
+        read_u32(self.data, offset).ok()
+    }
+
+    // Where the pointer stored at `offset` points, as a file offset.
+    fn follow(&self, offset: usize) -> Option<usize> {
+        let pointer = self.word(offset)?;
+        self.binary.offset_for_pointer(pointer, self.slice)
+    }
+
+    // Addresses in the dumps are relative to the image base, as
+    // Il2CppDumper writes them.
+    fn address(&self, pointer: u64) -> u64 {
+        pointer.saturating_sub(self.binary.image_base)
+    }
+
+    fn is_executable(&self, pointer: u64) -> bool {
+        let Some(address) = pointer.checked_sub(self.binary.image_base) else {
+            return false;
+        };
+        self.binary.sections.iter().any(|s| {
+            s.executable
+                && s.slice.as_deref() == self.slice
+                && address >= s.address
+                && address - s.address < s.size as u64
+        })
+    }
+
+    fn field(&self, index: usize) -> usize {
+        self.registration + index * self.pointer_size
+    }
+
+    // A count field followed by a pointer to that many entries.
+    fn table(&self, count_field: usize) -> Option<(usize, usize)> {
+        let count = self
+            .word(self.field(count_field))
+            .filter(|&n| n < MAX_ENTRIES)?;
+        Some((count as usize, self.follow(self.field(count_field + 1))?))
+    }
+
+    fn is_registration(&self) -> bool {
+        self.table(REGISTRATION_TYPES_COUNT)
+            .is_some_and(|(count, _)| count > 0)
+            && self
+                .follow(self.field(REGISTRATION_FIELD_OFFSETS_COUNT + 1))
+                .is_some()
+            && self
+                .follow(self.field(REGISTRATION_TYPE_SIZES_COUNT + 1))
+                .is_some()
+    }
+
+    fn type_layout(&self, version: MetadataVersion) -> TypeLayout {
+        let bitfields: Vec<u32> = self
+            .table(REGISTRATION_TYPES_COUNT)
+            .map(|(count, table)| {
+                (0..count)
+                    .filter_map(|i| {
+                        let ty = self.follow(table + i * self.pointer_size)?;
+                        self.word_u32(ty + self.pointer_size)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        TypeLayout::detect(version, &bitfields)
+    }
+
+    fn read_types(&self, metadata: &GlobalMetadata) -> Result<Vec<Il2CppType>, String> {
+        let (count, table) = self
+            .table(REGISTRATION_TYPES_COUNT)
+            .ok_or("MetadataRegistration type table is out of bounds")?;
+        (0..count)
+            .map(|i| {
+                self.follow(table + i * self.pointer_size)
+                    .map(|offset| self.read_type(metadata, offset, 0))
+                    .ok_or_else(|| format!("Type {} points outside the binary", i))
+            })
+            .collect()
+    }
+
+    fn read_generic_insts(&self, metadata: &GlobalMetadata) -> Vec<Vec<Il2CppType>> {
+        let Some((count, table)) = self.table(REGISTRATION_GENERIC_INSTS_COUNT) else {
+            return Vec::new();
+        };
+        (0..count)
+            .map(|i| {
+                self.follow(table + i * self.pointer_size)
+                    .and_then(|inst| self.read_inst(metadata, inst, 0))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn read_method_specs(&self) -> Vec<MethodSpec> {
+        let Some((count, table)) = self.table(REGISTRATION_METHOD_SPECS_COUNT) else {
+            return Vec::new();
+        };
+        (0..count)
+            .map_while(|i| {
+                let at = table + i * METHOD_SPEC_SIZE;
+                Some(MethodSpec {
+                    method_definition_index: self.word_u32(at)? as i32,
+                    class_inst_index: self.word_u32(at + 4)? as i32,
+                    method_inst_index: self.word_u32(at + 8)? as i32,
+                })
+            })
+            .collect()
+    }
+
+    // Before 27 the metadata pairs each usage with a slot in the binary's
+    // list. In 27 the list holds the slots alone, each starting out with its
+    // encoded usage. From 29 the list is gone from the registration and
+    // the slots are only reachable from code.
+    fn read_usages(&self, metadata: &GlobalMetadata) -> Vec<(u64, u32)> {
+        if metadata.version.major >= 29 {
+            return Vec::new();
+        }
+        let Some((count, table)) = self.table(REGISTRATION_USAGES_COUNT) else {
+            return Vec::new();
+        };
+        let slot = |index: usize| {
+            (index < count)
+                .then(|| self.word(table + index * self.pointer_size))
+                .flatten()
+        };
+        if metadata.version.major < 27 {
+            return metadata
+                .metadata_usage_pairs
+                .iter()
+                .filter_map(|pair| {
+                    let pointer = slot(pair.destination_index as usize)?;
+                    Some((self.address(pointer), pair.encoded_source_index))
+                })
+                .collect();
+        }
+        (0..count)
+            .filter_map(|index| {
+                let pointer = slot(index)?;
+                let offset = self.binary.offset_for_pointer(pointer, self.slice)?;
+                let encoded = self.word_u32(offset)?;
+                Some((self.address(pointer), encoded))
+            })
+            .collect()
+    }
+
+    // Up to 24.1 the CodeRegistration starts with one flat method pointer
+    // table, indexed by the methods' own `method_index`.
+    fn code_registration_pointers(
+        &self,
+        metadata: &GlobalMetadata,
+    ) -> Option<HashMap<usize, CodeAddress>> {
+        let count = metadata
+            .methods
+            .iter()
+            .map(|m| m.method_index)
+            .max()
+            .filter(|&max| max >= 0)? as u64
+            + 1;
+        let table = self.find_pointer_table(count, |table| {
+            (0..count as usize).all(|i| {
+                self.word(table + i * self.pointer_size)
+                    .is_some_and(|p| p == 0 || self.is_executable(p))
+            })
+        })?;
+        Some(
+            metadata
+                .methods
+                .iter()
+                .enumerate()
+                .filter_map(|(index, method)| {
+                    let slot = usize::try_from(method.method_index).ok()?;
+                    let pointer = self.code_pointer(table + slot * self.pointer_size)?;
+                    Some((index, pointer))
+                })
+                .collect(),
+        )
+    }
+
+    // From 24.2 every image has an Il2CppCodeGenModule holding its name and
+    // the pointers of its methods in token order. The CodeRegistration
+    // lists them, one per image, which is what the search keys on.
+    fn code_gen_module_pointers(
+        &self,
+        metadata: &GlobalMetadata,
+    ) -> Option<HashMap<usize, CodeAddress>> {
+        let names: HashSet<&str> = metadata
+            .images
+            .iter()
+            .filter_map(|image| metadata.get_string(image.name_index))
+            .collect();
+        let count = metadata.images.len() as u64;
+        let modules = self.find_pointer_table(count, |table| {
+            (0..count as usize).all(|i| {
+                self.follow(table + i * self.pointer_size)
+                    .and_then(|module| self.module_name(module))
+                    .is_some_and(|name| names.contains(name))
+            })
+        })?;
+
+        let mut by_name = HashMap::new();
+        for i in 0..count as usize {
+            let module = self.follow(modules + i * self.pointer_size)?;
+            let name = self.module_name(module)?;
+            let pointer_count = self.word_u32(module + self.pointer_size)? as usize;
+            let Some(pointers) = self.follow(module + 2 * self.pointer_size) else {
+                continue;
+            };
+            by_name.insert(name, (pointer_count, pointers));
+        }
+
+        let mut method_pointers = HashMap::new();
+        for (index, method) in metadata.methods.iter().enumerate() {
+            let Some(name) = usize::try_from(method.declaring_type)
+                .ok()
+                .and_then(|type_index| metadata.type_image(type_index))
+                .and_then(|image| metadata.get_string(metadata.images[image].name_index))
+            else {
+                continue;
+            };
+            let Some(&(pointer_count, pointers)) = by_name.get(name) else {
+                continue;
+            };
+            let rid = (method.token & 0x00FF_FFFF) as usize;
+            if rid == 0 || rid > pointer_count {
+                continue;
+            }
+            if let Some(pointer) = self.code_pointer(pointers + (rid - 1) * self.pointer_size) {
+                method_pointers.insert(index, pointer);
+            }
+        }
+        Some(method_pointers)
+    }
+
+    // Thumb code pointers carry the mode in bit 0.
+    fn code_pointer(&self, offset: usize) -> Option<CodeAddress> {
+        let pointer = self.word(offset).filter(|&p| p != 0)? & !1;
+        Some(CodeAddress {
+            rva: self.address(pointer),
+            offset: self.binary.offset_for_pointer(pointer, self.slice)?,
+            va: pointer,
+        })
+    }
+
+    fn module_name(&self, module: usize) -> Option<&str> {
+        let start = self.follow(module)?;
+        let rest = self.data.get(start..)?;
+        let end = rest.iter().take(256).position(|&b| b == 0)?;
+        std::str::from_utf8(&rest[..end]).ok()
+    }
+
+    // A `count` word followed by a pointer to a table `accept` agrees with.
+    fn find_pointer_table(&self, count: u64, accept: impl Fn(usize) -> bool) -> Option<usize> {
+        if count == 0 {
+            return None;
+        }
+        let ps = self.pointer_size;
+        for section in self
+            .binary
+            .sections
+            .iter()
+            .filter(|s| !s.executable && s.slice.as_deref() == self.slice)
+        {
+            let end = section.file_offset + section.raw(self.data).len();
+            let mut offset = section.file_offset.next_multiple_of(ps);
+            while offset + 2 * ps <= end {
+                if self.word(offset) == Some(count)
+                    && let Some(table) = self.follow(offset + ps)
+                    && accept(table)
+                {
+                    return Some(table);
+                }
+                offset += ps;
+            }
+        }
+        None
+    }
+
+    // Il2CppType is a pointer-sized data union followed by the bitfield
+    // TypeLayout decodes.
+    fn read_type(&self, metadata: &GlobalMetadata, offset: usize, depth: usize) -> Il2CppType {
+        let value = self.word(offset).unwrap_or(0);
+        let bits = self.word_u32(offset + self.pointer_size).unwrap_or(0);
+        let (attrs, element, byref) = self.layout.decode(bits);
+        let kind = if depth >= MAX_DEPTH {
+            TypeKind::Unknown(element)
+        } else {
+            self.read_kind(metadata, element, value, depth)
+                .unwrap_or(TypeKind::Unknown(element))
+        };
+        Il2CppType { kind, attrs, byref }
+    }
+
+    fn read_kind(
+        &self,
+        metadata: &GlobalMetadata,
+        element: u8,
+        value: u64,
+        depth: usize,
+    ) -> Option<TypeKind> {
+        let inner = |pointer: u64| {
+            let offset = self.binary.offset_for_pointer(pointer, self.slice)?;
+            Some(Box::new(self.read_type(metadata, offset, depth + 1)))
+        };
+        let definition = |index: u64| {
+            usize::try_from(index)
+                .ok()
+                .filter(|&i| i < metadata.type_definitions.len())
+        };
+        Some(match ElementType::from_u8(element)? {
+            ElementType::Class | ElementType::ValueType => {
+                TypeKind::Definition(definition(value & 0xFFFF_FFFF)?)
+            }
+            ElementType::Ptr => TypeKind::Pointer(inner(value)?),
+            ElementType::SzArray => TypeKind::SzArray(inner(value)?),
+            ElementType::Array => {
+                let array = self.binary.offset_for_pointer(value, self.slice)?;
+                let rank = *self.data.get(array + self.pointer_size)?;
+                TypeKind::Array(inner(self.word(array)?)?, rank)
+            }
+            ElementType::Var | ElementType::MVar => {
+                TypeKind::GenericParameter((value & 0xFFFF_FFFF) as usize)
+            }
+            ElementType::GenericInst => {
+                // Il2CppGenericClass: the generic type definition (its index
+                // before 27, a pointer to its Il2CppType since), then the
+                // class instantiation.
+                let class = self.binary.offset_for_pointer(value, self.slice)?;
+                let index = if metadata.version.major >= 27 {
+                    match inner(self.word(class)?)?.kind {
+                        TypeKind::Definition(index) => index,
+                        _ => return None,
+                    }
+                } else {
+                    definition(self.word(class)? & 0xFFFF_FFFF)?
+                };
+                let inst = self.follow(class + self.pointer_size)?;
+                TypeKind::GenericInstance(index, self.read_inst(metadata, inst, depth)?)
+            }
+            primitive => {
+                csharp::keyword(primitive)?;
+                TypeKind::Primitive(primitive)
+            }
+        })
+    }
+
+    // Il2CppGenericInst: an argument count and a pointer to the arguments.
+    fn read_inst(
+        &self,
+        metadata: &GlobalMetadata,
+        inst: usize,
+        depth: usize,
+    ) -> Option<Vec<Il2CppType>> {
+        let argc = self.word_u32(inst)? as usize;
+        let argv = self.follow(inst + self.pointer_size)?;
+        (0..argc.min(MAX_GENERIC_ARGUMENTS))
+            .map(|i| {
+                let offset = self.follow(argv + i * self.pointer_size)?;
+                Some(self.read_type(metadata, offset, depth + 1))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csharp::TypeResolver;
+    use crate::testing::{self, MetadataBuilder, va};
+    use crate::types::TypeTable;
+
+    const CLASS: u32 = 0x12 << 16;
+    const VALUE_TYPE: u32 = 0x11 << 16;
+    const I4: u32 = 0x08 << 16;
+    const ARRAY: u32 = 0x14 << 16;
+    const GENERIC_INST: u32 = 0x15 << 16;
+    const SZ_ARRAY: u32 = 0x1D << 16;
+    const OUT: u32 = 0x0002;
+
+    #[test]
+    fn type_layout_follows_the_runtime_version() {
+        let version = |major| MetadataVersion { major, minor: 0 };
+        // `out int` as each runtime lays it out.
+        let v24 = OUT | I4 | 1 << 30;
+        let v27_2 = OUT | I4 | 1 << 29 | 1 << 31;
+        let v29 = v27_2;
+
+        let layout = TypeLayout::detect(version(24), &[v24]);
+        assert_eq!(layout, TypeLayout::Wide);
+        assert_eq!(layout.decode(v24), (OUT as u16, 0x08, true));
+
+        assert_eq!(TypeLayout::detect(version(27), &[v24]), TypeLayout::Wide);
+        let layout = TypeLayout::detect(version(27), &[CLASS, v27_2]);
+        assert_eq!(layout, TypeLayout::Narrow);
+        assert_eq!(layout.decode(v27_2), (OUT as u16, 0x08, true));
+        // Read the old way, the same word is not by reference.
+        assert!(!TypeLayout::Wide.decode(v27_2).2);
+
+        let layout = TypeLayout::detect(version(29), &[CLASS]);
+        assert_eq!(layout, TypeLayout::Narrow);
+        assert_eq!(layout.decode(v29), (OUT as u16, 0x08, true));
+    }
+
+    // A section image addressed as testing::pe loads it.
+    struct Section(Vec<u8>);
+
+    impl Section {
+        fn put(&mut self, at: usize, value: u64) {
+            self.0[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        }
+
+        fn put_type(&mut self, at: usize, data: u64, bits: u32) {
+            self.put(at, data);
+            self.0[at + 8..at + 12].copy_from_slice(&bits.to_le_bytes());
+        }
+    }
+
+    // Int32, Game.Player and List`1, with the type table: 0 int, 1 Player,
+    // 2 ref Player, 3 out int, 4 Player[], 5 int[,], 6 List<Player> and
+    // 7 List`1.
+    fn binary() -> (GlobalMetadata, Vec<u8>) {
+        let mut b = MetadataBuilder::new();
+        b.add_type("System", "Int32");
+        b.add_type("Game", "Player");
+        let list = b.add_type("System.Collections.Generic", "List`1");
+        b.type_definitions[list].byval_type_index = 7;
+        b.make_generic(list, false, &[("T", 0)]);
+        let metadata = b.build();
+
+        const REGISTRATION: usize = 0x40;
+        const TYPE_TABLE: usize = 0x100;
+        const TYPES: usize = 0x200;
+        const ARRAY_TYPE: usize = 0x300;
+        const GENERIC_CLASS: usize = 0x320;
+        const INST: usize = 0x340;
+        const ARGUMENTS: usize = 0x360;
+        const INSTS: usize = 0x380;
+        let ty = |i: usize| TYPES + i * 16;
+
+        let mut s = Section(vec![0x5A; 0x400]);
+        let tables = [
+            (REGISTRATION_GENERIC_INSTS_COUNT, 1, va(INSTS)),
+            (REGISTRATION_TYPES_COUNT, 8, va(TYPE_TABLE)),
+            (REGISTRATION_FIELD_OFFSETS_COUNT, 3, va(0x3C0)),
+            (REGISTRATION_TYPE_SIZES_COUNT, 3, va(0x3E0)),
+        ];
+        for i in 0..16 {
+            s.put(REGISTRATION + i * 8, 0);
+        }
+        for (field, count, pointer) in tables {
+            s.put(REGISTRATION + field * 8, count);
+            s.put(REGISTRATION + field * 8 + 8, pointer);
+        }
+        for i in 0..8 {
+            s.put(TYPE_TABLE + i * 8, va(ty(i)));
+        }
+        s.put_type(ty(0), 0, VALUE_TYPE | 1 << 31);
+        s.put_type(ty(1), 1, CLASS);
+        s.put_type(ty(2), 1, CLASS | 1 << 29);
+        s.put_type(ty(3), 0, OUT | I4 | 1 << 29 | 1 << 31);
+        s.put_type(ty(4), va(ty(1)), SZ_ARRAY);
+        s.put_type(ty(5), va(ARRAY_TYPE), ARRAY);
+        s.put_type(ty(6), va(GENERIC_CLASS), GENERIC_INST);
+        s.put_type(ty(7), 2, CLASS);
+        s.put(ARRAY_TYPE, va(ty(0)));
+        s.0[ARRAY_TYPE + 8] = 2;
+        s.put(GENERIC_CLASS, va(ty(7)));
+        s.put(GENERIC_CLASS + 8, va(INST));
+        s.put(INST, 1);
+        s.put(INST + 8, va(ARGUMENTS));
+        s.put(ARGUMENTS, va(ty(1)));
+        s.put(INSTS, va(INST));
+        (metadata, testing::pe(&s.0, s.0.len()))
+    }
+
+    #[test]
+    fn metadata_registration_is_found_and_read() {
+        let (metadata, data) = binary();
+        let registration = Registration::find(&metadata, &data).unwrap();
+        assert_eq!(registration.pointer_size, 8);
+        assert_eq!(registration.types.len(), 8);
+        let flags: Vec<_> = registration
+            .types
+            .iter()
+            .map(|ty| (ty.attrs, ty.byref))
+            .collect();
+        assert_eq!(
+            flags,
+            [
+                (0, false),
+                (0, false),
+                (0, true),
+                (OUT as u16, true),
+                (0, false),
+                (0, false),
+                (0, false),
+                (0, false),
+            ]
+        );
+        assert_eq!(registration.generic_insts.len(), 1);
+        assert!(matches!(
+            registration.generic_insts[0][..],
+            [Il2CppType {
+                kind: TypeKind::Definition(1),
+                ..
+            }]
+        ));
+
+        let types = TypeTable::from_binary(&metadata, &data).unwrap();
+        let names: Vec<_> = (0..8)
+            .map(|i| {
+                format!(
+                    "{}{}",
+                    types.parameter_modifier(&metadata, i),
+                    types.type_name(&metadata, i)
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "int",
+                "Game.Player",
+                "ref Game.Player",
+                "out int",
+                "Game.Player[]",
+                "int[,]",
+                "System.Collections.Generic.List<Game.Player>",
+                "System.Collections.Generic.List<T>",
+            ]
+        );
+    }
+
+    #[test]
+    fn section_without_a_registration_is_rejected() {
+        let (metadata, _) = binary();
+        let data = testing::pe(&[0x5A; 0x400], 0x400);
+        assert_eq!(
+            Registration::find(&metadata, &data).err(),
+            Some("MetadataRegistration not found in binary".to_string())
+        );
+    }
+}
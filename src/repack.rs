@@ -0,0 +1,267 @@
+use crate::extractor::{BlobLocation, Boundary, HEAD_PATTERN};
+use crate::pe::{self, PeFile};
+use crate::utils::{read_u32, write_u32};
+
+/// What `patch` changed.
+pub struct Patch {
+    /// Where the blob starts in the file.
+    pub file_offset: usize,
+    /// The length of the blob it replaced.
+    pub old_size: usize,
+    /// The length of the new blob.
+    pub new_size: usize,
+    /// The section that grew to fit it, and by how much.
+    pub grown: Option<String>,
+}
+
+/// Writes `ciphertext` over the blob at `location`. A shorter blob is zero
+/// padded up to the old end so the zero-run boundary still finds it; a longer
+/// one needs the enclosing section grown, which only PE files support.
+pub fn patch(
+    data: &mut Vec<u8>,
+    location: &BlobLocation,
+    old_size: usize,
+    ciphertext: &[u8],
+) -> Result<Patch, String> {
+    if matches!(location.boundary, Boundary::Unvalidated) {
+        return Err(
+            "The original blob did not decrypt with this key, so its size is unknown".to_string(),
+        );
+    }
+    let start = location.file_offset;
+    let old_end = start + old_size;
+    let new_end = start + ciphertext.len();
+    if old_end > data.len() {
+        return Err("Blob location is outside the binary".to_string());
+    }
+
+    let grown = if new_end > old_end {
+        Some(grow_pe_section(data, old_end, new_end).map_err(|e| {
+            format!(
+                "New metadata is {} bytes but the slot holds {}: {}",
+                ciphertext.len(),
+                old_size,
+                e
+            )
+        })?)
+    } else {
+        None
+    };
+
+    data[start..new_end].copy_from_slice(ciphertext);
+    if old_end > new_end {
+        data[new_end..old_end].fill(0);
+    }
+    if matches!(location.boundary, Boundary::SizeField) {
+        let size_field = start - HEAD_PATTERN.len() - 4;
+        write_u32(data, size_field, ciphertext.len() as u32)?;
+    }
+
+    Ok(Patch {
+        file_offset: start,
+        old_size,
+        new_size: ciphertext.len(),
+        grown,
+    })
+}
+
+// The blob has to end where the section's virtual size does, so that only
+// file alignment padding follows it; zeros inside the virtual size may be
+// live data. It can then spill into that padding, or, for the last section
+// in the file, the file is extended and the image size bumped to match.
+fn grow_pe_section(data: &mut Vec<u8>, old_end: usize, new_end: usize) -> Result<String, String> {
+    if !data.starts_with(b"MZ") {
+        return Err("growing a section is only supported for PE files".to_string());
+    }
+    let pe = PeFile::parse(data)?;
+    let (index, section) = pe
+        .sections
+        .iter()
+        .enumerate()
+        .find(|(_, s)| {
+            let (start, end) = s.raw_range(data.len());
+            old_end > start && old_end <= end
+        })
+        .ok_or("the blob is not inside a section")?;
+    let (raw_start, raw_end) = section.raw_range(data.len());
+
+    if old_end < raw_start + section.virtual_size as usize
+        || data[old_end..raw_end].iter().any(|&b| b != 0)
+    {
+        return Err(format!(
+            "the blob is not at the end of {}, so it cannot grow",
+            section.name
+        ));
+    }
+
+    let new_virtual_size = (new_end - raw_start) as u32;
+    let next_rva = pe
+        .sections
+        .iter()
+        .map(|s| s.virtual_address)
+        .filter(|&rva| rva > section.virtual_address)
+        .min();
+    if let Some(next) = next_rva
+        && section.virtual_address as u64 + new_virtual_size as u64 > next as u64
+    {
+        return Err(format!(
+            "{} would overlap the next section in memory",
+            section.name
+        ));
+    }
+
+    let header = pe.section_header_offset(index);
+    let old_virtual_size = section.virtual_size;
+    if new_end > raw_end {
+        if raw_end != data.len() {
+            return Err(format!(
+                "{} is followed by other data in the file",
+                section.name
+            ));
+        }
+        let old_raw_size = section.size_of_raw_data;
+        let new_raw_size = align(new_virtual_size, pe.file_alignment);
+        data.resize(raw_start + new_raw_size as usize, 0);
+        write_u32(data, header + pe::SECTION_SIZE_OF_RAW_DATA, new_raw_size)?;
+
+        let image_end = align(
+            section.virtual_address + new_virtual_size,
+            pe.section_alignment,
+        );
+        let size_of_image = pe.optional_header_offset + pe::OPT_SIZE_OF_IMAGE;
+        let current = read_u32(data, size_of_image)?;
+        write_u32(data, size_of_image, current.max(image_end))?;
+
+        let initialized = pe.optional_header_offset + pe::OPT_SIZE_OF_INITIALIZED_DATA;
+        let current = read_u32(data, initialized)?;
+        write_u32(
+            data,
+            initialized,
+            current.wrapping_add(new_raw_size - old_raw_size),
+        )?;
+    }
+    write_u32(
+        data,
+        header + pe::SECTION_VIRTUAL_SIZE,
+        old_virtual_size.max(new_virtual_size),
+    )?;
+
+    Ok(format!(
+        "{} virtual size 0x{:X} -> 0x{:X}",
+        section.name,
+        old_virtual_size,
+        old_virtual_size.max(new_virtual_size)
+    ))
+}
+
+fn align(value: u32, alignment: u32) -> u32 {
+    if alignment == 0 {
+        return value;
+    }
+    value.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::DecryptConfig;
+
+    use crate::testing::{self, SECTION_OFFSET as SECTION_START};
+
+    // Where the blob's ciphertext starts: after a size field and the marker.
+    const BLOB_START: usize = SECTION_START + 0x40 + 4 + 10;
+
+    // A PE32+ file with one .data section at the end of the file, holding a
+    // size-prefixed blob of `blob_size` bytes. The section's virtual size
+    // ends `tail` bytes after the blob.
+    fn pe(blob_size: usize, tail: usize) -> Vec<u8> {
+        let blob = BLOB_START - SECTION_START;
+        let mut section = vec![0; 0x200];
+        section[..0x40].fill(0x11);
+        section[blob - 14..blob - 10].copy_from_slice(&(blob_size as u32).to_le_bytes());
+        section[blob - 10..blob].copy_from_slice(HEAD_PATTERN);
+        section[blob..blob + blob_size].fill(0xAA);
+        testing::pe(&section, blob + blob_size + tail)
+    }
+
+    fn location(boundary: Boundary) -> BlobLocation {
+        BlobLocation {
+            section: ".data".to_string(),
+            address: 0,
+            file_offset: BLOB_START,
+            slice: None,
+            boundary,
+        }
+    }
+
+    #[test]
+    fn shorter_blob_is_zero_padded() {
+        let mut data = pe(64, 0);
+        let original = data.clone();
+        let result = patch(&mut data, &location(Boundary::ZeroRun(0)), 64, &[0xBB; 32]).unwrap();
+        assert_eq!((result.old_size, result.new_size), (64, 32));
+        assert!(result.grown.is_none());
+        assert_eq!(data.len(), original.len());
+        assert!(data[BLOB_START..BLOB_START + 32].iter().all(|&b| b == 0xBB));
+        assert!(
+            data[BLOB_START + 32..BLOB_START + 64]
+                .iter()
+                .all(|&b| b == 0)
+        );
+        assert_eq!(
+            data[..BLOB_START - 4 - HEAD_PATTERN.len()],
+            original[..BLOB_START - 4 - HEAD_PATTERN.len()]
+        );
+    }
+
+    #[test]
+    fn size_field_is_updated() {
+        let mut data = pe(64, 0);
+        patch(&mut data, &location(Boundary::SizeField), 64, &[0xBB; 48]).unwrap();
+        let size_field = BLOB_START - HEAD_PATTERN.len() - 4;
+        assert_eq!(read_u32(&data, size_field), Ok(48));
+    }
+
+    #[test]
+    fn growth_is_refused_when_data_follows_the_blob() {
+        let mut data = pe(64, 0);
+        data[BLOB_START + 64] = 0x01;
+        let result = patch(&mut data, &location(Boundary::SizeField), 64, &[0xBB; 96]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn growth_is_refused_into_zeros_inside_the_virtual_size() {
+        let mut data = pe(64, 32);
+        let original = data.clone();
+        let result = patch(&mut data, &location(Boundary::SizeField), 64, &[0xBB; 96]);
+        assert!(result.is_err());
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn growth_spills_into_file_alignment_padding() {
+        let mut data = pe(64, 0);
+        let original_len = data.len();
+        let result = patch(&mut data, &location(Boundary::SizeField), 64, &[0xBB; 96]).unwrap();
+        assert!(result.grown.is_some());
+        assert_eq!(data.len(), original_len);
+        let pe = PeFile::parse(&data).unwrap();
+        let expected = (BLOB_START - SECTION_START + 96) as u32;
+        assert_eq!(pe.sections[0].virtual_size, expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        let config = DecryptConfig::default();
+        let plain: Vec<u8> = (0..200u8).collect();
+        let ciphertext = config.encrypt(&plain).unwrap();
+        let mut data = pe(256, 0);
+        let result = patch(&mut data, &location(Boundary::SizeField), 256, &ciphertext).unwrap();
+        let size_field = BLOB_START - HEAD_PATTERN.len() - 4;
+        let size = read_u32(&data, size_field).unwrap() as usize;
+        assert_eq!(size, result.new_size);
+        let blob = &data[result.file_offset..result.file_offset + size];
+        assert_eq!(config.decrypt(blob).unwrap(), plain);
+    }
+}
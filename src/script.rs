@@ -0,0 +1,318 @@
+use crate::csharp::{self, TypeResolver};
+use crate::header::Header;
+use crate::metadata::{GlobalMetadata, UsageKind};
+use crate::types::{TypeKind, TypeTable};
+use crate::utils::json_string;
+use std::collections::BTreeSet;
+
+/// The files Il2CppDumper writes next to dump.cs, in its formats, so its
+/// IDA and Ghidra scripts can apply them: script.json with method addresses,
+/// names and C signatures plus the metadata usage slots, the il2cpp.h those
+/// signatures refer to, and stringliteral.json.
+pub fn script_files(metadata: &GlobalMetadata, types: &TypeTable) -> Vec<(&'static str, String)> {
+    let mut header = Header::new(metadata, types);
+    let mut methods = Vec::new();
+    let mut strings = Vec::new();
+    let mut usages = Vec::new();
+    let mut usage_methods = Vec::new();
+    let mut literals = Vec::new();
+    let mut addresses = BTreeSet::new();
+
+    if let Some(registration) = types.registration() {
+        let mut pointers: Vec<_> = registration.method_pointers.iter().collect();
+        pointers.sort_by_key(|&(&index, _)| index);
+        for (&method_index, address) in pointers {
+            let Some(signature) = header.method_signature(method_index) else {
+                continue;
+            };
+            methods.push(format!(
+                "{{\"Address\": {}, \"Name\": {}, \"Signature\": {}, \"TypeSignature\": {}}}",
+                address.rva,
+                json_string(&method_name(metadata, method_index, "$$")),
+                json_string(&signature),
+                json_string(&header.type_signature(method_index).unwrap_or_default())
+            ));
+            addresses.insert(address.rva);
+        }
+
+        for &(address, encoded) in &registration.usages {
+            let (kind, index) = metadata.decode_index(encoded);
+            match kind {
+                Some(UsageKind::TypeInfo) => {
+                    let Some(ty) = types.resolve(index as i32) else {
+                        continue;
+                    };
+                    usages.push(usage(
+                        address,
+                        &format!("{}_TypeInfo", types.type_name(metadata, index as i32)),
+                        Some(&format!("{}_c*", header.struct_name(&ty))),
+                    ));
+                }
+                Some(UsageKind::Il2CppType) => {
+                    usages.push(usage(
+                        address,
+                        &format!("{}_var", types.type_name(metadata, index as i32)),
+                        Some("Il2CppType*"),
+                    ));
+                }
+                Some(UsageKind::FieldInfo) => {
+                    let Some(name) = field_ref_name(metadata, types, index as usize) else {
+                        continue;
+                    };
+                    usages.push(usage(address, &format!("Field${}", name), None));
+                }
+                Some(UsageKind::StringLiteral) => {
+                    let Some(value) = metadata.string_literals.get(index as usize) else {
+                        continue;
+                    };
+                    strings.push(format!(
+                        "{{\"Address\": {}, \"Value\": {}}}",
+                        address,
+                        json_string(value)
+                    ));
+                    literals.push(format!(
+                        "{{\"value\": {}, \"address\": \"0x{:X}\"}}",
+                        json_string(value),
+                        address
+                    ));
+                }
+                Some(UsageKind::MethodDef) => {
+                    let method_index = index as usize;
+                    if method_index >= metadata.methods.len() {
+                        continue;
+                    }
+                    let method_address = registration
+                        .method_pointers
+                        .get(&method_index)
+                        .map_or(0, |a| a.rva);
+                    usage_methods.push(format!(
+                        "{{\"Address\": {}, \"Name\": {}, \"MethodAddress\": {}}}",
+                        address,
+                        json_string(&format!(
+                            "Method${}()",
+                            method_name(metadata, method_index, ".")
+                        )),
+                        method_address
+                    ));
+                }
+                Some(UsageKind::MethodRef) => {
+                    let Some(name) = method_spec_name(metadata, types, index as usize) else {
+                        continue;
+                    };
+                    usage_methods.push(format!(
+                        "{{\"Address\": {}, \"Name\": {}, \"MethodAddress\": 0}}",
+                        address,
+                        json_string(&format!("Method${}()", name))
+                    ));
+                }
+                None => {}
+            }
+        }
+    }
+
+    let addresses: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+    let script = format!(
+        "{{\n  \"ScriptMethod\": {},\n  \"ScriptString\": {},\n  \"ScriptMetadata\": {},\n  \"ScriptMetadataMethod\": {},\n  \"Addresses\": {}\n}}\n",
+        array(&methods, "  "),
+        array(&strings, "  "),
+        array(&usages, "  "),
+        array(&usage_methods, "  "),
+        array(&addresses, "  ")
+    );
+    vec![
+        ("script.json", script),
+        ("il2cpp.h", header.finish()),
+        ("stringliteral.json", array(&literals, "") + "\n"),
+    ]
+}
+
+// One entry per line, nested `indent` deep.
+fn array(entries: &[String], indent: &str) -> String {
+    if entries.is_empty() {
+        return "[]".to_string();
+    }
+    let separator = format!(",\n{}  ", indent);
+    format!("[\n{}  {}\n{}]", indent, entries.join(&separator), indent)
+}
+
+fn usage(address: u64, name: &str, signature: Option<&str>) -> String {
+    format!(
+        "{{\"Address\": {}, \"Name\": {}, \"Signature\": {}}}",
+        address,
+        json_string(name),
+        signature.map_or("null".to_string(), json_string)
+    )
+}
+
+// `Game.Player$$Damage`, Il2CppDumper's name for a method's code, or
+// `Game.Player.Damage` for the method itself.
+fn method_name(metadata: &GlobalMetadata, method_index: usize, separator: &str) -> String {
+    let method = &metadata.methods[method_index];
+    let owner = usize::try_from(method.declaring_type)
+        .ok()
+        .filter(|&index| index < metadata.type_definitions.len())
+        .map(|index| csharp::full_type_name(metadata, index))
+        .unwrap_or_default();
+    format!(
+        "{}{}{}",
+        owner,
+        separator,
+        metadata.method_name(method).unwrap_or("")
+    )
+}
+
+// `Game.Pool<int>.value` for a field reference, which names the field of a
+// type by its index in that type.
+fn field_ref_name(metadata: &GlobalMetadata, types: &TypeTable, index: usize) -> Option<String> {
+    let field_ref = metadata.field_refs.get(index)?;
+    let type_index = match types.resolve(field_ref.type_index)?.kind {
+        TypeKind::Definition(index) | TypeKind::GenericInstance(index, _) => index,
+        _ => return None,
+    };
+    let type_def = &metadata.type_definitions[type_index];
+    let field = metadata
+        .type_fields(type_def)
+        .get(usize::try_from(field_ref.field_index).ok()?)?;
+    Some(format!(
+        "{}.{}",
+        types.type_name(metadata, field_ref.type_index),
+        metadata.field_name(field).unwrap_or("")
+    ))
+}
+
+// `Game.Pool<int>.Take<string>` for a generic method instance.
+fn method_spec_name(metadata: &GlobalMetadata, types: &TypeTable, index: usize) -> Option<String> {
+    let registration = types.registration()?;
+    let spec = registration.method_specs.get(index)?;
+    let method = metadata
+        .methods
+        .get(usize::try_from(spec.method_definition_index).ok()?)?;
+    let type_index = usize::try_from(method.declaring_type)
+        .ok()
+        .filter(|&index| index < metadata.type_definitions.len())?;
+    let arguments = |inst: i32| {
+        let inst = registration
+            .generic_insts
+            .get(usize::try_from(inst).ok()?)?;
+        Some(
+            inst.iter()
+                .map(|ty| types.name(metadata, ty))
+                .collect::<Vec<_>>(),
+        )
+    };
+    let owner = match arguments(spec.class_inst_index) {
+        Some(arguments) => csharp::generic_instance_name(metadata, type_index, &arguments),
+        None => csharp::full_type_name(metadata, type_index),
+    };
+    let mut name = format!("{}.{}", owner, metadata.method_name(method).unwrap_or(""));
+    if let Some(arguments) = arguments(spec.method_inst_index) {
+        name += &format!("<{}>", arguments.join(", "));
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::FieldRef;
+    use crate::registration::CodeAddress;
+    use crate::testing::{MetadataBuilder, il2cpp_type, registration};
+
+    const INT: i32 = 0;
+
+    fn address(rva: u64) -> CodeAddress {
+        CodeAddress {
+            rva,
+            offset: rva as usize,
+            va: 0x1_8000_0000 + rva,
+        }
+    }
+
+    // A usage slot as 27 and later encode it.
+    fn usage(kind: u32, index: u32) -> u32 {
+        kind << 29 | index << 1 | 1
+    }
+
+    #[test]
+    fn script_json_lists_methods_and_usages() {
+        let mut b = MetadataBuilder::new();
+        let int = b.add_type("System", "Int32");
+        b.type_definitions[int].bitfield = 1;
+        let player = b.add_type("Game", "Player");
+        b.add_field(player, "hp", INT);
+        let damage = b.add_method(player, "Damage", INT);
+        b.add_parameter(damage, "amount", INT);
+        let create = b.add_method(player, "Create", player as i32);
+        b.methods[create].flags = 0x16;
+        let mut metadata = b.build();
+        metadata.string_literals = vec!["Hello \"world\"".to_string()];
+        metadata.field_refs = vec![FieldRef {
+            type_index: player as i32,
+            field_index: 0,
+        }];
+
+        let mut registration = registration(
+            (0..metadata.type_definitions.len())
+                .map(|index| il2cpp_type(TypeKind::Definition(index)))
+                .collect(),
+        );
+        registration.method_pointers.insert(create, address(0x2000));
+        registration.method_pointers.insert(damage, address(0x1A2B));
+        registration.usages = vec![
+            (0x5000, usage(1, player as u32)),
+            (0x5008, usage(2, INT as u32)),
+            (0x5010, usage(4, 0)),
+            (0x5018, usage(5, 0)),
+            (0x5020, usage(3, damage as u32)),
+        ];
+        let types = TypeTable::from_registration(&metadata, registration);
+
+        let files = script_files(&metadata, &types);
+        let names: Vec<_> = files.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["script.json", "il2cpp.h", "stringliteral.json"]);
+        assert_eq!(
+            files[0].1,
+            r#"{
+  "ScriptMethod": [
+    {"Address": 6699, "Name": "Game.Player$$Damage", "Signature": "int32_t Game_Player__Damage (Game_Player_o* __this, int32_t amount, const MethodInfo* method);", "TypeSignature": "iiii"},
+    {"Address": 8192, "Name": "Game.Player$$Create", "Signature": "Game_Player_o* Game_Player__Create (const MethodInfo* method);", "TypeSignature": "ii"}
+  ],
+  "ScriptString": [
+    {"Address": 20504, "Value": "Hello \"world\""}
+  ],
+  "ScriptMetadata": [
+    {"Address": 20480, "Name": "Game.Player_TypeInfo", "Signature": "Game_Player_c*"},
+    {"Address": 20488, "Name": "int_var", "Signature": "Il2CppType*"},
+    {"Address": 20496, "Name": "Field$Game.Player.hp", "Signature": null}
+  ],
+  "ScriptMetadataMethod": [
+    {"Address": 20512, "Name": "Method$Game.Player.Damage()", "MethodAddress": 6699}
+  ],
+  "Addresses": [
+    6699,
+    8192
+  ]
+}
+"#
+        );
+        assert_eq!(
+            files[2].1,
+            "[\n  {\"value\": \"Hello \\\"world\\\"\", \"address\": \"0x5018\"}\n]\n"
+        );
+    }
+
+    #[test]
+    fn script_json_without_a_registration_is_empty() {
+        let mut b = MetadataBuilder::new();
+        b.add_type("Game", "Player");
+        let metadata = b.build();
+        let types = TypeTable::from_metadata(&metadata);
+        let files = script_files(&metadata, &types);
+        assert_eq!(
+            files[0].1,
+            "{\n  \"ScriptMethod\": [],\n  \"ScriptString\": [],\n  \"ScriptMetadata\": [],\n  \
+             \"ScriptMetadataMethod\": [],\n  \"Addresses\": []\n}\n"
+        );
+        assert_eq!(files[2].1, "[]\n");
+    }
+}
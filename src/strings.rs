@@ -0,0 +1,127 @@
+use crate::metadata::{GlobalMetadata, UsageKind};
+use crate::registration::Registration;
+use crate::utils::json_string;
+use std::collections::HashMap;
+
+/// The formats string literals can be exported in.
+#[derive(Clone, Copy)]
+pub enum StringsFormat {
+    /// One JSON array of records.
+    Json,
+    /// A header row, then one row per literal.
+    Csv,
+    /// One JSON record per line.
+    JsonLines,
+}
+
+impl StringsFormat {
+    /// The format a `--strings-format` value names.
+    pub fn by_name(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "ndjson" => Ok(Self::JsonLines),
+            _ => Err(format!(
+                "Unknown strings format: {} (expected json, csv or jsonl)",
+                name
+            )),
+        }
+    }
+
+    /// Picked from the output's extension when not given, JSON otherwise.
+    pub fn for_path(path: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::by_name(&extension).unwrap_or(Self::Json)
+    }
+}
+
+/// Every string literal with where its bytes are and the metadata usage
+/// slots that load it. Up to 24.5 the slots are numbered by the metadata's
+/// usage pairs. From 27 only the binary knows them, so they are the slot
+/// addresses from `registration`; the lists are empty without one, and from
+/// 29, whose registration no longer lists the slots.
+pub fn export_strings(
+    metadata: &GlobalMetadata,
+    registration: Option<&Registration>,
+    format: StringsFormat,
+) -> String {
+    let mut usages: HashMap<u32, Vec<u64>> = HashMap::new();
+    let mut add = |slot: u64, encoded: u32| {
+        if let (Some(UsageKind::StringLiteral), index) = metadata.decode_index(encoded) {
+            usages.entry(index).or_default().push(slot);
+        }
+    };
+    match registration {
+        Some(registration) if metadata.version.major >= 27 => {
+            for &(address, encoded) in &registration.usages {
+                add(address, encoded);
+            }
+        }
+        _ => {
+            for pair in &metadata.metadata_usage_pairs {
+                add(pair.destination_index as u64, pair.encoded_source_index);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    if let StringsFormat::Csv = format {
+        out += "index,offset,length,value,usages\n";
+    }
+    let count = metadata.string_literal_infos.len();
+    for (index, info) in metadata.string_literal_infos.iter().enumerate() {
+        let value = metadata
+            .string_literals
+            .get(index)
+            .map(String::as_str)
+            .unwrap_or("");
+        let slots = usages
+            .get(&(index as u32))
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+        let slots: Vec<String> = slots.iter().map(|slot| slot.to_string()).collect();
+        match format {
+            StringsFormat::Csv => {
+                out += &format!(
+                    "{},{},{},{},{}\n",
+                    index,
+                    info.offset,
+                    info.length,
+                    csv_field(value),
+                    slots.join(";")
+                );
+            }
+            StringsFormat::Json | StringsFormat::JsonLines => {
+                let record = format!(
+                    "{{\"index\": {}, \"offset\": {}, \"length\": {}, \"value\": {}, \"usages\": [{}]}}",
+                    index,
+                    info.offset,
+                    info.length,
+                    json_string(value),
+                    slots.join(", ")
+                );
+                out += &match format {
+                    StringsFormat::JsonLines => format!("{}\n", record),
+                    _ if index == 0 => format!("[\n  {}", record),
+                    _ => format!(",\n  {}", record),
+                };
+            }
+        }
+    }
+    if let StringsFormat::Json = format {
+        out += if count == 0 { "[]\n" } else { "\n]\n" };
+    }
+    out
+}
+
+// Quoted when it holds a separator, quote or line break, with quotes doubled.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,583 @@
+//! Builders for the synthetic binaries and metadata files the unit tests
+//! run against.
+
+use crate::metadata::{
+    EXPECTED_MAGIC, EventDefinition, FieldDefaultValue, FieldDefinition, GenericContainer,
+    GenericParameter, GlobalMetadata, MethodDefinition, ParameterDefaultValue, ParameterDefinition,
+    PropertyDefinition, TypeDefinition,
+};
+use crate::registration::Registration;
+use crate::types::{Il2CppType, TypeKind};
+use std::collections::HashMap;
+
+/// Where the single section of [`pe`] starts in the file.
+pub const SECTION_OFFSET: usize = 0x200;
+/// The section's RVA.
+pub const SECTION_RVA: u64 = 0x1000;
+/// The preferred load address.
+pub const IMAGE_BASE: u64 = 0x1_8000_0000;
+const FILE_ALIGNMENT: usize = 0x200;
+
+/// A PE32+ file with one writable `.data` section holding `section`, padded
+/// to the file alignment. The section is `virtual_size` bytes once loaded.
+pub fn pe(section: &[u8], virtual_size: usize) -> Vec<u8> {
+    let raw_size = section.len().div_ceil(FILE_ALIGNMENT).max(1) * FILE_ALIGNMENT;
+    let mut data = vec![0; SECTION_OFFSET + raw_size];
+    data[..2].copy_from_slice(b"MZ");
+    data[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+    data[0x40..0x44].copy_from_slice(b"PE\0\0");
+    data[0x44..0x46].copy_from_slice(&0x8664u16.to_le_bytes());
+    data[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+    data[0x54..0x56].copy_from_slice(&0xF0u16.to_le_bytes());
+    let optional = 0x58;
+    data[optional..optional + 2].copy_from_slice(&0x20Bu16.to_le_bytes());
+    data[optional + 24..optional + 32].copy_from_slice(&IMAGE_BASE.to_le_bytes());
+    data[optional + 32..optional + 36].copy_from_slice(&0x1000u32.to_le_bytes());
+    data[optional + 36..optional + 40].copy_from_slice(&(FILE_ALIGNMENT as u32).to_le_bytes());
+    data[optional + 56..optional + 60].copy_from_slice(&0x2000u32.to_le_bytes());
+
+    let header = optional + 0xF0;
+    data[header..header + 5].copy_from_slice(b".data");
+    data[header + 8..header + 12].copy_from_slice(&(virtual_size as u32).to_le_bytes());
+    data[header + 12..header + 16].copy_from_slice(&(SECTION_RVA as u32).to_le_bytes());
+    data[header + 16..header + 20].copy_from_slice(&(raw_size as u32).to_le_bytes());
+    data[header + 20..header + 24].copy_from_slice(&(SECTION_OFFSET as u32).to_le_bytes());
+    data[header + 36..header + 40].copy_from_slice(&0xC000_0040u32.to_le_bytes());
+
+    data[SECTION_OFFSET..SECTION_OFFSET + section.len()].copy_from_slice(section);
+    data
+}
+
+/// The address the byte at `offset` into the section of [`pe`] loads at.
+pub fn va(offset: usize) -> u64 {
+    IMAGE_BASE + SECTION_RVA + offset as u64
+}
+
+/// A metadata file of the given header version and layout with `sections`
+/// placed after the header at their slots. Every other slot is empty.
+pub fn metadata_file(version: i32, pairs: usize, sections: &[(usize, &[u8])]) -> Vec<u8> {
+    let mut data = vec![0; 8 + pairs * 8];
+    data[..4].copy_from_slice(&EXPECTED_MAGIC.to_le_bytes());
+    data[4..8].copy_from_slice(&version.to_le_bytes());
+    for slot in 0..pairs {
+        let bytes = sections
+            .iter()
+            .find(|(s, _)| *s == slot)
+            .map_or(&[][..], |(_, bytes)| *bytes);
+        let at = 8 + slot * 8;
+        let offset = data.len() as i32;
+        data[at..at + 4].copy_from_slice(&offset.to_le_bytes());
+        data[at + 4..at + 8].copy_from_slice(&(bytes.len() as i32).to_le_bytes());
+        data.extend_from_slice(bytes);
+    }
+    data
+}
+
+// Version 29 header slots.
+const STRINGS: usize = 2;
+const EVENTS: usize = 3;
+const PROPERTIES: usize = 4;
+const METHODS: usize = 5;
+const PARAMETER_DEFAULT_VALUES: usize = 6;
+const FIELD_DEFAULT_VALUES: usize = 7;
+const DEFAULT_VALUE_DATA: usize = 8;
+const PARAMETERS: usize = 10;
+const FIELDS: usize = 11;
+const GENERIC_PARAMETERS: usize = 12;
+const GENERIC_PARAMETER_CONSTRAINTS: usize = 13;
+const GENERIC_CONTAINERS: usize = 14;
+const NESTED_TYPES: usize = 15;
+const TYPE_DEFINITIONS: usize = 19;
+const IMAGES: usize = 20;
+const ATTRIBUTE_DATA: usize = 24;
+const ATTRIBUTE_DATA_RANGES: usize = 25;
+const PAIRS: usize = 31;
+
+/// Builds a version 29 file from the parsed records, so tests can describe
+/// types and members the way the rest of the crate sees them. Every record
+/// gets the token of its table and position, and type definition `n` is
+/// type index `n`. All types go in one image, `Test.dll`.
+#[derive(Default)]
+pub struct MetadataBuilder {
+    strings: Vec<u8>,
+    default_value_data: Vec<u8>,
+    attributes: Vec<(u32, Vec<u8>)>,
+    pub type_definitions: Vec<TypeDefinition>,
+    pub methods: Vec<MethodDefinition>,
+    pub parameters: Vec<ParameterDefinition>,
+    pub fields: Vec<FieldDefinition>,
+    pub properties: Vec<PropertyDefinition>,
+    pub events: Vec<EventDefinition>,
+    pub nested_types: Vec<i32>,
+    pub generic_containers: Vec<GenericContainer>,
+    pub generic_parameters: Vec<GenericParameter>,
+    pub constraints: Vec<i32>,
+    pub field_default_values: Vec<FieldDefaultValue>,
+    pub parameter_default_values: Vec<ParameterDefaultValue>,
+}
+
+impl MetadataBuilder {
+    pub fn new() -> Self {
+        let mut builder = Self::default();
+        builder.string("Test.dll");
+        builder
+    }
+
+    pub fn string(&mut self, s: &str) -> i32 {
+        let index = self.strings.len() as i32;
+        self.strings.extend_from_slice(s.as_bytes());
+        self.strings.push(0);
+        index
+    }
+
+    /// A public class.
+    pub fn add_type(&mut self, namespace: &str, name: &str) -> usize {
+        let index = self.type_definitions.len();
+        let type_def = TypeDefinition {
+            name_index: self.string(name),
+            namespace_index: self.string(namespace),
+            custom_attribute_index: -1,
+            byval_type_index: index as i32,
+            byref_type_index: -1,
+            declaring_type_index: -1,
+            parent_index: -1,
+            element_type_index: -1,
+            rgctx_start_index: -1,
+            rgctx_count: 0,
+            generic_container_index: -1,
+            flags: 0x1,
+            field_start: self.fields.len() as i32,
+            method_start: self.methods.len() as i32,
+            event_start: self.events.len() as i32,
+            property_start: self.properties.len() as i32,
+            nested_types_start: self.nested_types.len() as i32,
+            interfaces_start: 0,
+            vtable_start: 0,
+            interface_offsets_start: 0,
+            method_count: 0,
+            property_count: 0,
+            field_count: 0,
+            event_count: 0,
+            nested_type_count: 0,
+            vtable_count: 0,
+            interfaces_count: 0,
+            interface_offsets_count: 0,
+            bitfield: 0,
+            token: 0x0200_0001 + index as u32,
+        };
+        self.type_definitions.push(type_def);
+        index
+    }
+
+    /// A public method of `type_index`, which has to be the last type to
+    /// have had members added.
+    pub fn add_method(&mut self, type_index: usize, name: &str, return_type: i32) -> usize {
+        let index = self.methods.len();
+        let method = MethodDefinition {
+            name_index: self.string(name),
+            declaring_type: type_index as i32,
+            return_type,
+            return_parameter_token: 0,
+            parameter_start: self.parameters.len() as i32,
+            custom_attribute_index: -1,
+            generic_container_index: -1,
+            method_index: -1,
+            invoker_index: -1,
+            delegate_wrapper_index: -1,
+            rgctx_start_index: -1,
+            rgctx_count: 0,
+            token: 0x0600_0001 + index as u32,
+            flags: 0x6,
+            iflags: 0,
+            slot: 0xFFFF,
+            parameter_count: 0,
+        };
+        self.methods.push(method);
+        let type_def = &mut self.type_definitions[type_index];
+        if type_def.method_count == 0 {
+            type_def.method_start = index as i32;
+        }
+        type_def.method_count += 1;
+        index
+    }
+
+    /// The next parameter of `method`, the last method added.
+    pub fn add_parameter(&mut self, method: usize, name: &str, type_index: i32) -> usize {
+        let index = self.parameters.len();
+        let parameter = ParameterDefinition {
+            name_index: self.string(name),
+            token: 0x0800_0001 + index as u32,
+            custom_attribute_index: -1,
+            type_index,
+        };
+        self.parameters.push(parameter);
+        self.methods[method].parameter_count += 1;
+        index
+    }
+
+    /// Makes a type, or a method when `is_method`, generic over `parameters`
+    /// given as names and GenericParameterAttributes. Returns the index of
+    /// the first parameter.
+    pub fn make_generic(
+        &mut self,
+        owner: usize,
+        is_method: bool,
+        parameters: &[(&str, u16)],
+    ) -> usize {
+        let container = self.generic_containers.len() as i32;
+        let start = self.generic_parameters.len();
+        self.generic_containers.push(GenericContainer {
+            owner_index: owner as i32,
+            type_argc: parameters.len() as i32,
+            is_method: is_method as i32,
+            generic_parameter_start: start as i32,
+        });
+        for (num, &(name, flags)) in parameters.iter().enumerate() {
+            let parameter = GenericParameter {
+                owner_index: container,
+                name_index: self.string(name),
+                constraints_start: 0,
+                constraints_count: 0,
+                num: num as u16,
+                flags,
+            };
+            self.generic_parameters.push(parameter);
+        }
+        if is_method {
+            self.methods[owner].generic_container_index = container;
+        } else {
+            self.type_definitions[owner].generic_container_index = container;
+        }
+        start
+    }
+
+    /// Constrains a generic parameter, which has to be the last to have
+    /// had constraints added, to derive from `type_index`.
+    pub fn add_constraint(&mut self, parameter: usize, type_index: i32) {
+        let parameter = &mut self.generic_parameters[parameter];
+        if parameter.constraints_count == 0 {
+            parameter.constraints_start = self.constraints.len() as i16;
+        }
+        parameter.constraints_count += 1;
+        self.constraints.push(type_index);
+    }
+
+    /// Makes `inner` a nested type of `outer`. Nested types are listed
+    /// in the order they are added, so each outer type's have to be added
+    /// together.
+    pub fn nest(&mut self, outer: usize, inner: usize) {
+        if self.type_definitions[outer].nested_type_count == 0 {
+            self.type_definitions[outer].nested_types_start = self.nested_types.len() as i32;
+        }
+        self.type_definitions[outer].nested_type_count += 1;
+        self.type_definitions[inner].declaring_type_index = outer as i32;
+        self.nested_types.push(inner as i32);
+    }
+
+    /// A field of `type_index`, which has to be the last type to have had
+    /// fields added.
+    pub fn add_field(&mut self, type_index: usize, name: &str, type_index_of_field: i32) -> usize {
+        let index = self.fields.len();
+        let field = FieldDefinition {
+            name_index: self.string(name),
+            type_index: type_index_of_field,
+            custom_attribute_index: -1,
+            token: 0x0400_0001 + index as u32,
+        };
+        self.fields.push(field);
+        let type_def = &mut self.type_definitions[type_index];
+        if type_def.field_count == 0 {
+            type_def.field_start = index as i32;
+        }
+        type_def.field_count += 1;
+        index
+    }
+
+    /// A property of `type_index` with the given accessors, by method index.
+    pub fn add_property(
+        &mut self,
+        type_index: usize,
+        name: &str,
+        get: Option<usize>,
+        set: Option<usize>,
+    ) -> usize {
+        let index = self.properties.len();
+        let method_start = self.type_definitions[type_index].method_start as usize;
+        let relative = |method: Option<usize>| method.map_or(-1, |m| (m - method_start) as i32);
+        let property = PropertyDefinition {
+            name_index: self.string(name),
+            get: relative(get),
+            set: relative(set),
+            attrs: 0,
+            custom_attribute_index: -1,
+            token: 0x1700_0001 + index as u32,
+        };
+        self.properties.push(property);
+        let type_def = &mut self.type_definitions[type_index];
+        if type_def.property_count == 0 {
+            type_def.property_start = index as i32;
+        }
+        type_def.property_count += 1;
+        index
+    }
+
+    /// An event of `type_index` with add and remove accessors.
+    pub fn add_event(
+        &mut self,
+        type_index: usize,
+        name: &str,
+        type_index_of_event: i32,
+        add: usize,
+        remove: usize,
+    ) -> usize {
+        let index = self.events.len();
+        let method_start = self.type_definitions[type_index].method_start as usize;
+        let event = EventDefinition {
+            name_index: self.string(name),
+            type_index: type_index_of_event,
+            add: (add - method_start) as i32,
+            remove: (remove - method_start) as i32,
+            raise: -1,
+            custom_attribute_index: -1,
+            token: 0x1400_0001 + index as u32,
+        };
+        self.events.push(event);
+        let type_def = &mut self.type_definitions[type_index];
+        if type_def.event_count == 0 {
+            type_def.event_start = index as i32;
+        }
+        type_def.event_count += 1;
+        index
+    }
+
+    /// A field's constant, encoded the way 29 stores it, or null.
+    pub fn field_default(&mut self, field: usize, type_index: i32, data: Option<&[u8]>) {
+        let data_index = self.default_data(data);
+        self.field_default_values.push(FieldDefaultValue {
+            field_index: field as i32,
+            type_index,
+            data_index,
+        });
+    }
+
+    /// A parameter's default, encoded the way 29 stores it, or null.
+    pub fn parameter_default(&mut self, parameter: usize, type_index: i32, data: Option<&[u8]>) {
+        let data_index = self.default_data(data);
+        self.parameter_default_values.push(ParameterDefaultValue {
+            parameter_index: parameter as i32,
+            type_index,
+            data_index,
+        });
+    }
+
+    fn default_data(&mut self, data: Option<&[u8]>) -> i32 {
+        data.map_or(-1, |data| {
+            let index = self.default_value_data.len() as i32;
+            self.default_value_data.extend_from_slice(data);
+            index
+        })
+    }
+
+    /// The attribute blob of the item with `token`.
+    pub fn attribute(&mut self, token: u32, blob: &[u8]) {
+        self.attributes.push((token, blob.to_vec()));
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        fn table<T>(records: &[T], encode: fn(&T, &mut Vec<u8>)) -> Vec<u8> {
+            let mut out = Vec::new();
+            records.iter().for_each(|record| encode(record, &mut out));
+            out
+        }
+
+        let mut attributes: Vec<_> = self.attributes.iter().collect();
+        attributes.sort_by_key(|(token, _)| *token);
+        let mut ranges = Vec::new();
+        let mut attribute_data = Vec::new();
+        for (token, blob) in &attributes {
+            ranges.extend(words(&[*token as i32, attribute_data.len() as i32]));
+            attribute_data.extend_from_slice(blob);
+        }
+
+        let image = words(&[
+            0,
+            0,
+            0,
+            self.type_definitions.len() as i32,
+            0,
+            0,
+            -1,
+            1,
+            0,
+            attributes.len() as i32,
+        ]);
+        let indices = |values: &[i32]| words(values);
+        metadata_file(
+            29,
+            PAIRS,
+            &[
+                (STRINGS, &self.strings),
+                (EVENTS, &table(&self.events, encode_event)),
+                (PROPERTIES, &table(&self.properties, encode_property)),
+                (METHODS, &table(&self.methods, encode_method)),
+                (
+                    PARAMETER_DEFAULT_VALUES,
+                    &table(&self.parameter_default_values, |v, out| {
+                        out.extend(words(&[v.parameter_index, v.type_index, v.data_index]))
+                    }),
+                ),
+                (
+                    FIELD_DEFAULT_VALUES,
+                    &table(&self.field_default_values, |v, out| {
+                        out.extend(words(&[v.field_index, v.type_index, v.data_index]))
+                    }),
+                ),
+                (DEFAULT_VALUE_DATA, &self.default_value_data),
+                (
+                    PARAMETERS,
+                    &table(&self.parameters, |p, out| {
+                        out.extend(words(&[p.name_index, p.token as i32, p.type_index]))
+                    }),
+                ),
+                (
+                    FIELDS,
+                    &table(&self.fields, |f, out| {
+                        out.extend(words(&[f.name_index, f.type_index, f.token as i32]))
+                    }),
+                ),
+                (
+                    GENERIC_PARAMETERS,
+                    &table(&self.generic_parameters, encode_generic_parameter),
+                ),
+                (GENERIC_PARAMETER_CONSTRAINTS, &indices(&self.constraints)),
+                (
+                    GENERIC_CONTAINERS,
+                    &table(&self.generic_containers, |c, out| {
+                        out.extend(words(&[
+                            c.owner_index,
+                            c.type_argc,
+                            c.is_method,
+                            c.generic_parameter_start,
+                        ]))
+                    }),
+                ),
+                (NESTED_TYPES, &indices(&self.nested_types)),
+                (
+                    TYPE_DEFINITIONS,
+                    &table(&self.type_definitions, encode_type),
+                ),
+                (IMAGES, &image),
+                (ATTRIBUTE_DATA, &attribute_data),
+                (ATTRIBUTE_DATA_RANGES, &ranges),
+            ],
+        )
+    }
+
+    pub fn build(&self) -> GlobalMetadata {
+        GlobalMetadata::parse(&self.bytes()).unwrap()
+    }
+}
+
+fn words(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn halves(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn encode_type(t: &TypeDefinition, out: &mut Vec<u8>) {
+    out.extend(words(&[
+        t.name_index,
+        t.namespace_index,
+        t.byval_type_index,
+        t.declaring_type_index,
+        t.parent_index,
+        t.element_type_index,
+        t.generic_container_index,
+        t.flags as i32,
+        t.field_start,
+        t.method_start,
+        t.event_start,
+        t.property_start,
+        t.nested_types_start,
+        t.interfaces_start,
+        t.vtable_start,
+        t.interface_offsets_start,
+    ]));
+    out.extend(halves(&[
+        t.method_count,
+        t.property_count,
+        t.field_count,
+        t.event_count,
+        t.nested_type_count,
+        t.vtable_count,
+        t.interfaces_count,
+        t.interface_offsets_count,
+    ]));
+    out.extend(words(&[t.bitfield as i32, t.token as i32]));
+}
+
+fn encode_method(m: &MethodDefinition, out: &mut Vec<u8>) {
+    out.extend(words(&[
+        m.name_index,
+        m.declaring_type,
+        m.return_type,
+        m.parameter_start,
+        m.generic_container_index,
+        m.token as i32,
+    ]));
+    out.extend(halves(&[m.flags, m.iflags, m.slot, m.parameter_count]));
+}
+
+fn encode_property(p: &PropertyDefinition, out: &mut Vec<u8>) {
+    out.extend(words(&[
+        p.name_index,
+        p.get,
+        p.set,
+        p.attrs as i32,
+        p.token as i32,
+    ]));
+}
+
+fn encode_event(e: &EventDefinition, out: &mut Vec<u8>) {
+    out.extend(words(&[
+        e.name_index,
+        e.type_index,
+        e.add,
+        e.remove,
+        e.raise,
+        e.token as i32,
+    ]));
+}
+
+fn encode_generic_parameter(p: &GenericParameter, out: &mut Vec<u8>) {
+    out.extend(words(&[p.owner_index, p.name_index]));
+    out.extend(halves(&[
+        p.constraints_start as u16,
+        p.constraints_count as u16,
+        p.num,
+        p.flags,
+    ]));
+}
+
+/// A registration holding only the Il2CppType table.
+pub fn registration(types: Vec<Il2CppType>) -> Registration {
+    Registration {
+        pointer_size: 8,
+        types,
+        generic_insts: Vec::new(),
+        method_specs: Vec::new(),
+        usages: Vec::new(),
+        method_pointers: HashMap::new(),
+    }
+}
+
+/// An Il2CppType of `kind` with no attrs, by value.
+pub fn il2cpp_type(kind: TypeKind) -> Il2CppType {
+    Il2CppType {
+        kind,
+        attrs: 0,
+        byref: false,
+    }
+}
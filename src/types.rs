@@ -0,0 +1,330 @@
+use crate::csharp::{self, TypeResolver};
+use crate::metadata::{ElementType, GlobalMetadata};
+use crate::registration::{CodeAddress, Registration};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+const PARAM_ATTRIBUTE_IN: u16 = 0x0001;
+const PARAM_ATTRIBUTE_OUT: u16 = 0x0002;
+
+const SYSTEM_TYPES: [(&str, ElementType); 18] = [
+    ("Void", ElementType::Void),
+    ("Boolean", ElementType::Boolean),
+    ("Char", ElementType::Char),
+    ("SByte", ElementType::I1),
+    ("Byte", ElementType::U1),
+    ("Int16", ElementType::I2),
+    ("UInt16", ElementType::U2),
+    ("Int32", ElementType::I4),
+    ("UInt32", ElementType::U4),
+    ("Int64", ElementType::I8),
+    ("UInt64", ElementType::U8),
+    ("Single", ElementType::R4),
+    ("Double", ElementType::R8),
+    ("String", ElementType::String),
+    ("Object", ElementType::Object),
+    ("IntPtr", ElementType::I),
+    ("UIntPtr", ElementType::U),
+    ("TypedReference", ElementType::TypedByRef),
+];
+
+/// A type as the runtime describes it.
+#[derive(Clone)]
+pub struct Il2CppType {
+    /// What the type is.
+    pub kind: TypeKind,
+    /// The field or parameter attributes of its use.
+    pub attrs: u16,
+    /// Whether it is passed by reference.
+    pub byref: bool,
+}
+
+/// The shapes of an `Il2CppType`.
+#[derive(Clone)]
+pub enum TypeKind {
+    /// A built-in type.
+    Primitive(ElementType),
+    /// A class or value type, by type definition index.
+    Definition(usize),
+    /// A generic type definition, by index, with its type arguments.
+    GenericInstance(usize, Vec<Il2CppType>),
+    /// A multi-dimensional array and its rank.
+    Array(Box<Il2CppType>, u8),
+    /// A single-dimensional, zero-based array.
+    SzArray(Box<Il2CppType>),
+    /// An unmanaged pointer.
+    Pointer(Box<Il2CppType>),
+    /// VAR and MVAR, by generic parameter index.
+    GenericParameter(usize),
+    /// An element type tag this tool does not decode.
+    Unknown(u8),
+}
+
+/// Resolves type indices from the metadata where it can: every type
+/// definition records the index of its own by-value (and before 27, by-ref)
+/// type. Everything else, such as arrays, generic instances and generic
+/// parameters, needs the Il2CppType table from the binary.
+pub struct TypeTable {
+    definitions: HashMap<i32, (usize, bool)>,
+    registration: Option<Registration>,
+}
+
+impl TypeTable {
+    /// The type definitions' own types only.
+    pub fn from_metadata(metadata: &GlobalMetadata) -> Self {
+        let mut definitions = HashMap::new();
+        for (index, type_def) in metadata.type_definitions.iter().enumerate() {
+            if type_def.byref_type_index >= 0 {
+                definitions.insert(type_def.byref_type_index, (index, true));
+            }
+            if type_def.byval_type_index >= 0 {
+                definitions.insert(type_def.byval_type_index, (index, false));
+            }
+        }
+        Self {
+            definitions,
+            registration: None,
+        }
+    }
+
+    /// Adds the Il2CppType table of the binary the metadata belongs to,
+    /// located through its MetadataRegistration.
+    pub fn from_binary(metadata: &GlobalMetadata, data: &[u8]) -> Result<Self, String> {
+        Ok(Self::from_registration(
+            metadata,
+            Registration::find(metadata, data)?,
+        ))
+    }
+
+    pub(crate) fn from_registration(metadata: &GlobalMetadata, registration: Registration) -> Self {
+        let mut table = Self::from_metadata(metadata);
+        table.registration = Some(registration);
+        table
+    }
+
+    /// The binary's registration, when added.
+    pub fn registration(&self) -> Option<&Registration> {
+        self.registration.as_ref()
+    }
+
+    fn types(&self) -> &[Il2CppType] {
+        self.registration
+            .as_ref()
+            .map(|r| r.types.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// How many types the binary's table holds.
+    pub fn binary_type_count(&self) -> usize {
+        self.types().len()
+    }
+
+    /// The type at `type_index`, if it is known.
+    pub fn resolve(&self, type_index: i32) -> Option<Cow<'_, Il2CppType>> {
+        if let Some(&(index, byref)) = self.definitions.get(&type_index) {
+            return Some(Cow::Owned(Il2CppType {
+                kind: TypeKind::Definition(index),
+                attrs: 0,
+                byref,
+            }));
+        }
+        usize::try_from(type_index)
+            .ok()
+            .and_then(|index| self.types().get(index))
+            .map(Cow::Borrowed)
+    }
+
+    /// The C# name of `ty`.
+    pub fn name(&self, metadata: &GlobalMetadata, ty: &Il2CppType) -> String {
+        match &ty.kind {
+            TypeKind::Primitive(element) => csharp::keyword(*element).unwrap_or("").to_string(),
+            TypeKind::Definition(index) => match system_type(metadata, *index) {
+                Some(element) => csharp::keyword(element).unwrap_or("").to_string(),
+                None => csharp::full_type_name(metadata, *index),
+            },
+            TypeKind::GenericInstance(index, arguments) => {
+                let arguments: Vec<String> =
+                    arguments.iter().map(|a| self.name(metadata, a)).collect();
+                csharp::generic_instance_name(metadata, *index, &arguments)
+            }
+            TypeKind::Array(element, rank) => format!(
+                "{}[{}]",
+                self.name(metadata, element),
+                ",".repeat(rank.saturating_sub(1) as usize)
+            ),
+            TypeKind::SzArray(element) => format!("{}[]", self.name(metadata, element)),
+            TypeKind::Pointer(element) => format!("{}*", self.name(metadata, element)),
+            TypeKind::GenericParameter(index) => metadata
+                .generic_parameters
+                .get(*index)
+                .and_then(|p| metadata.generic_parameter_name(p))
+                .unwrap_or("T")
+                .to_string(),
+            TypeKind::Unknown(element) => format!("Il2CppType0x{:02X}", element),
+        }
+    }
+
+    fn element_type(
+        &self,
+        metadata: &GlobalMetadata,
+        type_index: i32,
+        enums: bool,
+    ) -> Option<ElementType> {
+        let ty = self.resolve(type_index)?;
+        match ty.kind {
+            TypeKind::Primitive(element) => Some(element),
+            TypeKind::Definition(index) => {
+                if let Some(element) = system_type(metadata, index) {
+                    return Some(element);
+                }
+                let type_def = &metadata.type_definitions[index];
+                // An enum's element type is its underlying integer type.
+                (enums && type_def.is_enum())
+                    .then(|| self.element_type(metadata, type_def.element_type_index, false))
+                    .flatten()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TypeResolver for TypeTable {
+    fn type_name(&self, metadata: &GlobalMetadata, type_index: i32) -> String {
+        match self.resolve(type_index) {
+            Some(ty) => self.name(metadata, &ty),
+            None => format!("Type{}", type_index),
+        }
+    }
+
+    fn constant_type(&self, metadata: &GlobalMetadata, type_index: i32) -> Option<ElementType> {
+        self.element_type(metadata, type_index, true)
+            .filter(|&element| {
+                (ElementType::Boolean as u8..=ElementType::String as u8).contains(&(element as u8))
+            })
+    }
+
+    fn parameter_modifier(&self, _metadata: &GlobalMetadata, type_index: i32) -> &'static str {
+        match self.resolve(type_index) {
+            Some(ty) if ty.byref => match ty.attrs & (PARAM_ATTRIBUTE_IN | PARAM_ATTRIBUTE_OUT) {
+                PARAM_ATTRIBUTE_OUT => "out ",
+                PARAM_ATTRIBUTE_IN => "in ",
+                _ => "ref ",
+            },
+            _ => "",
+        }
+    }
+
+    // Only the binary's types carry attrs; the metadata alone has none.
+    fn type_attrs(&self, _metadata: &GlobalMetadata, type_index: i32) -> Option<u16> {
+        usize::try_from(type_index)
+            .ok()
+            .and_then(|index| self.types().get(index))
+            .map(|ty| ty.attrs)
+    }
+
+    fn method_address(&self, method_index: usize) -> Option<CodeAddress> {
+        self.registration()?
+            .method_pointers
+            .get(&method_index)
+            .copied()
+    }
+}
+
+/// The built-in type a System type definition stands for, so metadata-only
+/// names read `int` rather than `System.Int32`.
+pub fn system_type(metadata: &GlobalMetadata, index: usize) -> Option<ElementType> {
+    let type_def = metadata.type_definitions.get(index)?;
+    if metadata.get_string(type_def.namespace_index)? != "System"
+        || metadata.declaring_type(index).is_some()
+    {
+        return None;
+    }
+    let name = metadata.type_name(type_def)?;
+    SYSTEM_TYPES
+        .iter()
+        .find(|(system, _)| *system == name)
+        .map(|&(_, element)| element)
+}
+
+/// `Int32` for the built-in type a System type definition stands for.
+pub fn system_name(element: ElementType) -> Option<&'static str> {
+    SYSTEM_TYPES
+        .iter()
+        .find(|&&(_, system)| system == element)
+        .map(|&(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MetadataBuilder, il2cpp_type, registration};
+
+    fn boxed(kind: TypeKind) -> Box<Il2CppType> {
+        Box::new(il2cpp_type(kind))
+    }
+
+    #[test]
+    fn type_indices_resolve_to_csharp_names() {
+        let mut b = MetadataBuilder::new();
+        b.add_type("System", "Int32");
+        let player = b.add_type("Game", "Player");
+        let dictionary = b.add_type("System.Collections.Generic", "Dictionary`2");
+        b.make_generic(dictionary, false, &[("TKey", 0), ("TValue", 0)]);
+        let metadata = b.build();
+
+        let player_type = il2cpp_type(TypeKind::Definition(player));
+        let inner = il2cpp_type(TypeKind::GenericInstance(
+            dictionary,
+            vec![
+                il2cpp_type(TypeKind::Primitive(ElementType::String)),
+                il2cpp_type(TypeKind::SzArray(Box::new(player_type.clone()))),
+            ],
+        ));
+        let types = registration(vec![
+            il2cpp_type(TypeKind::Definition(0)),
+            player_type.clone(),
+            il2cpp_type(TypeKind::Definition(dictionary)),
+            il2cpp_type(TypeKind::Array(boxed(TypeKind::Definition(0)), 3)),
+            il2cpp_type(TypeKind::Pointer(boxed(TypeKind::Primitive(
+                ElementType::I4,
+            )))),
+            il2cpp_type(TypeKind::GenericInstance(
+                dictionary,
+                vec![il2cpp_type(TypeKind::GenericParameter(1)), inner],
+            )),
+            il2cpp_type(TypeKind::GenericParameter(0)),
+            il2cpp_type(TypeKind::Unknown(0x41)),
+            Il2CppType {
+                byref: true,
+                ..player_type
+            },
+        ]);
+        let types = TypeTable::from_registration(&metadata, types);
+
+        let names: Vec<_> = (0..10)
+            .map(|i| {
+                format!(
+                    "{}{}",
+                    types.parameter_modifier(&metadata, i),
+                    types.type_name(&metadata, i)
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "int",
+                "Game.Player",
+                "System.Collections.Generic.Dictionary<TKey, TValue>",
+                "int[,,]",
+                "int*",
+                "System.Collections.Generic.Dictionary<TValue, \
+                 System.Collections.Generic.Dictionary<string, Game.Player[]>>",
+                "TKey",
+                "Il2CppType0x41",
+                "ref Game.Player",
+                "Type9",
+            ]
+        );
+    }
+}
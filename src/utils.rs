@@ -1,3 +1,4 @@
+/// A byte count, with the size in KB, MB or GB from 1 KB up.
 pub fn fmt_size(bytes: usize) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;
@@ -15,6 +16,7 @@ pub fn fmt_size(bytes: usize) -> String {
     }
 }
 
+/// Bytes as space-separated hex pairs.
 pub fn fmt_bytes_hex(bytes: &[u8]) -> String {
     bytes
         .iter()
@@ -22,3 +24,136 @@ pub fn fmt_bytes_hex(bytes: &[u8]) -> String {
         .collect::<Vec<_>>()
         .join(" ")
 }
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset.saturating_add(2))
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| format!("Read out of bounds: offset {} + 2 > {}", offset, data.len()))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset.saturating_add(4))
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| format!("Read out of bounds: offset {} + 4 > {}", offset, data.len()))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Result<u64, String> {
+    data.get(offset..offset.saturating_add(8))
+        .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+        .ok_or_else(|| format!("Read out of bounds: offset {} + 8 > {}", offset, data.len()))
+}
+
+pub(crate) fn find_pattern(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len())
+        .position(|window| window == pattern)
+}
+
+pub(crate) fn read_u16_be(data: &[u8], offset: usize) -> Result<u16, String> {
+    read_u16(data, offset).map(u16::swap_bytes)
+}
+
+pub(crate) fn read_u32_be(data: &[u8], offset: usize) -> Result<u32, String> {
+    read_u32(data, offset).map(u32::swap_bytes)
+}
+
+pub(crate) fn read_u64_be(data: &[u8], offset: usize) -> Result<u64, String> {
+    read_u64(data, offset).map(u64::swap_bytes)
+}
+
+/// XXTEA keys are 128 bits; shorter keys are zero padded by the cipher, longer
+/// ones are rejected rather than silently truncated.
+pub const MAX_KEY_LEN: usize = 16;
+
+/// Parses a --key value: ASCII text, or hex with a 0x prefix.
+pub fn parse_key(value: &str) -> Result<Vec<u8>, String> {
+    let key = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => parse_hex(hex)?,
+        None => value.as_bytes().to_vec(),
+    };
+    if key.is_empty() {
+        return Err("Key is empty".to_string());
+    }
+    if key.len() > MAX_KEY_LEN {
+        return Err(format!(
+            "Key is {} bytes, at most {} are supported",
+            key.len(),
+            MAX_KEY_LEN
+        ));
+    }
+    Ok(key)
+}
+
+pub(crate) fn parse_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = hex
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':')
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("Odd number of hex digits in \"{}\"", hex));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("Invalid hex digits in \"{}\"", hex))
+        })
+        .collect()
+}
+
+/// A key file holds either the key as text (same syntax as --key) or the raw
+/// key bytes.
+pub fn read_key_file<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<u8>, String> {
+    let raw = std::fs::read(path.as_ref())
+        .map_err(|e| format!("Failed to read key file {}: {}", path.as_ref().display(), e))?;
+    match std::str::from_utf8(&raw) {
+        Ok(text) if !text.trim().is_empty() => parse_key(text.trim()),
+        _ if !raw.is_empty() && raw.len() <= MAX_KEY_LEN => Ok(raw),
+        _ => Err("Key file must hold 1 to 16 raw bytes or a text key".to_string()),
+    }
+}
+
+/// Renders a key in the syntax --key accepts: ASCII when every byte is
+/// printable, 0x-prefixed hex otherwise.
+pub fn format_key(key: &[u8]) -> String {
+    let printable = !key.is_empty()
+        && key.iter().all(|b| b.is_ascii_graphic() || *b == b' ')
+        && !key.starts_with(b"0x")
+        && !key.starts_with(b"0X");
+    if printable {
+        String::from_utf8_lossy(key).into_owned()
+    } else {
+        let hex: String = key.iter().map(|b| format!("{:02X}", b)).collect();
+        format!("0x{}", hex)
+    }
+}
+
+pub(crate) fn write_u32(data: &mut [u8], offset: usize, value: u32) -> Result<(), String> {
+    let len = data.len();
+    data.get_mut(offset..offset.saturating_add(4))
+        .map(|b| b.copy_from_slice(&value.to_le_bytes()))
+        .ok_or_else(|| format!("Write out of bounds: offset {} + 4 > {}", offset, len))
+}
+
+/// A JSON string literal, quotes included.
+pub fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}